@@ -7,17 +7,178 @@ pub enum Mode {
     InkToEvm,
 }
 
+/// Version of ink! metadata read in `ink-to-evm` mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum MetadataVersion {
+    /// Produced by `cargo-contract` 1.x
+    V3,
+
+    /// Produced by `cargo-contract` 2.x and 3.x
+    V4,
+
+    /// Produced by `cargo-contract` 4.x
+    V5,
+}
+
+/// What kind of code to generate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Emit {
+    /// Bindings calling the original contract through XVM
+    Bindings,
+
+    /// ink! contract skeleton mirroring the EVM interface with matching selectors,
+    /// to be used as a starting point of a native reimplementation
+    Skeleton,
+
+    /// Only Rust types of ABI tuples and events, without call machinery
+    Types,
+
+    /// Solidity interface of the ink! contract, without call machinery, in ink-to-evm mode
+    Interface,
+}
+
+/// Rust representation of the Solidity `address` type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum AddressType {
+    /// Custom `H160` wrapper emitted into the module
+    H160,
+
+    /// Substrate `AccountId`, mapped to `H160` when encoding the call
+    AccountId,
+
+    /// Plain `[u8; 20]` byte array
+    Bytes20,
+}
+
+/// How `AccountId` arguments are mapped to EVM addresses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum AddressMapping {
+    /// First 20 bytes of the account
+    Truncate,
+
+    /// Last 20 bytes of the Keccak256 hash of the account
+    Keccak,
+}
+
+/// Where generated functions take the address of the EVM contract from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum AddressMode {
+    /// Single contract given to the constructor
+    Static,
+
+    /// Every function takes the address of the contract to call, e.g. for contracts
+    /// deployed by a factory
+    Dynamic,
+}
+
+/// How generated functions are attached to the contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EmitStyle {
+    /// Messages are defined in an inherent `impl` block
+    Inherent,
+
+    /// Messages implement a trait definition emitted alongside the module
+    TraitImpl,
+}
+
+/// How generated functions are exposed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum WrapperStyle {
+    /// `#[ink(message)]` entries of a standalone ink! contract
+    Message,
+
+    /// Plain methods of a helper struct taking the caller's environment,
+    /// to be embedded into a larger ink! contract
+    Helper,
+}
+
+/// How SCALE codec is implemented for generated structs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Codec {
+    /// `#[derive(Encode, Decode)]`
+    Derive,
+
+    /// Explicit `impl Encode` and `impl Decode` blocks encoding fields one by one
+    Manual,
+}
+
+/// How generated functions report the outcome of XVM calls
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ErrorStyle {
+    /// `Result<(), XvmError>` suitable for `?` propagation
+    Result,
+
+    /// Plain `bool` telling whether the call went through
+    Panic,
+}
+
+/// How generated ink! code reaches XVM
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Target {
+    /// Chain extension shipped with `xvm_environment`
+    Precompile,
+
+    /// `#[ink::chain_extension]` declared by the bindings themselves
+    ChainExtension,
+}
+
+/// XVM interface of the target chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum XvmVersion {
+    /// Legacy context with a single compact weight
+    V1,
+
+    /// Context with WeightsV2 `ref_time` and `proof_size` limits
+    V2,
+
+    /// Structured call context and detailed call outcome
+    V3,
+}
+
+/// Shape of the Solidity code calling an ink! contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SolidityShape {
+    /// Contract holding the address of the ink! contract, given to its constructor
+    Contract,
+
+    /// Library of internal functions taking the address of the ink! contract first
+    Library,
+}
+
+/// Version requirement of `pragma solidity`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolidityVersion {
+    pub requirement: String,
+
+    /// Lowest compiler version the requirement admits, as major, minor and patch
+    pub minimum: (u32, u32, u32),
+}
+
 #[derive(Parser, Debug)]
 pub struct Args {
-    /// Input filename or stdin if empty
+    /// Input filename or stdin if empty. May be repeated in `ink-to-evm` mode, where
+    /// directories stand for all `.json` and `.contract` files they contain
     #[arg(long, short)]
-    pub input: Option<PathBuf>,
+    pub input: Vec<PathBuf>,
 
     /// Output filename or stdout if empty
     #[arg(long, short)]
     pub output: Option<PathBuf>,
 
-    /// Ink module name to generate
+    /// Directory to write one module per contract into, along with `mod.rs`, or the Solidity
+    /// file in ink-to-evm mode
+    #[arg(long, conflicts_with = "output")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Pretty print generated Rust code
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    pub format: bool,
+
+    /// Write type definitions and call wrappers of each module into separate files
+    #[arg(long, requires = "output_dir")]
+    pub split: bool,
+
+    /// Name of the ink! module to generate, or of the Solidity contract in ink-to-evm mode
     #[arg(long)]
     pub module_name: Option<String>,
 
@@ -25,6 +186,264 @@ pub struct Args {
     #[arg(long, short, default_value = "0x0F")]
     pub evm_id: String,
 
+    /// XVM interface of the target chain
+    #[arg(long, default_value = "v2")]
+    pub xvm_version: XvmVersion,
+
+    /// Address of the XVM precompile called by generated Solidity code
+    #[arg(
+        long,
+        default_value = "0x0000000000000000000000000000000000005005",
+        value_parser = parse_xvm_address
+    )]
+    pub xvm_address: [u8; 20],
+
+    /// Make the constructor of generated Solidity contracts take the address of the XVM
+    /// precompile, zero meaning `--xvm-address`
+    #[arg(long)]
+    pub xvm_address_arg: bool,
+
+    /// Weight limit of XVM calls compiled into the generated code, zero meaning unlimited
+    #[arg(
+        long,
+        default_value = "0,0",
+        value_name = "REF_TIME,PROOF_SIZE",
+        value_parser = parse_weight_limit
+    )]
+    pub weight_limit: (u64, u64),
+
+    /// Make every generated function take the weight limit of its XVM call, a `WeightLimit`
+    /// in ink! or trailing `ref_time` and `proof_size` in Solidity
+    #[arg(long)]
+    pub weight_args: bool,
+
+    /// Make every generated function take an optional storage deposit limit of its XVM call
+    #[arg(long)]
+    pub storage_deposit_args: bool,
+
+    /// Make every generated function take an optional EVM ID overriding `DEFAULT_EVM_ID`
+    #[arg(long)]
+    pub evm_id_args: bool,
+
+    /// How generated ink! code reaches XVM
+    #[arg(long, default_value = "precompile")]
+    pub target: Target,
+
+    /// Chain extension function id of XVM calls made by generated ink! code
+    #[arg(long, default_value = "0x00010001", value_parser = parse_chain_extension_id)]
+    pub chain_extension_id: u32,
+
     #[arg(long, short, default_value = "evm-to-ink")]
     pub mode: Mode,
+
+    /// Version of ink! metadata input, detected if empty
+    #[arg(long)]
+    pub metadata_version: Option<MetadataVersion>,
+
+    /// Only warn about ink! metadata of unsupported languages or ink! versions
+    #[arg(long)]
+    pub allow_unsupported_source: bool,
+
+    /// Compiler version `pragma solidity` of generated Solidity code requires
+    #[arg(long, default_value = "^0.8.13", value_parser = parse_solidity_version)]
+    pub solidity_version: SolidityVersion,
+
+    /// Shape of generated Solidity code
+    #[arg(long, default_value = "contract")]
+    pub solidity_shape: SolidityShape,
+
+    /// What kind of code to generate
+    #[arg(long, default_value = "bindings")]
+    pub emit: Emit,
+
+    /// Rust type used for Solidity `address` values
+    #[arg(long, default_value = "h160")]
+    pub address_type: AddressType,
+
+    /// How `AccountId` addresses are mapped to `H160`, see `--address-type account-id`
+    #[arg(long, default_value = "truncate")]
+    pub address_mapping: AddressMapping,
+
+    /// Where generated functions take the address of the EVM contract from
+    #[arg(long, default_value = "static")]
+    pub address_mode: AddressMode,
+
+    /// Also generate an off-chain mock of the module for unit tests
+    #[arg(long)]
+    pub with_mock: bool,
+
+    /// Also generate an `ink_e2e` test of the module, requires `--output-dir`
+    #[arg(long)]
+    pub with_e2e: bool,
+
+    /// Also generate a Foundry test of the Solidity code in ink-to-evm mode, requires `--output-dir`
+    #[arg(long)]
+    pub with_tests: bool,
+
+    /// Also write the ABI JSON of the Solidity contract to the given path in ink-to-evm mode,
+    /// relative to `--output-dir`, or to the directory of `--output`
+    #[arg(long, value_name = "PATH")]
+    pub emit_abi: Option<PathBuf>,
+
+    /// Trace every XVM call when the generated code is built with `sumi-trace` feature
+    #[arg(long)]
+    pub with_tracing: bool,
+
+    /// Omit tool version and source ABI hash from the header comment
+    #[arg(long)]
+    pub no_banner: bool,
+
+    /// SPDX license expression of generated code, leading every generated file
+    #[arg(long, default_value = "UNLICENSED", value_parser = parse_license)]
+    pub license: String,
+
+    /// Attribute to emit at the top of the generated module, may be repeated
+    #[arg(long)]
+    pub module_attr: Vec<String>,
+
+    /// Do not emit the default set of lint allows
+    #[arg(long)]
+    pub no_default_attrs: bool,
+
+    /// How generated functions are attached to the contract
+    #[arg(long, default_value = "inherent")]
+    pub emit_style: EmitStyle,
+
+    /// How generated functions are exposed, as messages or helper methods
+    #[arg(long, default_value = "message")]
+    pub wrapper_style: WrapperStyle,
+
+    /// Name of the emitted trait, `<Module>Like` if empty
+    #[arg(long)]
+    pub trait_name: Option<String>,
+
+    /// Prefix of generated type and constant names, to avoid collisions with user code
+    #[arg(long, default_value = "")]
+    pub type_prefix: String,
+
+    /// How SCALE codec is implemented for generated structs
+    #[arg(long, default_value = "derive")]
+    pub codec: Codec,
+
+    /// How generated functions report the outcome of XVM calls
+    #[arg(long, default_value = "panic")]
+    pub error_style: ErrorStyle,
+
+    /// Also generate a `#[repr(u32)]` enum of all function selectors
+    #[arg(long)]
+    pub selector_enum: bool,
+
+    /// Fail on functions that cannot be translated instead of emitting `todo!()` stubs
+    #[arg(long)]
+    pub strict: bool,
+
+    /// TOML or JSON file mapping Solidity signatures to Rust function names
+    #[arg(long)]
+    pub rename: Option<PathBuf>,
+}
+
+/// Parses a hex encoded 20 byte address, `0x` prefix is optional
+fn parse_xvm_address(address: &str) -> Result<[u8; 20], String> {
+    let digits = address.strip_prefix("0x").unwrap_or(address);
+    let bytes = hex::decode(digits).map_err(|e| format!("`{address}` is not hex: {e}"))?;
+
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| format!("expected 20 bytes, got {}", bytes.len()))
+}
+
+/// Parses a version requirement of `pragma solidity`, like `0.8.20`, `^0.8.0` or
+/// `>=0.8.4 <0.9.0`
+fn parse_solidity_version(requirement: &str) -> Result<SolidityVersion, String> {
+    let invalid =
+        || format!("expected a version like `^0.8.0` or `>=0.8.4 <0.9.0`, got `{requirement}`");
+
+    let mut minimum = (0, 0, 0);
+    for comparator in requirement.split_whitespace() {
+        let operators = [">=", "<=", "^", "~", ">", "<", "="];
+        let (operator, version) = operators
+            .iter()
+            .find_map(|operator| Some((*operator, comparator.strip_prefix(operator)?)))
+            .unwrap_or(("", comparator));
+
+        let parts: Vec<u32> = version
+            .split('.')
+            .map(|part| part.parse().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+        let version = match parts[..] {
+            [major, minor, patch] => (major, minor, patch),
+            _ => return Err(invalid()),
+        };
+
+        if !operator.starts_with('<') {
+            minimum = minimum.max(version);
+        }
+    }
+
+    match requirement.trim().is_empty() {
+        true => Err(invalid()),
+        false => Ok(SolidityVersion {
+            requirement: requirement.trim().to_owned(),
+            minimum,
+        }),
+    }
+}
+
+/// Parses an SPDX license expression, like `MIT`, `GPL-3.0-or-later` or
+/// `(Apache-2.0 OR MIT) AND LicenseRef-Custom`
+fn parse_license(license: &str) -> Result<String, String> {
+    let invalid = || format!("`{license}` is not an SPDX license expression");
+    let identifier = |token: &str| {
+        !token.is_empty()
+            && !["AND", "OR", "WITH"].contains(&token)
+            && token
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+    };
+
+    // Alternates between licenses, optionally followed by `+` and `WITH` an exception, and
+    // operators joining them, with parentheses around any part
+    let spaced = license.replace('(', " ( ").replace(')', " ) ");
+    let (mut operand, mut exception, mut depth) = (true, false, 0);
+    for token in spaced.split_whitespace() {
+        match token {
+            _ if exception && !identifier(token) => return Err(invalid()),
+            _ if exception => exception = false,
+            "(" if operand => depth += 1,
+            ")" if !operand && depth > 0 => depth -= 1,
+            "AND" | "OR" if !operand => operand = true,
+            "WITH" if !operand => exception = true,
+            _ if operand && identifier(token.strip_suffix('+').unwrap_or(token)) => operand = false,
+            _ => return Err(invalid()),
+        }
+    }
+
+    match !operand && !exception && depth == 0 {
+        true => Ok(license.trim().to_owned()),
+        false => Err(invalid()),
+    }
+}
+
+/// Parses a weight limit given as `ref_time,proof_size`
+fn parse_weight_limit(limit: &str) -> Result<(u64, u64), String> {
+    let (ref_time, proof_size) = limit
+        .split_once(',')
+        .ok_or_else(|| format!("expected `ref_time,proof_size`, got `{limit}`"))?;
+
+    let parse = |part: &str| {
+        part.trim()
+            .parse::<u64>()
+            .map_err(|e| format!("`{part}` is not a weight: {e}"))
+    };
+
+    Ok((parse(ref_time)?, parse(proof_size)?))
+}
+
+/// Parses a `0x` prefixed hex or decimal chain extension function id
+fn parse_chain_extension_id(id: &str) -> Result<u32, String> {
+    match id.strip_prefix("0x") {
+        Some(digits) => u32::from_str_radix(digits, 16),
+        None => id.parse(),
+    }
+    .map_err(|e| format!("`{id}` is not a 32 bit id: {e}"))
 }
@@ -8,6 +8,51 @@ pub enum Error {
     #[error("unable to create output file {path}: {inner}")]
     WriteOutput { path: PathBuf, inner: io::Error },
 
+    #[error("module name is required for plain ABI input, use --module-name")]
+    ModuleNameRequired,
+
+    #[error("input contains {0} contracts, use --output-dir to generate a module for each")]
+    MultipleContracts(usize),
+
+    #[error("incompatible options: {0}")]
+    InvalidOptions(String),
+
+    #[error("unable to translate `{signature}`: {reason}")]
+    Unsupported { signature: String, reason: String },
+
+    #[error("unsupported type `{path}` in {context}: {reason}")]
+    UnsupportedType {
+        path: String,
+        context: String,
+        reason: String,
+    },
+
+    #[error("recursive type `{path}` has no Solidity counterpart")]
+    RecursiveType { path: String },
+
+    #[error(
+        "metadata of `{language}` built with `{compiler}` is not supported, {reason}, \
+         use --allow-unsupported-source to translate it anyway"
+    )]
+    UnsupportedSource {
+        language: String,
+        compiler: String,
+        reason: String,
+    },
+
+    #[error("unknown TypeDef `{def}` of type {id}, only those of scale-info 2 are supported")]
+    UnknownTypeDef { def: String, id: u64 },
+
+    #[error("`{first}` and `{second}` share selector 0x{selector}")]
+    SelectorCollision {
+        first: String,
+        second: String,
+        selector: String,
+    },
+
+    #[error("invalid rename map: {0}")]
+    Rename(String),
+
     #[error(transparent)]
     Clap(#[from] clap::Error),
 
@@ -1,11 +1,24 @@
 use ink_metadata::InkProject;
 use itertools::Itertools;
-use scale_info::{form::PortableForm, Path, Type, TypeDef, TypeDefPrimitive};
+use parity_scale_codec::{Compact, Encode};
+use scale_info::{form::PortableForm, Type, TypeDef, TypeDefPrimitive};
 use serde::Serialize;
-use std::{cell::RefCell, collections::HashMap, io::Read, rc::Rc};
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    io::Read,
+    path::PathBuf,
+    rc::Rc,
+};
 use tinytemplate::{error::Error::GenericError, TinyTemplate};
 
-use crate::error::Error;
+use crate::{
+    cli::{Args, Emit, MetadataVersion, SolidityShape, SolidityVersion, XvmVersion},
+    error::Error,
+    sol2ink::xvm_context,
+};
 
 #[derive(Debug, PartialEq, Eq, Clone, Default)]
 pub struct EvmType {
@@ -23,20 +36,70 @@ pub struct EvmType {
 
     /// How the type should be encoded to Scale format
     encoder: Option<String>,
+
+    /// How the type should be decoded from Scale format
+    decoder: Option<String>,
+
+    /// Identifier of the type in encoder and decoder names, derived from
+    /// `reference` if absent
+    name: Option<String>,
+
+    /// Environment type it stands for, like `Balance`
+    role: Option<String>,
+
+    /// ABI JSON parameter of the type without a name, like `{ "type": "uint32" }`,
+    /// see [`abi_param`]
+    abi: Value,
+}
+
+impl EvmType {
+    /// Identifier of the type in encoder and decoder names
+    fn name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => identifier(&self.reference),
+        }
+    }
+
+    /// Type along with its data location, as variables are declared, e.g. `Point memory`
+    fn declaration(&self) -> String {
+        match &self.modifier {
+            Some(modifier) => format!("{} {modifier}", self.reference),
+            None => self.reference.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct EvmTypeRegistry {
     mapping: HashMap<u32, EvmType>,
+
+    /// Ids of the types each converted type refers to
+    dependencies: HashMap<u32, Vec<u32>>,
+
+    /// Ids of types used by the contract interface in order of first use, see [`Self::use_type`]
+    used: Vec<u32>,
+
+    /// Ids of types being converted, to stop at recursive ones
+    converting: Vec<u32>,
 }
 
 struct Context<'template> {
     project: Rc<InkProject>,
     templates: TinyTemplate<'template>,
+
+    /// Environment types by registry id, see [`environment_roles`]
+    roles: HashMap<u32, &'static str>,
+
+    /// Paths of generic types instantiated with different parameters, like `Option`
+    shared_paths: HashSet<Vec<String>>,
+
+    /// Whether the target compiler has custom errors, see [`check_solidity_version`]
+    custom_errors: bool,
 }
 
 impl<'template> Context<'template> {
-    fn new(project: Rc<InkProject>) -> Self {
+    fn new(project: Rc<InkProject>, roles: HashMap<u32, &'static str>) -> Self {
         let mut templates = TinyTemplate::new();
         templates.set_default_formatter(&tinytemplate::format_unescaped);
         templates
@@ -48,11 +111,299 @@ impl<'template> Context<'template> {
         templates
             .add_template("encoder", include_str!("../templates/solidity-encoder.txt"))
             .unwrap();
+        templates
+            .add_template(
+                "data-enum",
+                include_str!("../templates/solidity-data-enum.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "data-enum-encoder",
+                include_str!("../templates/solidity-data-enum-encoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template("decoder", include_str!("../templates/solidity-decoder.txt"))
+            .unwrap();
+        templates
+            .add_template(
+                "primitive-encoder",
+                include_str!("../templates/solidity-primitive-encoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "account-id",
+                include_str!("../templates/solidity-account-id.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "packed-encoder",
+                include_str!("../templates/solidity-packed-encoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "char-encoder",
+                include_str!("../templates/solidity-char-encoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "bytes-encoder",
+                include_str!("../templates/solidity-bytes-encoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "compact-encoder",
+                include_str!("../templates/solidity-compact-encoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "sequence-encoder",
+                include_str!("../templates/solidity-sequence-encoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "result-decoder",
+                include_str!("../templates/solidity-result-decoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "array-encoder",
+                include_str!("../templates/solidity-array-encoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "fixed-bytes-encoder",
+                include_str!("../templates/solidity-fixed-bytes-encoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template("option", include_str!("../templates/solidity-option.txt"))
+            .unwrap();
+        templates
+            .add_template(
+                "bytes-decoder",
+                include_str!("../templates/solidity-bytes-decoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "sequence-decoder",
+                include_str!("../templates/solidity-sequence-decoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "option-decoder",
+                include_str!("../templates/solidity-option-decoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "packed-decoder",
+                include_str!("../templates/solidity-packed-decoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "option-encoder",
+                include_str!("../templates/solidity-option-encoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "struct-decoder",
+                include_str!("../templates/solidity-struct-decoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "array-decoder",
+                include_str!("../templates/solidity-array-decoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "enum-decoder",
+                include_str!("../templates/solidity-enum-decoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "fixed-bytes-decoder",
+                include_str!("../templates/solidity-fixed-bytes-decoder.txt"),
+            )
+            .unwrap();
+        templates
+            .add_template(
+                "compact-decoder",
+                include_str!("../templates/solidity-compact-decoder.txt"),
+            )
+            .unwrap();
 
         templates.add_formatter("path", format_path);
 
-        Context { project, templates }
+        let mut paths = HashMap::<&[String], usize>::new();
+        for ty in project.registry().types() {
+            let segments = ty.ty().path().segments();
+            if !segments.is_empty() {
+                *paths.entry(segments).or_default() += 1;
+            }
+        }
+
+        let shared_paths = paths
+            .into_iter()
+            .filter(|&(_, count)| count > 1)
+            .map(|(path, _)| path.to_vec())
+            .collect();
+
+        Context {
+            project,
+            templates,
+            roles,
+            shared_paths,
+            custom_errors: true,
+        }
+    }
+}
+
+/// Keywords and reserved words of Solidity, which can't be used as identifiers
+static SOLIDITY_KEYWORDS: &str = "\
+    abstract address after alias anonymous apply as assembly auto bool break byte bytes \
+    calldata case catch constant constructor continue contract copyof days default define \
+    delete do else emit enum ether event external fallback false final fixed for function \
+    gwei hex hours if immutable implements import in indexed inline int interface internal \
+    is let library macro mapping match memory minutes modifier mutable new null of override \
+    partial payable pragma private promise public pure receive reference relocatable return \
+    returns sealed seconds sizeof static storage string struct super supports switch this \
+    true try type typedef typeof ufixed uint unchecked using var view virtual weeks wei \
+    while years";
+
+/// Local variables and parameters of generated functions, which arguments must not shadow
+static GENERATED_LOCALS: &[&str] = &[
+    "buffer",
+    "code_hash",
+    "contract_address",
+    "data",
+    "ink_address",
+    "input",
+    "offset",
+    "output",
+    "proof_size",
+    "ref_time",
+    "salt",
+    "selector",
+    "status",
+    "success",
+    "topics",
+];
+
+/// Name usable as Solidity identifier, keywords and sized type names like `uint32` get
+/// a trailing underscore, e.g. `address_`
+fn solidity_identifier(name: &str) -> String {
+    let sized = |prefix: &str| {
+        name.strip_prefix(prefix).map_or(false, |size| {
+            !size.is_empty() && size.chars().all(|c| c.is_ascii_digit() || c == 'x')
+        })
+    };
+
+    let sized_type = ["int", "uint", "bytes", "fixed", "ufixed"]
+        .into_iter()
+        .any(sized);
+
+    let keyword = SOLIDITY_KEYWORDS
+        .split_whitespace()
+        .any(|keyword| keyword == name);
+    match keyword || sized_type {
+        true => format!("{name}_"),
+        false => name.to_owned(),
+    }
+}
+
+/// Name of the generated Solidity contract, `--module-name` or the name of the ink! one
+fn contract_name(metadata: &Value, args: &Args) -> Result<String, Error> {
+    let valid = |name: &str| {
+        let mut chars = name.chars();
+        let head = chars.next();
+        head.map_or(false, |c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+            && solidity_identifier(name) == name
+    };
+
+    match &args.module_name {
+        Some(name) if valid(name) => Ok(name.clone()),
+        Some(name) => Err(Error::InvalidOptions(format!(
+            "--module-name `{name}` is not a valid Solidity identifier"
+        ))),
+        None => match metadata["contract"]["name"].as_str() {
+            // ink! contracts are named after Rust identifiers, only keywords need escaping
+            Some(name) if valid(&solidity_identifier(name)) => Ok(solidity_identifier(name)),
+            Some(name) => Err(Error::Metadata(format!(
+                "contract name `{name}` is not a valid Solidity identifier, see --module-name"
+            ))),
+            None => Err(Error::Metadata(
+                "metadata names no contract, see --module-name".to_owned(),
+            )),
+        },
+    }
+}
+
+/// ABI JSON parameter named `name` of a type, given its [`EvmType::abi`]
+fn abi_param(name: &str, abi: &Value) -> Value {
+    let mut param = abi.clone();
+    param["name"] = json!(name);
+    param
+}
+
+/// Turns type reference into a valid part of Solidity identifier, e.g. `uint8[20]`
+/// into `uint8_20_`
+fn identifier(reference: &str) -> String {
+    reference
+        .trim_end_matches(" memory")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Whether values of the type are encoded as no bytes at all, like `()`, `PhantomData<T>`,
+/// empty arrays or structs of such fields only
+fn is_zero_sized(context: &Context, id: u32) -> bool {
+    fn zero_sized(context: &Context, id: u32, visited: &mut Vec<u32>) -> bool {
+        // Types containing themselves can't be empty
+        if visited.contains(&id) {
+            return false;
+        }
+
+        visited.push(id);
+        let zero_sized = match context.project.registry().resolve(id).map(Type::type_def) {
+            Some(TypeDef::Tuple(tuple)) => tuple
+                .fields()
+                .iter()
+                .all(|field| zero_sized(context, field.id(), visited)),
+            Some(TypeDef::Composite(composite)) => composite
+                .fields()
+                .iter()
+                .all(|field| zero_sized(context, field.ty().id(), visited)),
+            Some(TypeDef::Array(array)) => {
+                array.len() == 0 || zero_sized(context, array.type_param().id(), visited)
+            }
+            _ => false,
+        };
+
+        visited.pop();
+        zero_sized
     }
+
+    zero_sized(context, id, &mut Vec::new())
 }
 
 fn format_path(value: &serde_json::Value, buffer: &mut String) -> tinytemplate::error::Result<()> {
@@ -68,31 +419,138 @@ fn format_path(value: &serde_json::Value, buffer: &mut String) -> tinytemplate::
 }
 
 impl EvmTypeRegistry {
-    fn new() -> Self {
-        Self::default()
+    /// Registry of all types of the project having a Solidity counterpart
+    fn new(context: &Context) -> Self {
+        let mut registry = Self::default();
+        for ty in context.project.registry().types() {
+            registry.lookup_or_convert(ty.id(), context);
+        }
+
+        registry
     }
 
     fn lookup(&self, id: u32) -> Option<&EvmType> {
         self.mapping.get(&id)
     }
 
-    fn lookup_mut(&mut self, id: u32) -> Option<&mut EvmType> {
-        self.mapping.get_mut(&id)
-    }
-
     fn insert<'r, 'm>(&mut self, id: u32, ty: EvmType) {
         self.mapping.insert(id, ty);
     }
 
+    /// Converted type of the given id, marked as used by the contract interface,
+    /// so that it gets defined
+    fn use_type(&mut self, id: u32, context: &Context) -> Option<EvmType> {
+        let ty = self.lookup_or_convert(id, context)?;
+        if !self.used.contains(&id) {
+            self.used.push(id);
+        }
+
+        Some(ty)
+    }
+
+    /// Used types along with the types they refer to, each after the types it refers to
+    fn ordered(&self) -> Vec<(u32, &EvmType)> {
+        fn visit(registry: &EvmTypeRegistry, id: u32, visited: &mut Vec<u32>) {
+            if visited.contains(&id) {
+                return;
+            }
+
+            for &dependency in registry.dependencies.get(&id).into_iter().flatten() {
+                visit(registry, dependency, visited);
+            }
+
+            visited.push(id);
+        }
+
+        let mut ordered = Vec::new();
+        for &id in &self.used {
+            visit(self, id, &mut ordered);
+        }
+
+        ordered
+            .into_iter()
+            .filter_map(|id| Some((id, self.mapping.get(&id)?)))
+            .collect()
+    }
+
+    /// Converted type of the given id, converting it first if needed
+    fn lookup_or_convert(&mut self, id: u32, context: &Context) -> Option<EvmType> {
+        if let Some(ty) = self.lookup(id) {
+            return Some(ty.clone());
+        }
+
+        let ty = context.project.registry().resolve(id)?;
+        let new_type = self.convert_type(id, ty, context)?;
+        self.insert(id, new_type.clone());
+        Some(new_type)
+    }
+
+    /// Distinct definitions, encoders and decoders of all converted types, see [`Self::ordered`]
+    fn parts(&self) -> Vec<String> {
+        let mut emitted = HashSet::new();
+        self.ordered()
+            .into_iter()
+            .flat_map(|(_, ty)| [&ty.definition, &ty.encoder, &ty.decoder])
+            .filter_map(|part| part.clone())
+            .filter(|part| emitted.insert(part.clone()))
+            .collect()
+    }
+
+    /// Definitions, encoders and decoders of all converted types, each after those of
+    /// the types it refers to, and rendered once even if several ids map to the same
+    /// Solidity type. Those in `shared` come from the base contract, see [`shared_definitions`].
+    fn definitions(&self, shared: &HashSet<String>) -> String {
+        let mut emitted: HashSet<&str> = shared.iter().map(String::as_str).collect();
+        let mut buffer = String::new();
+        for (id, ty) in self.ordered() {
+            buffer.push_str(&format!("    // mapped id {id} -> {}\n", ty.reference));
+            for part in [&ty.definition, &ty.encoder, &ty.decoder] {
+                if let Some(part) = part.as_ref().filter(|part| emitted.insert(part.as_str())) {
+                    buffer.push_str(part);
+                }
+                buffer.push('\n');
+            }
+        }
+
+        buffer
+    }
+
+    /// Definitions of used types alone, without encoders and decoders, see [`Self::ordered`]
+    fn type_definitions(&self) -> String {
+        let mut emitted = HashSet::new();
+        self.ordered()
+            .into_iter()
+            .filter_map(|(_, ty)| ty.definition.as_deref())
+            .filter(|definition| emitted.insert(*definition))
+            .map(|definition| format!("{definition}\n"))
+            .collect()
+    }
+
+    /// Converts the type of the given id, none if it has no Solidity counterpart
     fn convert_type(
         &mut self,
         id: u32,
         ty: &Type<PortableForm>,
         context: &Context,
     ) -> Option<EvmType> {
-        let mut lookup_reference_or_insert = |id| {
+        // Recursive types have none, reported by `check_types` if used
+        if self.converting.contains(&id) {
+            return None;
+        }
+
+        self.converting.push(id);
+        let converted = self.convert(id, ty, context);
+        self.converting.pop();
+        converted
+    }
+
+    fn convert(&mut self, id: u32, ty: &Type<PortableForm>, context: &Context) -> Option<EvmType> {
+        let parent = id;
+        let mut lookup_or_insert = |id| {
+            self.dependencies.entry(parent).or_default().push(id);
+
             if let Some(ty) = self.lookup(id) {
-                Some(ty.reference.clone())
+                Some(ty.clone())
             } else {
                 let ty = context
                     .project
@@ -100,15 +558,14 @@ impl EvmTypeRegistry {
                     .resolve(id)
                     .expect("should exist");
                 let new_type = self.convert_type(id, ty, context)?;
-                let reference = new_type.reference.clone();
-                self.insert(id, new_type);
-                Some(reference)
+                self.insert(id, new_type.clone());
+                Some(new_type)
             }
         };
 
         #[derive(Serialize)]
         struct Struct {
-            path: Path<PortableForm>,
+            name: String,
             fields: Vec<Field>,
         }
 
@@ -117,34 +574,159 @@ impl EvmTypeRegistry {
             name: String,
             #[serde(rename = "type")]
             ty: String,
+
+            // Identifier of the field type in encoder names
+            encoder: String,
+
+            #[serde(skip)]
+            decodable: bool,
+
+            #[serde(skip)]
+            abi: Value,
+        }
+
+        #[derive(Serialize)]
+        struct DataEnum {
+            name: String,
+            variants: Vec<DataVariant>,
+        }
+
+        #[derive(Serialize)]
+        struct DataVariant {
+            index: u8,
+            name: String,
+            fields: Vec<DataField>,
+        }
+
+        #[derive(Serialize)]
+        struct DataField {
+            name: String,
+            #[serde(rename = "type")]
+            ty: String,
+
+            // Identifier of the field type in encoder names
+            encoder: String,
+
+            #[serde(skip)]
+            abi: Value,
+        }
+
+        #[derive(Serialize)]
+        struct Primitive<'a> {
+            name: &'a str,
+            unsigned: String,
+
+            // Little endian bytes of integers, none for `bool`
+            bytes: Option<usize>,
+        }
+
+        #[derive(Serialize)]
+        struct ResultType {
+            name: String,
+
+            // `bool` of `true` for `Result<(), E>`
+            value_type: String,
+            value_declaration: String,
+            value_name: Option<String>,
+
+            // Variants of fieldless error enums, empty if the error is reverted with as is
+            errors: Vec<ErrorVariant>,
+            custom_errors: bool,
+        }
+
+        #[derive(Serialize)]
+        struct ErrorVariant {
+            index: u8,
+            name: String,
+        }
+
+        #[derive(Serialize)]
+        struct Sequence {
+            name: String,
+            element_type: String,
+            element_name: String,
+        }
+
+        #[derive(Serialize)]
+        struct ArrayType {
+            name: String,
+            element_type: String,
+            element_name: String,
+            len: u32,
+        }
+
+        #[derive(Serialize)]
+        struct OptionType {
+            name: String,
+            value_type: String,
+
+            // Identifier of the value type in encoder names
+            value_name: String,
+        }
+
+        // Instantiations of a generic type sharing the path are told apart by their parameters,
+        // e.g. `Wrapper_uint32` and `Wrapper_bool`
+        let mut name = ty.path().segments().join("_");
+        if context.shared_paths.contains(ty.path().segments()) {
+            for param in ty.type_params().iter().filter_map(|param| param.ty()) {
+                name = format!("{name}_{}", lookup_or_insert(param.id())?.name());
+            }
         }
 
         let mut fields_to_struct =
-            |path: Path<PortableForm>,
-             fields: Box<dyn Iterator<Item = scale_info::Field<PortableForm>>>| {
+            |name: String, fields: Box<dyn Iterator<Item = scale_info::Field<PortableForm>>>| {
                 let fields = fields
                     .enumerate()
+                    .filter(|(_, field)| !is_zero_sized(context, field.ty().id()))
                     .map(|(index, field)| {
-                        let id = field.ty().id();
+                        let ty = lookup_or_insert(field.ty().id()).unwrap_or_default();
 
                         Field {
                             name: field
                                 .name()
-                                .cloned()
+                                .map(|name| solidity_identifier(name))
                                 .unwrap_or_else(|| format!("f{}", index)),
-                            ty: lookup_reference_or_insert(id).unwrap_or_default(),
+                            encoder: ty.name(),
+                            ty: ty.reference.trim_end_matches(" memory").to_owned(),
+                            decodable: ty.decoder.is_some(),
+                            abi: ty.abi,
                         }
                     })
                     .collect_vec();
 
-                Struct { path, fields }
+                Struct { name, fields }
             };
 
-        Some(match ty.type_def() {
-            TypeDef::Primitive(primitive) => EvmType {
-                reference: match primitive {
+        // Structs are passed to and from EVM tooling as tuples of their fields
+        let tuple_abi = |fields: &[Field]| {
+            let param = |field: &Field| abi_param(&field.name, &field.abi);
+            json!({ "type": "tuple", "components": fields.iter().map(param).collect_vec() })
+        };
+
+        // Arrays of elements keep the components of the element, if any
+        let array_abi = |element: &EvmType, suffix: String| {
+            let mut abi = element.abi.clone();
+            let ty = element.abi["type"].as_str().unwrap_or("?");
+            abi["type"] = json!(format!("{ty}{suffix}"));
+            abi
+        };
+
+        // Structs are decoded field by field, hence only if all of the fields can be
+        let struct_decoder = |st: &Struct| {
+            st.fields
+                .iter()
+                .all(|field| field.decodable)
+                .then(|| context.templates.render("struct-decoder", st).unwrap())
+        };
+
+        let role = context.roles.get(&id).copied();
+        let ident = ty.path().ident();
+
+        let evm_type = match ty.type_def() {
+            TypeDef::Primitive(primitive) => {
+                let reference = match primitive {
                     TypeDefPrimitive::Bool => "bool",
-                    TypeDefPrimitive::Char => return None, // TODO
+                    TypeDefPrimitive::Char => "char",
                     TypeDefPrimitive::Str => "string",
                     TypeDefPrimitive::U8 => "uint8",
                     TypeDefPrimitive::U16 => "uint16",
@@ -158,243 +740,4677 @@ impl EvmTypeRegistry {
                     TypeDefPrimitive::I64 => "int64",
                     TypeDefPrimitive::I128 => "int128",
                     TypeDefPrimitive::I256 => "int256",
+                };
+
+                let bits = reference
+                    .trim_start_matches(char::is_alphabetic)
+                    .parse::<usize>();
+                let primitive = match (primitive, bits) {
+                    (TypeDefPrimitive::Bool, _) => Some(None),
+                    (_, Ok(bits)) => Some(Some(bits / 8)),
+                    _ => None,
+                }
+                .map(|bytes| Primitive {
+                    name: reference,
+                    unsigned: format!("uint{}", reference.trim_start_matches(char::is_alphabetic)),
+                    bytes,
+                });
+
+                let render = |template| {
+                    primitive
+                        .as_ref()
+                        .map(|primitive| context.templates.render(template, primitive).unwrap())
+                };
+
+                // Chars are encoded as their code point
+                if reference == "char" {
+                    let encoder = context.templates.render("char-encoder", &());
+                    return Some(EvmType {
+                        reference: "uint32".to_owned(),
+                        encoder: Some(encoder.unwrap()),
+                        name: Some("char".to_owned()),
+                        abi: json!({ "type": "uint32" }),
+                        ..EvmType::default()
+                    });
+                }
+
+                // Strings are encoded as their UTF-8 bytes
+                if reference == "string" {
+                    let string = json!({ "name": "string" });
+                    let encoder = context.templates.render("bytes-encoder", &string);
+                    let decoder = context.templates.render("bytes-decoder", &string);
+                    return Some(EvmType {
+                        reference: reference.to_owned(),
+                        modifier: Some("memory".to_owned()),
+                        encoder: Some(encoder.unwrap()),
+                        decoder: Some(decoder.unwrap()),
+                        abi: json!({ "type": reference }),
+                        ..EvmType::default()
+                    });
                 }
-                .to_owned(),
-                ..EvmType::default()
-            },
 
+                EvmType {
+                    reference: reference.to_owned(),
+                    encoder: render("primitive-encoder"),
+                    decoder: render("decoder"),
+                    abi: json!({ "type": reference }),
+                    ..EvmType::default()
+                }
+            }
+
+            // SCALE encodes fixed size arrays as the elements without a length
             TypeDef::Array(array) => {
-                let id = array.type_param().id();
-                let reference = lookup_reference_or_insert(id)?;
+                let element = lookup_or_insert(array.type_param().id())?;
                 let size = array.len();
 
                 // Special handling of byte arrays
-                if reference == "uint8" && size <= 32 {
+                if element.reference == "uint8" && size <= 32 {
+                    let name = format!("bytes{size}");
+                    let bytes = json!({ "name": name, "type": name, "bytes": size });
+                    EvmType {
+                        reference: name.clone(),
+                        encoder: Some(context.templates.render("packed-encoder", &bytes).unwrap()),
+                        decoder: Some(context.templates.render("packed-decoder", &bytes).unwrap()),
+                        abi: json!({ "type": name }),
+                        name: Some(name),
+                        ..EvmType::default()
+                    }
+                } else if element.reference == "uint8" {
+                    // Larger ones are passed as `bytes` checked for the length, rather than
+                    // as a `uint8[N]` taking a word per byte
+                    let name = format!("bytes{size}");
+                    let bytes = json!({ "name": name, "len": size });
+                    let encoder = context.templates.render("fixed-bytes-encoder", &bytes);
+                    let decoder = context.templates.render("fixed-bytes-decoder", &bytes);
                     EvmType {
-                        reference: format!("bytes{size}"),
+                        reference: "bytes".to_owned(),
+                        modifier: Some("memory".to_owned()),
+                        encoder: Some(encoder.unwrap()),
+                        decoder: Some(decoder.unwrap()),
+                        abi: json!({ "type": "bytes" }),
+                        name: Some(name),
                         ..EvmType::default()
                     }
                 } else {
+                    let array = ArrayType {
+                        name: format!("Array_{}_{size}", element.name()),
+                        element_type: element.reference.trim_end_matches(" memory").to_owned(),
+                        element_name: element.name(),
+                        len: size,
+                    };
+
+                    let encoder = context.templates.render("array-encoder", &array);
+                    let abi = array_abi(&element, format!("[{size}]"));
+                    let decoder = element
+                        .decoder
+                        .map(|_| context.templates.render("array-decoder", &array).unwrap());
                     EvmType {
-                        reference: format!("{reference}[{size}]"),
+                        reference: format!("{}[{size}]", array.element_type),
+                        modifier: Some("memory".to_owned()),
+                        encoder: Some(encoder.unwrap()),
+                        decoder,
+                        abi,
+                        name: Some(array.name),
                         ..EvmType::default()
                     }
                 }
             }
 
+            // SCALE encodes sequences as a compact length followed by the elements
+            TypeDef::Sequence(sequence) => {
+                let element = lookup_or_insert(sequence.type_param().id())?;
+
+                // Special handling of byte vectors
+                if element.reference == "uint8" {
+                    let bytes = json!({ "name": "bytes" });
+                    let encoder = context.templates.render("bytes-encoder", &bytes);
+                    let decoder = context.templates.render("bytes-decoder", &bytes);
+                    return Some(EvmType {
+                        reference: "bytes".to_owned(),
+                        modifier: Some("memory".to_owned()),
+                        encoder: Some(encoder.unwrap()),
+                        decoder: Some(decoder.unwrap()),
+                        abi: json!({ "type": "bytes" }),
+                        ..EvmType::default()
+                    });
+                }
+
+                let sequence = Sequence {
+                    name: format!("Vec_{}", element.name()),
+                    element_type: element.reference.trim_end_matches(" memory").to_owned(),
+                    element_name: element.name(),
+                };
+
+                let encoder = context.templates.render("sequence-encoder", &sequence);
+                let abi = array_abi(&element, "[]".to_owned());
+                let decoder = element.decoder.map(|_| {
+                    context
+                        .templates
+                        .render("sequence-decoder", &sequence)
+                        .unwrap()
+                });
+                EvmType {
+                    reference: format!("{}[]", sequence.element_type),
+                    modifier: Some("memory".to_owned()),
+                    encoder: Some(encoder.unwrap()),
+                    decoder,
+                    abi,
+                    name: Some(sequence.name),
+                    ..EvmType::default()
+                }
+            }
+
+            // Compact integers keep the type of the value and only differ in encoding
+            TypeDef::Compact(compact) => {
+                let value = lookup_or_insert(compact.type_param().id())?;
+                let name = format!("Compact_{}", value.name());
+
+                // `u32` and `u128` have helpers of their own in the codec library, others are
+                // range checked where decoded
+                let codec = match value.reference.as_str() {
+                    "uint32" => Some("u32"),
+                    "uint128" => Some("u128"),
+                    _ => None,
+                };
+                let compact =
+                    json!({ "name": name, "value_type": value.reference, "codec": codec });
+                let encoder = context.templates.render("compact-encoder", &compact);
+                let decoder = context.templates.render("compact-decoder", &compact);
+
+                EvmType {
+                    reference: value.reference,
+                    encoder: Some(encoder.unwrap()),
+                    decoder: Some(decoder.unwrap()),
+                    abi: value.abi,
+                    name: Some(name),
+                    ..EvmType::default()
+                }
+            }
+
+            // Accounts are passed as is rather than as a struct wrapping the bytes
+            TypeDef::Composite(_)
+                if role == Some("AccountId") || ident.as_deref() == Some("AccountId") =>
+            {
+                let account = json!({ "name": "AccountId", "type": "bytes32", "bytes": 32 });
+                let decoder = context.templates.render("packed-decoder", &account);
+                EvmType {
+                    reference: "bytes32".to_owned(),
+                    encoder: Some(context.templates.render("account-id", &()).unwrap()),
+                    decoder: Some(decoder.unwrap()),
+                    name: Some("AccountId".to_owned()),
+                    abi: json!({ "type": "bytes32" }),
+                    ..EvmType::default()
+                }
+            }
+
+            TypeDef::Composite(_) if role == Some("Hash") || ident.as_deref() == Some("Hash") => {
+                let hash = json!({ "name": "Hash", "type": "bytes32", "bytes": 32 });
+                EvmType {
+                    reference: "bytes32".to_owned(),
+                    encoder: Some(context.templates.render("packed-encoder", &hash).unwrap()),
+                    decoder: Some(context.templates.render("packed-decoder", &hash).unwrap()),
+                    name: Some("Hash".to_owned()),
+                    abi: json!({ "type": "bytes32" }),
+                    ..EvmType::default()
+                }
+            }
+
             TypeDef::Composite(composite) => {
-                let st = fields_to_struct(
-                    ty.path().clone(),
-                    Box::new(composite.fields().iter().cloned()),
-                );
+                let st = fields_to_struct(name, Box::new(composite.fields().iter().cloned()));
 
                 EvmType {
                     // Tuples are not first class citizens of Solidity.
                     // Hence, we are forced to define them as structs.
                     definition: Some(context.templates.render("struct", &st).unwrap()),
 
-                    reference: ty.path().segments().join("_"),
+                    reference: st.name.clone(),
 
                     // Structures should be declared using `memory` specifier
                     modifier: Some("memory".to_owned()),
 
                     encoder: Some(context.templates.render("encoder", &st).unwrap()),
 
+                    decoder: struct_decoder(&st),
+
+                    abi: tuple_abi(&st.fields),
+
                     ..EvmType::default()
                 }
             }
 
             TypeDef::Tuple(tuple) => {
-                let st =
+                let mut st =
                     fields_to_struct(
-                        ty.path().clone(),
+                        name,
                         Box::new(tuple.fields().iter().map(|id| {
                             scale_info::Field::<PortableForm>::new(None, *id, None, vec![])
                         })),
                     );
 
+                // Tuples have no path, hence are named after their elements, e.g. `Tuple_uint32_bool`
+                st.name = std::iter::once("Tuple")
+                    .chain(st.fields.iter().map(|field| field.encoder.as_str()))
+                    .join("_");
+
                 EvmType {
                     // Tuples are not first class citizens of Solidity.
                     // Hence, we are forced to define them as structs.
                     definition: Some(context.templates.render("struct", &st).unwrap()),
 
                     // Structures should be referred using `memory` specifier
-                    reference: ty.path().segments().join("_") + " memory",
+                    reference: st.name.clone() + " memory",
 
-                    ..EvmType::default()
-                }
-            }
+                    encoder: Some(context.templates.render("encoder", &st).unwrap()),
 
-            TypeDef::Variant(variant) => {
-                let default_indices = variant
-                    .variants()
-                    .iter()
-                    .enumerate()
-                    .all(|(index, variant)| index == variant.index() as usize);
+                    decoder: struct_decoder(&st),
 
-                // Solidity does not support non-default variant discriminants :(
-                if !default_indices {
-                    return None; // TODO report error
-                }
+                    abi: tuple_abi(&st.fields),
 
-                // Algebraic enums would require complex discriminant and substructure handling :(
-                // Currently we just encode them as C-style POD enums completely omitting fields
-                EvmType {
-                    definition: Some(context.templates.render("enum", &ty).unwrap()),
-                    reference: ty.path().segments().join("_"),
                     ..EvmType::default()
                 }
             }
 
-            _ => return None, // todo!(),
-        })
-    }
+            // Results are unwrapped to the `Ok` value, reverting with the `Err` one
+            TypeDef::Variant(variant) if ty.path().segments() == ["Result"] => {
+                let field = |name: &str| {
+                    variant
+                        .variants()
+                        .iter()
+                        .find(|variant| variant.name() == name)
+                        .and_then(|variant| variant.fields().first())
+                        .map(|field| field.ty().id())
+                };
+
+                let (ok, err) = (field("Ok")?, field("Err")?);
+                let resolve = |id| context.project.registry().resolve(id);
+
+                let value = match is_zero_sized(context, ok) {
+                    true => None,
+                    false => Some(lookup_or_insert(ok)?),
+                };
+
+                let errors = match resolve(err)?.type_def() {
+                    TypeDef::Variant(error)
+                        if error.variants().iter().all(|v| v.fields().is_empty()) =>
+                    {
+                        let enum_name = resolve(err)?.path().ident().unwrap_or_default();
+                        error
+                            .variants()
+                            .iter()
+                            .map(|variant| ErrorVariant {
+                                index: variant.index(),
+                                name: format!("{enum_name}::{}", variant.name()),
+                            })
+                            .collect()
+                    }
+                    _ => Vec::new(),
+                };
+
+                // Decoded only if the `Ok` value can be
+                let decodable = value.as_ref().map_or(true, |value| value.decoder.is_some());
+                let value_name = value.as_ref().map(EvmType::name);
+                let abi = match &value {
+                    Some(value) => value.abi.clone(),
+                    None => json!({ "type": "bool" }),
+                };
+                let modifier = value.as_ref().and_then(|value| value.modifier.clone());
+                let declaration = value.as_ref().map(EvmType::declaration);
+                let result = ResultType {
+                    name: format!(
+                        "Result_{}_{}",
+                        value_name.as_deref().unwrap_or("unit"),
+                        resolve(err)?.path().segments().join("_"),
+                    ),
+                    value_declaration: declaration.unwrap_or_else(|| "bool".to_owned()),
+                    value_type: value.map_or("bool".to_owned(), |value| {
+                        value.reference.trim_end_matches(" memory").to_owned()
+                    }),
+                    value_name,
+                    errors,
+                    custom_errors: context.custom_errors,
+                };
+
+                EvmType {
+                    reference: result.value_type.clone(),
+                    modifier,
+                    abi,
+                    decoder: decodable
+                        .then(|| context.templates.render("result-decoder", &result).unwrap()),
+                    name: Some(result.name),
+                    ..EvmType::default()
+                }
+            }
+
+            // SCALE encodes `Option` as a flag byte optionally followed by the value
+            TypeDef::Variant(variant) if ty.path().segments() == ["Option"] => {
+                let some = variant
+                    .variants()
+                    .iter()
+                    .find(|variant| variant.name() == "Some")?;
+
+                let value = lookup_or_insert(some.fields().first()?.ty().id())?;
+                let components = [
+                    abi_param("isSome", &json!({ "type": "bool" })),
+                    abi_param("value", &value.abi),
+                ];
+                let option = OptionType {
+                    name: format!("Option_{}", value.name()),
+                    value_type: value.reference.trim_end_matches(" memory").to_owned(),
+                    value_name: value.name(),
+                };
+
+                EvmType {
+                    definition: Some(context.templates.render("option", &option).unwrap()),
+                    reference: option.name.clone(),
+                    modifier: Some("memory".to_owned()),
+                    encoder: Some(context.templates.render("option-encoder", &option).unwrap()),
+                    decoder: value
+                        .decoder
+                        .map(|_| context.templates.render("option-decoder", &option).unwrap()),
+                    abi: json!({ "type": "tuple", "components": components }),
+                    ..EvmType::default()
+                }
+            }
+
+            TypeDef::Variant(variant) => {
+                let default_indices = variant
+                    .variants()
+                    .iter()
+                    .enumerate()
+                    .all(|(index, variant)| index == variant.index() as usize);
+
+                // Solidity does not support non-default variant discriminants :(
+                // Reported by `check_types` before rendering
+                if !default_indices && variant.variants().iter().all(|v| v.fields().is_empty()) {
+                    return None;
+                }
+
+                // C-style enums are encoded as the index of the variant
+                if variant.variants().iter().all(|v| v.fields().is_empty()) {
+                    let packed = json!({ "name": name, "type": name });
+                    let encoder = context.templates.render("packed-encoder", &packed);
+                    let variants = variant.variants().iter().map(|variant| {
+                        let name = solidity_identifier(variant.name());
+                        json!({ "name": name, "index": variant.index() })
+                    });
+                    let definition = json!({ "name": name, "variants": variants.collect_vec() });
+
+                    // Indices past the last variant are rejected rather than cast into the enum
+                    let count = variant.variants().len();
+                    let enum_decoder = json!({ "name": name, "count": count });
+                    let decoder = context.templates.render("enum-decoder", &enum_decoder);
+
+                    EvmType {
+                        definition: Some(context.templates.render("enum", &definition).unwrap()),
+                        reference: name,
+                        encoder: Some(encoder.unwrap()),
+                        decoder: Some(decoder.unwrap()),
+                        abi: json!({ "type": "uint8" }),
+                        ..EvmType::default()
+                    }
+                } else {
+                    // Solidity has no tagged unions, so enums carrying data become a struct
+                    // of the variant index and a payload struct per variant
+                    let mut variants = Vec::new();
+                    for variant in variant.variants() {
+                        let mut fields = Vec::new();
+                        for (index, field) in variant.fields().iter().enumerate() {
+                            if is_zero_sized(context, field.ty().id()) {
+                                continue;
+                            }
+
+                            let ty = lookup_or_insert(field.ty().id())?;
+                            fields.push(DataField {
+                                name: field
+                                    .name()
+                                    .map(|name| solidity_identifier(name))
+                                    .unwrap_or_else(|| format!("f{index}")),
+                                encoder: ty.name(),
+                                ty: ty.reference.trim_end_matches(" memory").to_owned(),
+                                abi: ty.abi,
+                            });
+                        }
+
+                        variants.push(DataVariant {
+                            index: variant.index(),
+                            name: solidity_identifier(variant.name()),
+                            fields,
+                        });
+                    }
+
+                    // The tag followed by a tuple of the fields of each variant carrying any
+                    let mut components = vec![abi_param("tag", &json!({ "type": "uint8" }))];
+                    for variant in variants.iter().filter(|variant| !variant.fields.is_empty()) {
+                        let param = |field: &DataField| abi_param(&field.name, &field.abi);
+                        let fields = variant.fields.iter().map(param).collect_vec();
+                        let payload = json!({ "type": "tuple", "components": fields });
+                        components.push(abi_param(&variant.name, &payload));
+                    }
+
+                    let data_enum = DataEnum { name, variants };
+                    let definition = context.templates.render("data-enum", &data_enum);
+                    let encoder = context.templates.render("data-enum-encoder", &data_enum);
+
+                    EvmType {
+                        definition: Some(definition.unwrap()),
+                        reference: data_enum.name,
+                        modifier: Some("memory".to_owned()),
+                        encoder: Some(encoder.unwrap()),
+                        abi: json!({ "type": "tuple", "components": components }),
+                        ..EvmType::default()
+                    }
+                }
+            }
+
+            // Reported by `check_types` before rendering
+            TypeDef::BitSequence(_) => return None,
+        };
+
+        Some(EvmType {
+            role: role.map(str::to_owned),
+            ..evm_type
+        })
+    }
+}
+
+/// Formats address with EIP-55 mixed case checksum, as Solidity requires for literals
+fn checksum_address(address: &[u8; 20]) -> String {
+    let digits = hex::encode(address);
+    let hash = Keccak256::digest(digits.as_bytes());
+
+    let checksummed: String = digits
+        .chars()
+        .enumerate()
+        .map(|(i, digit)| match (hash[i / 2] >> (4 * (1 - i % 2))) & 0x0f {
+            8..=15 => digit.to_ascii_uppercase(),
+            _ => digit,
+        })
+        .collect();
+
+    format!("0x{checksummed}")
+}
+
+/// Keys of spec entries known to the V3 `InkProject`, newer metadata adds others
+static CONSTRUCTOR_KEYS: &[&str] = &["label", "selector", "payable", "args", "docs"];
+static MESSAGE_KEYS: &[&str] = &[
+    "label",
+    "selector",
+    "mutates",
+    "payable",
+    "args",
+    "returnType",
+    "docs",
+];
+static EVENT_KEYS: &[&str] = &["label", "args", "docs"];
+static EVENT_ARG_KEYS: &[&str] = &["label", "indexed", "type", "docs"];
+static ARG_KEYS: &[&str] = &["label", "type"];
+
+/// Kinds of type definitions of `scale-info` 2
+static TYPE_DEFS: &[&str] = &[
+    "composite",
+    "variant",
+    "sequence",
+    "array",
+    "tuple",
+    "primitive",
+    "compact",
+    "bitsequence",
+];
+
+/// Keys of the environment section and the types they declare
+static ENVIRONMENT_TYPES: &[(&str, &str)] = &[
+    ("accountId", "AccountId"),
+    ("balance", "Balance"),
+    ("hash", "Hash"),
+    ("timestamp", "Timestamp"),
+    ("blockNumber", "BlockNumber"),
+];
+
+static SUPPORTED_VERSIONS: &str = "supported versions are 3, 4 and 5";
+
+/// Detects version of ink! metadata from its version key or `version` field
+fn metadata_version(metadata: &Value) -> Result<MetadataVersion, Error> {
+    if metadata["V3"].is_object() {
+        return Ok(MetadataVersion::V3);
+    }
+
+    // Early V4 metadata was nested under its own key like V3
+    if metadata["V4"].is_object() {
+        return Ok(MetadataVersion::V4);
+    }
+
+    // V4 spells the version as a string, V5 as a number
+    let version = match &metadata["version"] {
+        Value::String(version) => version.clone(),
+        Value::Number(version) => version.to_string(),
+        _ => {
+            return Err(Error::Metadata(format!(
+                "unable to detect ink! metadata version, {SUPPORTED_VERSIONS}, see --metadata-version"
+            )))
+        }
+    };
+
+    match version.as_str() {
+        "4" => Ok(MetadataVersion::V4),
+        "5" => Ok(MetadataVersion::V5),
+        _ => Err(Error::Metadata(format!(
+            "ink! metadata version {version} is not supported, {SUPPORTED_VERSIONS}"
+        ))),
+    }
+}
+
+/// Major versions of ink! whose metadata can be translated
+static SUPPORTED_INK_VERSIONS: std::ops::RangeInclusive<u32> = 3..=5;
+
+/// Fails on metadata produced by other languages than ink! or by unsupported ink! versions,
+/// or only warns about it if `allow` is set. Bare metadata lacking `source` is accepted.
+fn check_source(source: &Value, allow: bool) -> Result<(), Error> {
+    let language = match source["language"].as_str() {
+        Some(language) => language,
+        None => return Ok(()),
+    };
+
+    let major = language
+        .strip_prefix("ink! ")
+        .and_then(|version| version.trim_start_matches('v').split('.').next())
+        .and_then(|major| major.parse::<u32>().ok());
+
+    let reason = match major {
+        Some(major) if SUPPORTED_INK_VERSIONS.contains(&major) => return Ok(()),
+        Some(_) => "supported ink! versions are 3, 4 and 5",
+        None if language.starts_with("Solang") => {
+            "Solang lays out types differently than ink!, only ink! is supported"
+        }
+        None => "only metadata of ink! contracts is supported",
+    };
+
+    let error = Error::UnsupportedSource {
+        language: language.to_owned(),
+        compiler: source["compiler"]
+            .as_str()
+            .unwrap_or("unknown compiler")
+            .to_owned(),
+        reason: reason.to_owned(),
+    };
+
+    if !allow {
+        return Err(error);
+    }
+
+    eprintln!("warning: {error}");
+    Ok(())
+}
+
+/// Brings ink! metadata to the V3 layout `InkProject` is deserialized from
+fn v3_metadata(metadata: &Value, version: Option<MetadataVersion>) -> Result<Value, Error> {
+    let version = match version {
+        Some(version) => version,
+        None => metadata_version(metadata)?,
+    };
+
+    let nested = |key: &str| match &metadata[key] {
+        Value::Object(_) => &metadata[key],
+        _ => metadata,
+    };
+
+    Ok(match version {
+        MetadataVersion::V3 => nested("V3").clone(),
+        MetadataVersion::V4 | MetadataVersion::V5 => from_v4(nested("V4")),
+    })
+}
+
+/// Deserializes ink! project from metadata of any supported version. Documents without
+/// any version marker are tried as a bare project of V3 and then V4 layout.
+fn ink_project(metadata: &Value, version: Option<MetadataVersion>) -> Result<InkProject, Error> {
+    let versioned = version.is_some()
+        || ["V3", "V4", "version"]
+            .iter()
+            .any(|key| metadata.get(key).is_some());
+
+    if versioned {
+        let project = v3_metadata(metadata, version)?;
+        check_type_defs(&project["types"])?;
+        return Ok(serde_json::from_value(project)?);
+    }
+
+    check_type_defs(&metadata["types"])?;
+    serde_json::from_value(metadata.clone())
+        .or_else(|_| serde_json::from_value(from_v4(metadata)))
+        .map_err(|_| {
+            let keys = match metadata.as_object() {
+                Some(object) => object.keys().map(|key| format!("`{key}`")).join(", "),
+                None => String::new(),
+            };
+
+            Error::Metadata(format!(
+                "input is neither versioned ink! metadata nor a bare ink! project, \
+                 found top level keys [{keys}]"
+            ))
+        })
+}
+
+/// Converts V4 and V5 metadata to the V3 layout. Fields added since, like event
+/// signature topics of V5, are dropped. Storage layout is not used by the templates
+/// and is left empty.
+fn from_v4(project: &Value) -> Value {
+    let types = &project["types"];
+    let spec = &project["spec"];
+
+    let with_args = |entry: &Value, keys: &[&str], arg_keys: &[&str]| {
+        let mut entry = pick(entry, keys);
+        entry["args"] = entries(&entry["args"]).map(|arg| pick(arg, arg_keys)).collect();
+        entry
+    };
+
+    let messages = entries(&spec["messages"])
+        .map(|message| {
+            let mut message = with_args(message, MESSAGE_KEYS, ARG_KEYS);
+            message["returnType"] = unwrap_message_result(&message["returnType"], types);
+            message
+        })
+        .collect::<Value>();
+
+    json!({
+        "spec": {
+            "constructors": entries(&spec["constructors"])
+                .map(|constructor| with_args(constructor, CONSTRUCTOR_KEYS, ARG_KEYS))
+                .collect::<Value>(),
+            "docs": spec["docs"],
+            "events": entries(&spec["events"])
+                .map(|event| with_args(event, EVENT_KEYS, EVENT_ARG_KEYS))
+                .collect::<Value>(),
+            "messages": messages,
+        },
+        "storage": { "struct": { "fields": [] } },
+        "types": types,
+    })
+}
+
+/// Whether a message of V4 or later metadata returns `MessageResult<T>`
+fn message_result(return_type: &Value) -> bool {
+    return_type["displayName"]
+        .as_array()
+        .and_then(|name| name.last())
+        .map_or(false, |name| name == "MessageResult")
+}
+
+/// Whether output of messages is wrapped into `Result<_, LangError>`, as since ink! 4,
+/// told by messages of the metadata returning `MessageResult`
+fn lang_error(metadata: &Value) -> bool {
+    [&metadata["spec"], &metadata["V4"]["spec"]]
+        .into_iter()
+        .flat_map(|spec| entries(&spec["messages"]))
+        .any(|message| message_result(&message["returnType"]))
+}
+
+/// V4 messages return `MessageResult<T>`, i.e. `Result<T, LangError>`.
+/// Unwraps `T`, or no type at all for `()`, as V3 metadata describes it.
+fn unwrap_message_result(return_type: &Value, types: &Value) -> Value {
+    if !message_result(return_type) {
+        return return_type.clone();
+    }
+
+    let resolve = |id: &Value| {
+        entries(types)
+            .find(|ty| ty["id"] == *id)
+            .map(|ty| &ty["type"])
+    };
+
+    let output = resolve(&return_type["type"])
+        .and_then(|ty| entries(&ty["def"]["variant"]["variants"]).find(|v| v["name"] == "Ok"))
+        .map(|ok| &ok["fields"][0]["type"])
+        .and_then(|id| Some((id, resolve(id)?)));
+
+    match output {
+        Some((_, ty)) if ty["def"]["tuple"] == json!([]) => Value::Null,
+        Some((id, ty)) => json!({
+            "displayName": match &ty["path"] {
+                Value::Array(path) => path.clone(),
+                _ => Vec::new(),
+            },
+            "type": id,
+        }),
+        None => return_type.clone(),
+    }
+}
+
+/// Type without a Solidity counterpart, see [`unsupported_type`]
+struct Unsupported {
+    path: String,
+
+    /// Innermost named field referring to the type, if any
+    field: Option<String>,
+
+    reason: String,
+}
+
+/// First type reachable from type `id` that cannot be passed across XVM: storage-only
+/// types such as `Mapping` nested in a struct, bit sequences, and C-style enums with
+/// custom discriminants
+fn unsupported_type(types: &Value, id: &Value, visited: &mut Vec<Value>) -> Option<Unsupported> {
+    if visited.contains(id) {
+        return None;
+    }
+
+    visited.push(id.clone());
+    let ty = &entries(types).find(|ty| ty["id"] == *id)?["type"];
+    let def = &ty["def"];
+
+    let path = entries(&ty["path"]).filter_map(Value::as_str).collect_vec();
+    let variants = entries(&def["variant"]["variants"]).collect_vec();
+
+    // Solidity enums are encoded as the position of the variant
+    let fieldless = variants
+        .iter()
+        .all(|v| entries(&v["fields"]).next().is_none());
+    let discriminant = variants
+        .iter()
+        .enumerate()
+        .find(|(position, v)| v["index"] != *position as u64)
+        .filter(|_| fieldless && !path.is_empty());
+
+    let reason = match (path.as_slice(), discriminant) {
+        (["ink_storage", ..] | ["ink", "storage", ..], _) => {
+            Some("storage-only types have no SCALE encoding".to_owned())
+        }
+        (_, Some((_, variant))) => Some(format!(
+            "variant `{}` has discriminant {}, which Solidity enums cannot express",
+            variant["name"].as_str().unwrap_or("?"),
+            variant["index"],
+        )),
+        _ if def.get("bitsequence").is_some() => {
+            Some("bit sequences have no Solidity counterpart".to_owned())
+        }
+        _ => None,
+    };
+
+    if let Some(reason) = reason {
+        return Some(Unsupported {
+            path: match path.is_empty() {
+                true => "BitSequence".to_owned(),
+                false => path.join("::"),
+            },
+            field: None,
+            reason,
+        });
+    }
+
+    referenced_types(ty).into_iter().find_map(|(name, id)| {
+        let mut unsupported = unsupported_type(types, id, visited)?;
+        unsupported.field = unsupported.field.or(name.map(str::to_owned));
+        Some(unsupported)
+    })
+}
+
+/// Ids of the types the type is made of, along with the names of fields referring to them
+fn referenced_types(ty: &Value) -> Vec<(Option<&str>, &Value)> {
+    let def = &ty["def"];
+    let variants = entries(&def["variant"]["variants"]).collect_vec();
+
+    // Errors are decoded by their index regardless of their representation
+    let variants = match ty["path"] == json!(["Result"]) {
+        true => &variants[..variants.len().min(1)],
+        false => &variants[..],
+    };
+
+    let fields = entries(&def["composite"]["fields"])
+        .chain(variants.iter().flat_map(|v| entries(&v["fields"])))
+        .map(|field| (field["name"].as_str(), &field["type"]));
+
+    let params = ["sequence", "array", "compact"]
+        .iter()
+        .map(|kind| &def[*kind]["type"])
+        .chain(entries(&def["tuple"]))
+        .filter(|id| !id.is_null())
+        .map(|id| (None, id));
+
+    fields.chain(params).collect()
+}
+
+/// Chain of type names leading from type `id` back to a type on the `stack`, if the type
+/// contains itself, like `Node -> Option -> Node` for `struct Node { next: Option<Box<Node>> }`
+fn recursive_type(types: &Value, id: &Value, stack: &mut Vec<Value>) -> Option<Vec<String>> {
+    if let Some(position) = stack.iter().position(|visiting| visiting == id) {
+        let cycle = stack[position..].iter().chain([id]);
+        return Some(cycle.map(|id| type_name(types, id)).collect());
+    }
+
+    let ty = &entries(types).find(|ty| ty["id"] == *id)?["type"];
+
+    stack.push(id.clone());
+    let cycle = referenced_types(ty)
+        .into_iter()
+        .find_map(|(_, id)| recursive_type(types, id, stack));
+    stack.pop();
+
+    cycle
+}
+
+/// Path of the type for error messages, or the kind of its definition if it has none
+fn type_name(types: &Value, id: &Value) -> String {
+    let ty = entries(types)
+        .find(|ty| ty["id"] == *id)
+        .map_or(&Value::Null, |ty| &ty["type"]);
+
+    match entries(&ty["path"]).filter_map(Value::as_str).join("::") {
+        path if path.is_empty() => ty["def"]
+            .as_object()
+            .and_then(|def| def.keys().next().cloned())
+            .unwrap_or_else(|| id.to_string()),
+        path => path,
+    }
+}
+
+/// Fails on types defined in a way newer than `scale-info` this tool is built with
+fn check_type_defs(types: &Value) -> Result<(), Error> {
+    for ty in entries(types) {
+        let mut defs = ty["type"]["def"]
+            .as_object()
+            .into_iter()
+            .flat_map(|def| def.keys());
+        if let Some(def) = defs.find(|def| !TYPE_DEFS.contains(&def.as_str())) {
+            let def = def.clone();
+            let id = ty["id"].as_u64().unwrap_or_default();
+            return Err(Error::UnknownTypeDef { def, id });
+        }
+    }
+
+    Ok(())
+}
+
+/// Fails on constructors and messages referring to types without Solidity counterpart
+fn check_types(project: &InkProject) -> Result<(), Error> {
+    let project = serde_json::to_value(project)?;
+
+    let kinds = [
+        ("constructor", "constructors"),
+        ("message", "messages"),
+        ("event", "events"),
+    ];
+
+    for (kind, key) in kinds {
+        for entry in entries(&project["spec"][key]) {
+            let types = &project["types"];
+            let label = entry["label"].as_str().unwrap_or("?");
+            let args = entries(&entry["args"]).map(|arg| {
+                let label = arg["label"].as_str().unwrap_or("?");
+                (format!("argument `{label}`"), &arg["type"]["type"])
+            });
+
+            let output = ("return type".to_owned(), &entry["returnType"]["type"]);
+            for (place, id) in args.chain([output]) {
+                if let Some(cycle) = recursive_type(types, id, &mut Vec::new()) {
+                    return Err(Error::RecursiveType {
+                        path: cycle.join(" -> "),
+                    });
+                }
+
+                if let Some(unsupported) = unsupported_type(types, id, &mut Vec::new()) {
+                    let context = match unsupported.field {
+                        Some(field) => format!("field `{field}` of {place} of {kind} `{label}`"),
+                        None => format!("{place} of {kind} `{label}`"),
+                    };
+
+                    return Err(Error::UnsupportedType {
+                        path: unsupported.path,
+                        context,
+                        reason: unsupported.reason,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Fails on messages sharing a selector. Selectors are embedded verbatim since those of
+/// `#[ink(selector = ..)]` overrides and trait messages can't be recomputed from labels
+fn check_selectors(project: &InkProject) -> Result<(), Error> {
+    let project = serde_json::to_value(project)?;
+
+    for key in ["constructors", "messages"] {
+        let mut seen: Vec<(&str, &str)> = Vec::new();
+        for entry in entries(&project["spec"][key]) {
+            let label = entry["label"].as_str().unwrap_or("?");
+            let selector = entry["selector"].as_str().unwrap_or_default();
+            let digits = selector.strip_prefix("0x").unwrap_or(selector);
+            if let Some((first, _)) = seen.iter().find(|(_, other)| *other == digits) {
+                return Err(Error::SelectorCollision {
+                    first: first.to_string(),
+                    second: label.to_owned(),
+                    selector: digits.to_owned(),
+                });
+            }
+
+            seen.push((label, digits));
+        }
+    }
+
+    Ok(())
+}
+
+/// Registry ids of environment types declared by V4 and later metadata
+fn environment_roles(environment: &Value) -> HashMap<u32, &'static str> {
+    ENVIRONMENT_TYPES
+        .iter()
+        .filter_map(|&(key, role)| {
+            let id = environment[key]["type"].as_u64()?.try_into().ok()?;
+            Some((id, role))
+        })
+        .collect()
+}
+
+/// Copy of JSON object `entry` with only the given keys
+fn pick(entry: &Value, keys: &[&str]) -> Value {
+    keys.iter()
+        .filter_map(|&key| Some((key.to_owned(), entry.get(key)?.clone())))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// VM id of ink! contracts, leading the XVM context of calls to them
+const WASM_VM_ID: u8 = 0x1f;
+
+/// Solidity expression of the XVM context of a call to an ink! contract limited by the given
+/// weight, or by the `ref_time` and `proof_size` arguments of the function if none: a
+/// `XVM.Context` struct for v3, otherwise the same bytes generated ink! code passes along with
+/// the EVM id
+fn solidity_xvm_context(version: XvmVersion, weight_limit: Option<(u64, u64)>) -> String {
+    let vm_id = format!("{WASM_VM_ID:#04x}");
+    let (ref_time, proof_size) = match weight_limit {
+        Some((ref_time, proof_size)) => (ref_time.to_string(), proof_size.to_string()),
+        None => ("ref_time".to_owned(), "proof_size".to_owned()),
+    };
+
+    match (version, weight_limit) {
+        (XvmVersion::V3, _) => format!("XVM.Context({vm_id}, {ref_time}, {proof_size}, 0)"),
+
+        // Encoded while generating the code if known
+        (_, Some(weight_limit)) => {
+            let context = [vec![WASM_VM_ID], xvm_context(version, weight_limit)].concat();
+            let escaped: String = context
+                .iter()
+                .map(|byte| format!("\\x{byte:02x}"))
+                .collect();
+            format!("\"{escaped}\"")
+        }
+
+        // Otherwise by the codec, following the layout of [`xvm_context`]
+        (XvmVersion::V1, None) => {
+            let ref_time = format!("ScaleCodec.encode_compact({ref_time})");
+            format!("bytes.concat(hex\"{WASM_VM_ID:02x}\", {ref_time})")
+        }
+        (XvmVersion::V2, None) => {
+            let ref_time = format!("ScaleCodec.encode_compact({ref_time})");
+            let proof_size = format!("ScaleCodec.encode_compact({proof_size})");
+            let limits = format!("{ref_time}, {proof_size}");
+            format!("bytes.concat(hex\"{WASM_VM_ID:02x}\", {limits}, hex\"0000\")")
+        }
+    }
+}
+
+/// Items of JSON array, none if `value` is not an array
+fn entries(value: &Value) -> impl Iterator<Item = &Value> {
+    value.as_array().into_iter().flatten()
+}
+
+static MODULE_TEMPLATE: &str = include_str!("../templates/solidity-module.txt");
+static CONTRACT_TEMPLATE: &str = include_str!("../templates/solidity-contract.txt");
+static INTERFACE_TEMPLATE: &str = include_str!("../templates/solidity-interface.txt");
+static CODEC_TEMPLATE: &str = include_str!("../templates/solidity-codec.txt");
+static TEST_TEMPLATE: &str = include_str!("../templates/solidity-test.txt");
+
+#[derive(Serialize, Debug)]
+struct RenderContext {
+    module_name: String,
+
+    /// Hash of the ink! contract code, if known
+    source_hash: Option<String>,
+
+    xvm_address: String,
+
+    /// Whether the constructor takes the XVM precompile address, `xvm_address` if zero
+    xvm_address_arg: bool,
+
+    /// Solidity expression of the XVM context of every call, see [`solidity_xvm_context`]
+    xvm_context: String,
+
+    /// Whether calls go through `xvm_call_v3`, taking the context as a struct
+    xvm_v3: bool,
+
+    /// Whether messages take the weight limit of their call, see [`solidity_xvm_context`]
+    weight_args: bool,
+
+    /// Whether event data starts with the index of the event
+    prefixed_events: bool,
+
+    /// Whether XVM calls carry a value to transfer, which the legacy interface lacks
+    xvm_value: bool,
+
+    /// Whether message output is wrapped into `Result<_, LangError>`, see [`lang_error`]
+    lang_error: bool,
+
+    /// Whether the target compiler has custom errors, see [`check_solidity_version`]
+    custom_errors: bool,
+
+    /// Whether functions are those of a library taking the address of the ink! contract
+    library: bool,
+
+    /// Whether shared definitions come from the `SumiCodec` base contract
+    shared: bool,
+
+    /// Project with unit arguments and return types dropped, see [`without_units`],
+    /// and NatSpec comments and Solidity names added, see [`with_natspec`] and [`with_names`]
+    project: Value,
+}
+
+/// Template of a single contract along with everything it is rendered from
+struct Contract {
+    template: TinyTemplate<'static>,
+
+    /// Types converted while rendering
+    registry: Rc<RefCell<EvmTypeRegistry>>,
+
+    /// Definitions moved into the base contract, see [`shared_definitions`]
+    shared: Rc<RefCell<HashSet<String>>>,
+
+    /// Name of the template to render, `contract` or `interface` with `--emit interface`
+    kind: &'static str,
+
+    context: RenderContext,
 }
 
-pub fn render(reader: &mut dyn Read, module_name: &Option<String>) -> Result<String, Error> {
-    let mut buffer = String::new();
-    reader.read_to_string(&mut buffer)?;
+impl Contract {
+    fn new(reader: &mut dyn Read, args: &Args) -> Result<Self, Error> {
+        let mut buffer = String::new();
+        reader.read_to_string(&mut buffer)?;
+
+        // `.contract` bundles are metadata with the Wasm code added to `source`
+        let mut metadata: serde_json::Value = serde_json::from_str(&buffer)?;
+        if let Some(source) = metadata["source"].as_object_mut() {
+            source.remove("wasm");
+        }
+
+        check_source(&metadata["source"], args.allow_unsupported_source)?;
+        let project = Rc::new(ink_project(&metadata, args.metadata_version)?);
+        check_types(&project)?;
+        check_selectors(&project)?;
+        let module_name = contract_name(&metadata, args)?;
+
+        let mut template = TinyTemplate::new();
+        template.set_default_formatter(&tinytemplate::format_unescaped);
+        template.add_template("contract", CONTRACT_TEMPLATE)?;
+        template.add_template("interface", INTERFACE_TEMPLATE)?;
+
+        template.add_formatter("debug", |value, buffer| {
+            buffer.push_str(&format!("{:?}", value));
+            Ok(())
+        });
+
+        template.add_formatter("path", format_path);
+
+        let roles = environment_roles(&metadata["spec"]["environment"]);
+        let mut context = Context::new(project.clone(), roles);
+        context.custom_errors = check_solidity_version(&args.solidity_version)?;
+        let context = Rc::new(context);
+        let evm_registry = Rc::new(RefCell::new(EvmTypeRegistry::new(&context)));
+        let mut spec = without_units(&context)?;
+        with_natspec(&mut spec);
+        with_names(&mut spec);
+
+        // Types are marked used up front, so that their definitions can precede the functions
+        for key in ["constructors", "messages", "events"] {
+            for entry in entries(&spec["spec"][key]) {
+                let args = entries(&entry["args"]).map(|arg| &arg["type"]["type"]);
+                for id in args.chain([&entry["returnType"]["type"]]) {
+                    if let Some(id) = id.as_u64().and_then(|id| id.try_into().ok()) {
+                        evm_registry.borrow_mut().use_type(id, &context);
+                    }
+                }
+            }
+        }
+
+        // ink! 5 events are no longer variants of a single enum prefixed with their index
+        let prefixed_events = match args.metadata_version {
+            Some(version) => version != MetadataVersion::V5,
+            None => !matches!(metadata_version(&metadata), Ok(MetadataVersion::V5)),
+        };
+
+        let (registry, types) = (evm_registry.clone(), context.clone());
+        // Whether the type of the given id, or all arguments of the given event, have a decoder
+        template.add_predicate("decodable", move |value| {
+            let mut registry = registry.borrow_mut();
+            let mut decodable = |id: &Value| {
+                id.as_u64()
+                    .and_then(|id| id.try_into().ok())
+                    .and_then(|id| registry.lookup_or_convert(id, &types))
+                    .map_or(false, |ty| ty.decoder.is_some())
+            };
+
+            Ok(match value {
+                Value::Number(_) => decodable(value),
+                _ => entries(&value["args"]).all(|arg| decodable(&arg["type"]["type"])),
+            })
+        });
+
+        let shared = Rc::new(RefCell::new(HashSet::new()));
+        let (registry, excluded) = (evm_registry.clone(), shared.clone());
+        // Along with encoders and decoders, or with `types` only definitions of types themselves
+        template.add_formatter_with_args("definitions", move |_, arg, buffer| {
+            let registry = registry.borrow();
+            buffer.push_str(&match arg {
+                Some("types") => registry.type_definitions(),
+                _ => registry.definitions(&excluded.borrow()),
+            });
+            Ok(())
+        });
+
+        let registry = evm_registry.clone();
+        template.add_formatter_with_args("type", move |value, arg, buffer| {
+            if let serde_json::Value::Number(id) = value {
+                let id = id
+                    .as_u64()
+                    .and_then(|id| id.try_into().ok())
+                    .ok_or_else(|| GenericError {
+                        msg: format!("invalid id {id:?}"),
+                    })?;
+
+                let write_buffer = |ty: &EvmType, buffer: &mut String| {
+                    let empty = String::default();
+                    buffer.push_str(match arg {
+                        Some("reference") => ty.reference.as_ref(),
+                        Some("declaration") => return buffer.push_str(&ty.declaration()),
+                        Some("definition") => ty.definition.as_ref().unwrap_or(&empty),
+                        Some("modifier") => ty.modifier.as_ref().unwrap_or(&empty),
+                        Some("encoder") => ty.encoder.as_ref().unwrap_or(&empty),
+                        Some("decoder") => ty.decoder.as_ref().unwrap_or(&empty),
+                        Some("name") => return buffer.push_str(&ty.name()),
+                        Some("role") => ty.role.as_ref().unwrap_or(&ty.reference),
+                        _ => panic!("type formatter must come with an argument"),
+                    });
+                };
+
+                let ty = registry
+                    .borrow_mut()
+                    .use_type(id, &context)
+                    .ok_or_else(|| GenericError {
+                        msg: format!("unsupported type id {id:?}"),
+                    })?;
+                write_buffer(&ty, buffer);
+
+                Ok(())
+            } else {
+                return Err(GenericError {
+                    msg: format!("invalid type id {:?}", value),
+                });
+            }
+        });
+
+        let context = RenderContext {
+            module_name,
+            source_hash: metadata["source"]["hash"].as_str().map(str::to_owned),
+            xvm_address: checksum_address(&args.xvm_address),
+            xvm_address_arg: args.xvm_address_arg,
+            xvm_context: solidity_xvm_context(
+                args.xvm_version,
+                (!args.weight_args).then_some(args.weight_limit),
+            ),
+            weight_args: args.weight_args,
+            xvm_v3: args.xvm_version == XvmVersion::V3,
+            prefixed_events,
+            xvm_value: args.xvm_version != XvmVersion::V1,
+            lang_error: lang_error(&metadata),
+            custom_errors: context.custom_errors,
+            library: args.solidity_shape == SolidityShape::Library,
+            shared: false,
+            project: spec,
+        };
+
+        Ok(Contract {
+            template,
+            registry: evm_registry,
+            shared,
+            kind: match args.emit {
+                Emit::Interface => "interface",
+                _ => "contract",
+            },
+            context,
+        })
+    }
+
+    fn render(&self) -> Result<String, Error> {
+        Ok(self.template.render(self.kind, &self.context)?)
+    }
+}
+
+/// Functions of the `ScaleCodec` library every contract calls
+fn codec_helpers() -> Result<String, Error> {
+    let mut template = TinyTemplate::new();
+    template.add_template("codec", CODEC_TEMPLATE)?;
+    Ok(template.render("codec", &())?)
+}
+
+/// Definitions, encoders and decoders of types, given those of each contract, that are
+/// rendered identically by several contracts, in order of their first appearance. Those
+/// declaring a name another contract declares differently, or referring to one, are left
+/// out, since each contract then needs its own.
+fn shared_definitions(parts: &[Vec<String>]) -> Vec<String> {
+    let identifiers = |part: &str| {
+        part.split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .filter(|token| !token.is_empty())
+            .map(str::to_owned)
+            .collect_vec()
+    };
+
+    // Names following `struct`, `enum` and `function` keywords
+    let declared = |part: &str| {
+        identifiers(part)
+            .into_iter()
+            .tuple_windows()
+            .filter(|(keyword, _)| ["struct", "enum", "function"].contains(&keyword.as_str()))
+            .map(|(_, name)| name)
+            .collect_vec()
+    };
+
+    let mut declarations = HashMap::<String, HashSet<&str>>::new();
+    let mut counts = HashMap::<&str, usize>::new();
+    for part in parts.iter().flatten() {
+        *counts.entry(part).or_default() += 1;
+        for name in declared(part) {
+            declarations.entry(name).or_default().insert(part);
+        }
+    }
+
+    let mut shared: Vec<&str> = parts
+        .iter()
+        .flatten()
+        .map(String::as_str)
+        .unique()
+        .filter(|part| counts[part] > 1)
+        .filter(|part| {
+            declared(part)
+                .iter()
+                .all(|name| declarations[name].len() == 1)
+        })
+        .collect();
+
+    // Parts referring to definitions that stay in the contracts can't be shared either
+    loop {
+        let kept = shared
+            .iter()
+            .copied()
+            .filter(|part| {
+                identifiers(part)
+                    .iter()
+                    .all(|name| match declarations.get(name) {
+                        Some(declaring) => declaring.iter().all(|other| shared.contains(other)),
+                        None => true,
+                    })
+            })
+            .collect_vec();
+
+        if kept.len() == shared.len() {
+            break;
+        }
+
+        shared = kept;
+    }
+
+    shared.into_iter().map(str::to_owned).collect()
+}
+
+pub fn render(reader: &mut dyn Read, args: &Args) -> Result<String, Error> {
+    render_all(&mut [reader], args)
+}
+
+/// Renders a single Solidity file with a contract calling each of the ink! contracts whose
+/// metadata `readers` provide. Codec helpers and definitions of types several contracts
+/// render identically are moved into a `SumiCodec` base contract they all inherit.
+pub fn render_all(readers: &mut [&mut dyn Read], args: &Args) -> Result<String, Error> {
+    Ok(render_module(readers, args)?.0)
+}
+
+/// Renders the file of [`render_all`] as `<Contract>.sol`, or `Bindings.sol` for several
+/// contracts, followed by its Foundry test `<Contract>.t.sol` with `--with-tests`, and
+/// by the ABI JSON of the contract at the path `--emit-abi` gives
+pub fn render_files(
+    readers: &mut [&mut dyn Read],
+    args: &Args,
+) -> Result<Vec<(PathBuf, String)>, Error> {
+    if args.with_tests && args.output_dir.is_none() {
+        return Err(Error::InvalidOptions(
+            "Foundry tests are only written to --output-dir".to_owned(),
+        ));
+    }
+
+    if args.with_tests && args.emit == Emit::Interface {
+        return Err(Error::InvalidOptions(
+            "Foundry tests are only emitted for bindings".to_owned(),
+        ));
+    }
+
+    if args.emit_abi.is_some() && args.solidity_shape == SolidityShape::Library {
+        return Err(Error::InvalidOptions(
+            "libraries have no ABI, as their functions are internal".to_owned(),
+        ));
+    }
+
+    if args.emit_abi.is_some() && args.output.is_none() && args.output_dir.is_none() {
+        return Err(Error::InvalidOptions(
+            "--emit-abi is written next to --output or into --output-dir".to_owned(),
+        ));
+    }
+
+    if args.emit_abi.is_some() && readers.len() > 1 {
+        return Err(Error::InvalidOptions(
+            "--emit-abi describes a single contract, but several were given".to_owned(),
+        ));
+    }
+
+    let (module, contracts) = render_module(readers, args)?;
+    let name = match &contracts[..] {
+        [contract] => contract.context.module_name.clone(),
+        _ => "Bindings".to_owned(),
+    };
+
+    let mut files = vec![(PathBuf::from(format!("{name}.sol")), module)];
+    if args.with_tests {
+        let tests = render_tests(&name, &contracts, args)?;
+        files.push((PathBuf::from(format!("{name}.t.sol")), tests));
+    }
+
+    if let Some(path) = &args.emit_abi {
+        let abi = contract_abi(&contracts[0], args)?;
+        files.push((path.clone(), serde_json::to_string_pretty(&abi)?));
+    }
+
+    Ok(files)
+}
+
+/// ABI JSON of the functions, events and errors the Solidity code of `contract` declares
+fn contract_abi(contract: &Contract, args: &Args) -> Result<Value, Error> {
+    let registry = contract.registry.borrow();
+    let project = &contract.context.project["spec"];
+    let interface = contract.kind == "interface";
+
+    // Types are converted while rendering, so every type the spec refers to is known
+    let lookup = |id: &Value| registry.lookup(id.as_u64()?.try_into().ok()?);
+    let decodable = |id: &Value| lookup(id).map_or(false, |ty| ty.decoder.is_some());
+    let param = |name: &str, id: &Value| -> Result<Value, Error> {
+        let ty = lookup(id)
+            .ok_or_else(|| Error::Metadata(format!("type {id} has no Solidity counterpart")))?;
+        Ok(abi_param(name, &ty.abi))
+    };
+    let params = |entry: &Value| -> Result<Vec<Value>, Error> {
+        entries(&entry["args"])
+            .map(|arg| param(arg["label"].as_str().unwrap_or("?"), &arg["type"]["type"]))
+            .collect()
+    };
+    let plain = |name: &str, ty: &str| json!({ "name": name, "type": ty });
+
+    let mut abi = Vec::new();
+    if !interface {
+        let mut inputs = vec![plain("_ink_address", "address")];
+        if args.xvm_address_arg {
+            inputs.push(plain("_xvm_address", "address"));
+        }
+
+        abi.push(json!({
+            "type": "constructor",
+            "inputs": inputs,
+            "stateMutability": "nonpayable",
+        }));
+    }
+
+    if !interface && contract.context.custom_errors {
+        let inputs = [plain("data", "bytes")];
+        abi.push(json!({ "type": "error", "name": "InkError", "inputs": inputs }));
+    }
+
+    for event in entries(&project["events"]) {
+        let mut inputs = params(event)?;
+        for (input, arg) in inputs.iter_mut().zip(entries(&event["args"])) {
+            input["indexed"] = json!(arg["indexed"].as_bool().unwrap_or(false));
+        }
+
+        abi.push(json!({
+            "type": "event",
+            "name": event["name"],
+            "inputs": inputs,
+            "anonymous": false,
+        }));
+
+        // Decoders are only declared if every field can be decoded
+        if entries(&event["args"]).all(|arg| decodable(&arg["type"]["type"])) {
+            abi.push(json!({
+                "type": "function",
+                "name": format!("decode{}Event", event["label"].as_str().unwrap_or("?")),
+                "inputs": [plain("topics", "bytes"), plain("data", "bytes")],
+                "outputs": params(event)?,
+                "stateMutability": "pure",
+            }));
+        }
+    }
+
+    for constructor in entries(&project["constructors"]) {
+        let inputs = [plain("code_hash", "bytes32"), plain("salt", "bytes")];
+        let payable = constructor["payable"].as_bool().unwrap_or(false);
+        abi.push(json!({
+            "type": "function",
+            "name": format!("{}_init_data", constructor["name"].as_str().unwrap_or("?")),
+            "inputs": inputs.into_iter().chain(params(constructor)?).collect_vec(),
+            "outputs": [plain("", "bytes")],
+            "stateMutability": if payable { "payable" } else { "pure" },
+        }));
+    }
+
+    // Messages call XVM, hence change state even if read-only in ink!
+    for message in entries(&project["messages"]) {
+        let payable = message["payable"].as_bool().unwrap_or(false);
+        let output = &message["returnType"]["type"];
+        let outputs = match (output.is_null(), decodable(output)) {
+            (true, _) => Vec::new(),
+            (false, true) => vec![param("", output)?],
+            (false, false) => vec![plain("", "bytes")],
+        };
+
+        let mut inputs = params(message)?;
+        if args.weight_args {
+            inputs.push(plain("ref_time", "uint64"));
+        }
+        if args.weight_args && args.xvm_version != XvmVersion::V1 {
+            inputs.push(plain("proof_size", "uint64"));
+        }
+
+        abi.push(json!({
+            "type": "function",
+            "name": message["name"],
+            "inputs": inputs,
+            "outputs": outputs,
+            "stateMutability": match payable && args.xvm_version != XvmVersion::V1 {
+                true => "payable",
+                false => "nonpayable",
+            },
+        }));
+    }
+
+    Ok(Value::Array(abi))
+}
+
+/// Solidity file of [`render_all`] along with the contracts it was rendered from
+fn render_module(
+    readers: &mut [&mut dyn Read],
+    args: &Args,
+) -> Result<(String, Vec<Contract>), Error> {
+    let custom_errors = check_solidity_version(&args.solidity_version)?;
+    let interface = match args.emit {
+        Emit::Bindings => false,
+        Emit::Interface => true,
+        Emit::Skeleton | Emit::Types => {
+            return Err(Error::InvalidOptions(
+                "only bindings and interfaces are emitted in ink-to-evm mode".to_owned(),
+            ))
+        }
+    };
+    if readers.len() > 1 && args.module_name.is_some() {
+        return Err(Error::InvalidOptions(
+            "--module-name names a single contract, but several were given".to_owned(),
+        ));
+    }
+
+    // Only contracts have a constructor to take the address
+    if args.xvm_address_arg && (interface || args.solidity_shape == SolidityShape::Library) {
+        return Err(Error::InvalidOptions(
+            "--xvm-address-arg is only taken by contracts, not libraries or interfaces".to_owned(),
+        ));
+    }
+
+    let mut contracts = readers
+        .iter_mut()
+        .map(|reader| Contract::new(&mut **reader, args))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    for (i, contract) in contracts.iter().enumerate() {
+        let name = &contract.context.module_name;
+        if contracts[..i]
+            .iter()
+            .any(|other| other.context.module_name == *name)
+        {
+            return Err(Error::Metadata(format!(
+                "several contracts are named `{name}`"
+            )));
+        }
+    }
+
+    let mut rendered = contracts
+        .iter()
+        .map(Contract::render)
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    // Interfaces have no definitions worth sharing, as they have no functions, while
+    // libraries can't inherit them
+    let mut codec = String::new();
+    let library = args.solidity_shape == SolidityShape::Library;
+    if contracts.len() > 1 && !interface && !library {
+        // Types are converted while rendering, so only now definitions can be compared
+        let parts = contracts
+            .iter()
+            .map(|contract| contract.registry.borrow().parts())
+            .collect_vec();
+        let shared = shared_definitions(&parts);
+
+        // Without shared definitions there is no base contract to inherit
+        if !shared.is_empty() {
+            for contract in &mut contracts {
+                contract.context.shared = true;
+                *contract.shared.borrow_mut() = shared.iter().cloned().collect();
+            }
+
+            rendered = contracts
+                .iter()
+                .map(Contract::render)
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            // Private functions of the base contract would be out of reach of the contracts
+            let definitions = shared.iter().map(|part| format!("{part}\n")).join("");
+            codec = definitions.replace(") private pure", ") internal pure");
+        }
+    }
+
+    #[derive(Serialize, Debug)]
+    struct ModuleContext {
+        /// SPDX license expression of the file
+        license: String,
+
+        /// Version requirement of `pragma solidity`
+        solidity_version: String,
+
+        /// Whether XVM calls carry a value to transfer, which the legacy interface lacks
+        xvm_value: bool,
+        custom_errors: bool,
+
+        /// Whether the XVM interface is that of v3, see [`RenderContext::xvm_v3`]
+        xvm_v3: bool,
+
+        /// Whether contracts are interfaces, which need no XVM interface
+        interface: bool,
+
+        /// Body of the `ScaleCodec` library, empty for interfaces
+        scale_codec: String,
+
+        /// Body of the base contract, empty unless several contracts share definitions
+        codec: String,
+
+        contracts: Vec<String>,
+    }
+
+    let mut template = TinyTemplate::new();
+    template.set_default_formatter(&tinytemplate::format_unescaped);
+    template.add_template("module", MODULE_TEMPLATE)?;
+
+    let module = template.render(
+        "module",
+        &ModuleContext {
+            license: args.license.clone(),
+            solidity_version: args.solidity_version.requirement.clone(),
+            xvm_value: args.xvm_version != XvmVersion::V1,
+            xvm_v3: args.xvm_version == XvmVersion::V3,
+            custom_errors,
+            interface,
+            scale_codec: match interface {
+                true => String::new(),
+                false => codec_helpers()?.trim_end().to_owned(),
+            },
+            codec,
+            contracts: rendered,
+        },
+    )?;
+
+    // `bytes.concat` came along with custom errors, and packs bytes just like `abi.encodePacked`
+    let module = match custom_errors {
+        true => module,
+        false => module.replace("bytes.concat(", "abi.encodePacked("),
+    };
+
+    Ok((module, contracts))
+}
+
+/// Foundry test of the Solidity file `<name>.sol`, checking the compact, little-endian, `bool`
+/// and byte string encoding and decoding of the codec library and the data of each constructor against SCALE encoding by
+/// `parity-scale-codec`
+fn render_tests(name: &str, contracts: &[Contract], args: &Args) -> Result<String, Error> {
+    #[derive(Serialize)]
+    struct TestContext {
+        license: String,
+        solidity_version: String,
+
+        /// Solidity file under test, next to the test
+        file: String,
+        name: String,
+        library: bool,
+        xvm_address_arg: bool,
+        code_hash: String,
+        salt: String,
+        compact: Vec<CompactCase>,
+
+        /// `u32::MAX` and the value following it, which `decode_compact_u32` rejects
+        compact_u32_max: String,
+        compact_u32_overflow: String,
+        little_endian: Vec<LittleEndianCase>,
+        bools: Vec<BoolCase>,
+        bytes: Vec<BytesCase>,
+        constructors: Vec<ConstructorTest>,
+
+        /// Constructors taking arguments with no sample value, see [`sample_value`]
+        untested: Vec<String>,
+    }
+
+    #[derive(Serialize)]
+    struct CompactCase {
+        value: String,
+        encoded: String,
+        length: usize,
+    }
+
+    #[derive(Serialize)]
+    struct LittleEndianCase {
+        value: String,
+        length: usize,
+        encoded: String,
+    }
+
+    #[derive(Serialize)]
+    struct BoolCase {
+        value: bool,
+        encoded: String,
+    }
+
+    #[derive(Serialize)]
+    struct BytesCase {
+        value: String,
+        encoded: String,
+        length: usize,
+    }
+
+    #[derive(Serialize)]
+    struct ConstructorTest {
+        contract: String,
+        name: String,
+
+        /// Solidity literals of the arguments
+        args: Vec<String>,
+        expected: String,
+    }
+
+    // Either side of the boundaries between the modes of compact encoding
+    let boundaries = [0, 63, 64, 16383, 16384, (1 << 30) - 1, 1 << 30, u128::MAX];
+    let compact = boundaries
+        .iter()
+        .map(|&value| {
+            let encoded = Compact(value).encode();
+            CompactCase {
+                value: value.to_string(),
+                encoded: hex::encode(&encoded),
+                length: encoded.len(),
+            }
+        })
+        .collect();
+
+    // Signed integers are passed as their two's complement, as their encoders cast them
+    let integers = [
+        (0x0102, 0x0102u16.encode()),
+        (1234, 1234u32.encode()),
+        (-2i64 as u64 as u128, (-2i64).encode()),
+        (u128::MAX, u128::MAX.encode()),
+    ];
+    let little_endian = integers
+        .into_iter()
+        .map(|(value, encoded)| LittleEndianCase {
+            value: value.to_string(),
+            length: encoded.len(),
+            encoded: hex::encode(encoded),
+        })
+        .collect();
+
+    let bools = [true, false]
+        .into_iter()
+        .map(|value| BoolCase {
+            value,
+            encoded: hex::encode(value.encode()),
+        })
+        .collect();
+
+    // Empty, and with a single and a two byte compact length
+    let bytes = [vec![], vec![1u8, 2, 3], vec![0xff; 64]]
+        .into_iter()
+        .map(|value| {
+            let encoded = value.encode();
+            BytesCase {
+                value: hex::encode(&value),
+                encoded: hex::encode(&encoded),
+                length: encoded.len(),
+            }
+        })
+        .collect();
+
+    let (code_hash, salt) = ([0xab; 32], b"salt".to_vec());
+    let (mut constructors, mut untested) = (Vec::new(), Vec::new());
+    for contract in contracts {
+        let registry = contract.registry.borrow();
+        let contract_name = &contract.context.module_name;
+        for constructor in entries(&contract.context.project["spec"]["constructors"]) {
+            let name = constructor["name"].as_str().unwrap_or("?").to_owned();
+            let samples = entries(&constructor["args"])
+                .map(|arg| {
+                    let id = arg["type"]["type"].as_u64()?.try_into().ok()?;
+                    sample_value(registry.lookup(id)?)
+                })
+                .collect::<Option<Vec<_>>>();
+
+            let samples = match samples {
+                Some(samples) => samples,
+                None => {
+                    untested.push(format!("{contract_name}.{name}_init_data"));
+                    continue;
+                }
+            };
+
+            let selector = constructor["selector"].as_str().unwrap_or_default();
+            let selector = hex::decode(selector.trim_start_matches("0x")).unwrap_or_default();
+            let encoded_args = samples.iter().flat_map(|(_, encoded)| encoded.clone());
+            let input = selector.into_iter().chain(encoded_args).collect_vec();
+
+            // Nothing is paid, and lengths are compact `u32`
+            let expected = [
+                0u128.encode(),
+                code_hash.to_vec(),
+                Compact(input.len() as u32).encode(),
+                input,
+                Compact(salt.len() as u32).encode(),
+                salt.clone(),
+            ];
+
+            constructors.push(ConstructorTest {
+                contract: contract_name.clone(),
+                name,
+                args: samples.into_iter().map(|(literal, _)| literal).collect(),
+                expected: hex::encode(expected.concat()),
+            });
+        }
+    }
+
+    let mut template = TinyTemplate::new();
+    template.set_default_formatter(&tinytemplate::format_unescaped);
+    template.add_template("test", TEST_TEMPLATE)?;
+
+    let context = TestContext {
+        license: args.license.clone(),
+        solidity_version: args.solidity_version.requirement.clone(),
+        file: format!("{name}.sol"),
+        name: name.to_owned(),
+        library: args.solidity_shape == SolidityShape::Library,
+        xvm_address_arg: args.xvm_address_arg,
+        code_hash: hex::encode(code_hash),
+        salt: hex::encode(&salt),
+        compact,
+        compact_u32_max: hex::encode(Compact(u32::MAX).encode()),
+        compact_u32_overflow: hex::encode(Compact(u32::MAX as u64 + 1).encode()),
+        little_endian,
+        bools,
+        bytes,
+        constructors,
+        untested,
+    };
+
+    Ok(template.render("test", &context)?)
+}
+
+/// Solidity literal of a sample value of the type along with its SCALE encoding, for types
+/// whose values are written as literals: `bool`, integers of up to 128 bits, `string`, `bytes`
+/// and byte arrays, including `AccountId` and `Hash`
+fn sample_value(ty: &EvmType) -> Option<(String, Vec<u8>)> {
+    // Distinct bytes, so that any of them out of place shows
+    let bytes = |length: usize| (1..=length).map(|byte| byte as u8).collect_vec();
+    let literal = |bytes: &[u8]| format!("hex\"{}\"", hex::encode(bytes));
+    let fixed = |length: usize| {
+        let value = bytes(length);
+        let encoded = value.iter().flat_map(Encode::encode).collect();
+        (literal(&value), encoded)
+    };
+
+    let name = ty.name();
+    let bits = |prefix: &str| name.strip_prefix(prefix)?.parse::<usize>().ok();
+
+    Some(match (name.as_str(), bits("uint"), bits("int")) {
+        ("bool", ..) => ("true".to_owned(), true.encode()),
+        ("string", ..) => ("\"ink!\"".to_owned(), "ink!".encode()),
+        ("bytes", ..) => (literal(&bytes(3)), bytes(3).encode()),
+        ("AccountId" | "Hash", ..) => fixed(32),
+
+        // Most significant byte first in the literal, last in the encoding
+        (_, Some(bits), _) => {
+            let value = bytes(bits / 8);
+            let number = value.iter().fold(0, |n, &byte| n << 8 | byte as u128);
+            let encoded = match bits {
+                8 => (number as u8).encode(),
+                16 => (number as u16).encode(),
+                32 => (number as u32).encode(),
+                64 => (number as u64).encode(),
+                128 => number.encode(),
+                _ => return None,
+            };
+
+            (format!("0x{}", hex::encode(value)), encoded)
+        }
+
+        // Negative, so that two's complement shows
+        (_, _, Some(bits)) => {
+            let encoded = match bits {
+                8 => (-2i8).encode(),
+                16 => (-2i16).encode(),
+                32 => (-2i32).encode(),
+                64 => (-2i64).encode(),
+                128 => (-2i128).encode(),
+                _ => return None,
+            };
+
+            ("-2".to_owned(), encoded)
+        }
+
+        _ => fixed(name.strip_prefix("bytes")?.parse().ok()?),
+    })
+}
+
+/// Whether compilers admitted by `--solidity-version` all have custom errors and
+/// `bytes.concat`, both of Solidity 0.8.4. Those before 0.8.0 are rejected, as generated
+/// code relies on checked arithmetic.
+fn check_solidity_version(version: &SolidityVersion) -> Result<bool, Error> {
+    if version.minimum < (0, 8, 0) {
+        return Err(Error::InvalidOptions(format!(
+            "--solidity-version `{}` admits compilers before 0.8.0, whose arithmetic is unchecked",
+            version.requirement
+        )));
+    }
+
+    Ok(version.minimum >= (0, 8, 4))
+}
+
+/// Adds Solidity names to constructors, messages and events, and escapes names of their
+/// arguments, see [`solidity_identifier`]. Trait messages are labelled like `PSP22::transfer`,
+/// the trait is only kept, as in `PSP22_transfer`, if the name would be ambiguous otherwise.
+fn with_names(project: &mut Value) {
+    let unqualified = |label: &str| label.rsplit("::").next().unwrap_or(label).to_owned();
+
+    for key in ["constructors", "messages", "events"] {
+        let names = entries(&project["spec"][key])
+            .map(|entry| unqualified(entry["label"].as_str().unwrap_or("?")))
+            .collect_vec();
+
+        for entry in project["spec"][key].as_array_mut().into_iter().flatten() {
+            let label = entry["label"].as_str().unwrap_or("?");
+            let mut name = unqualified(label);
+            if names.iter().filter(|&other| *other == name).count() > 1 {
+                name = label.replace("::", "_");
+            }
+
+            entry["name"] = json!(solidity_identifier(&name));
+            if key != "events" {
+                entry["selector_constant"] = json!(format!("{}_SELECTOR", name.to_uppercase()));
+            }
+
+            for arg in entry["args"].as_array_mut().into_iter().flatten() {
+                let label = arg["label"].as_str().unwrap_or("?");
+                arg["label"] = json!(match GENERATED_LOCALS.contains(&label) {
+                    true => format!("{label}_"),
+                    false => solidity_identifier(label),
+                });
+            }
+        }
+    }
+}
+
+/// Doc lines of ink! metadata as lines of a NatSpec comment. Lines are trimmed and blank ones
+/// dropped, `@` is spelled out so that no text is taken for a NatSpec tag.
+fn natspec(docs: &Value) -> Vec<String> {
+    entries(docs)
+        .filter_map(Value::as_str)
+        .flat_map(str::lines)
+        .map(|line| line.trim().replace('@', "(at)"))
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Adds NatSpec `@notice` lines to constructors, messages and events, naming the ink! label
+/// and selector of undocumented ones, and the description of each documented event field
+fn with_natspec(project: &mut Value) {
+    let kinds = [
+        ("constructor", "constructors"),
+        ("message", "messages"),
+        ("event", "events"),
+    ];
+
+    for (kind, key) in kinds {
+        for entry in project["spec"][key].as_array_mut().into_iter().flatten() {
+            let mut lines = natspec(&entry["docs"]);
+            if lines.is_empty() {
+                let label = entry["label"].as_str().unwrap_or("?");
+                lines.push(match entry["selector"].as_str() {
+                    Some(selector) => format!("ink! {kind} `{label}` with selector {selector}"),
+                    None => format!("ink! {kind} `{label}`"),
+                });
+            }
+
+            lines[0].insert_str(0, "@notice ");
+            entry["natspec"] = json!(lines);
+
+            for arg in entry["args"].as_array_mut().into_iter().flatten() {
+                arg["natspec"] = json!(natspec(&arg["docs"]).join(" "));
+            }
+        }
+    }
+}
+
+/// Serializes the project for the module template, dropping arguments of zero sized types,
+/// which are encoded as no bytes at all, and return types of messages returning nothing
+fn without_units(context: &Context) -> Result<Value, Error> {
+    let mut project = serde_json::to_value(&*context.project)?;
+    let unit = |ty: &Value| match ty.as_u64().and_then(|id| id.try_into().ok()) {
+        Some(id) => is_zero_sized(context, id),
+        None => true,
+    };
+
+    for key in ["constructors", "messages", "events"] {
+        for entry in project["spec"][key].as_array_mut().into_iter().flatten() {
+            if key == "messages" && unit(&entry["returnType"]["type"]) {
+                entry["returnType"] = Value::Null;
+            }
+
+            if let Some(args) = entry["args"].as_array_mut() {
+                args.retain(|arg| !unit(&arg["type"]["type"]));
+            }
+        }
+    }
+
+    Ok(project)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use scale_info::{meta_type, PortableRegistry, Registry};
+
+    /// Context of a project with the given types and a message taking none of them
+    fn registry_context(types: Value) -> Context {
+        let metadata = message_metadata(types, json!([]), 0);
+        let project = ink_project(&metadata, None).unwrap();
+        Context::new(Rc::new(project), HashMap::new())
+    }
+
+    #[test]
+    fn type_registry() {
+        let mut ink_registry = Registry::new();
+        let unit_type_id = ink_registry.register_type(&meta_type::<()>()).id();
+        let array_type_id = ink_registry.register_type(&meta_type::<[u8; 20]>()).id();
+        let byte_type_id = ink_registry.register_type(&meta_type::<u8>()).id();
+        assert_eq!(unit_type_id, 0);
+
+        let ink_registry: PortableRegistry = ink_registry.into();
+        let types = serde_json::to_value(&ink_registry).unwrap();
+        let context = registry_context(types["types"].clone());
+        let mut evm_registry = EvmTypeRegistry::new(&context);
+
+        let reference = |id| evm_registry.lookup(id).map(|ty| ty.reference.clone());
+        assert_eq!(reference(array_type_id).as_deref(), Some("bytes20"));
+        assert_eq!(reference(byte_type_id).as_deref(), Some("uint8"));
+
+        // Converted types are only defined once used
+        assert!(evm_registry.ordered().is_empty());
+        evm_registry.use_type(array_type_id, &context).unwrap();
+        let ordered: Vec<_> = evm_registry.ordered().iter().map(|(id, _)| *id).collect();
+        assert_eq!(ordered, [byte_type_id, array_type_id]);
+    }
+
+    #[test]
+    fn interdependent_types() {
+        let composite = |id: u32, name: &str, field: u32| {
+            json!({
+                "id": id,
+                "type": {
+                    "def": { "composite": { "fields": [{ "name": "value", "type": field }] } },
+                    "path": ["sample", name],
+                },
+            })
+        };
+
+        // Types refer to those defined after them, and `Node` to itself through `Vec<Node>`
+        let types = json!([
+            { "id": 0, "type": { "def": { "tuple": [] } } },
+            composite(1, "Outer", 2),
+            composite(2, "Middle", 3),
+            composite(3, "Inner", 4),
+            { "id": 4, "type": { "def": { "primitive": "u32" } } },
+            composite(5, "Node", 6),
+            { "id": 6, "type": { "def": { "sequence": { "type": 5 } } } },
+        ]);
+
+        let context = registry_context(types);
+        let mut evm_registry = EvmTypeRegistry::new(&context);
+        for id in 1..=4 {
+            assert!(evm_registry.lookup(id).is_some(), "type {id} not converted");
+        }
+        assert!(evm_registry.lookup(5).is_none());
+        assert!(evm_registry.lookup(6).is_none());
+
+        evm_registry.use_type(1, &context).unwrap();
+        let ordered: Vec<_> = evm_registry.ordered().iter().map(|(id, _)| *id).collect();
+        assert_eq!(ordered, [4, 3, 2, 1]);
+
+        let definitions = evm_registry.definitions(&HashSet::new());
+        let position = |needle: &str| definitions.find(needle).unwrap();
+        assert!(position("struct sample_Inner {") < position("struct sample_Middle {"));
+        assert!(position("struct sample_Middle {") < position("struct sample_Outer {"));
+        assert!(!definitions.contains("sample_Node"));
+    }
+
+    #[test]
+    fn checksum() {
+        assert_eq!(
+            checksum_address(&hex_literal("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed")),
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        );
+        assert_eq!(
+            checksum_address(&hex_literal("0000000000000000000000000000000000005005")),
+            "0x0000000000000000000000000000000000005005"
+        );
+    }
+
+    fn sample(name: &str) -> Value {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("samples")
+            .join(format!("{name}.json"));
+
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    fn render_sample(name: &str) -> String {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("samples")
+            .join(format!("{name}.json"));
+        let args = Args::parse_from(["sumi", "--mode", "ink-to-evm"]);
+
+        render(&mut std::fs::File::open(path).unwrap(), &args).unwrap()
+    }
+
+    /// Output of V4 and later metadata as if messages returned their output unwrapped
+    fn without_lang_error(rendered: &str) -> String {
+        let check = "\n\n        \
+            // Output is wrapped into `Result<_, LangError>`, which is only `Err` if ink! couldn't read the input\n        \
+            require(data.length > 0 && uint8(data[0]) == 0, \"LangError: CouldNotReadInput\");";
+
+        rendered
+            .replace(check, "")
+            .replace("(data, 1);", "(data, 0);")
+    }
+
+    #[test]
+    fn metadata_v4() {
+        let v3 = v3_metadata(&sample("ink-erc20"), None).unwrap();
+        let v4 = v3_metadata(&sample("ink-erc20-v4"), None).unwrap();
+
+        let labels = |entries: &Value| {
+            entries
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| entry["label"].as_str().unwrap().to_owned())
+                .collect_vec()
+        };
+
+        // Constructors lose their `ConstructorResult`
+        assert_eq!(labels(&v4["spec"]["constructors"]), ["new"]);
+        assert_eq!(v4["spec"]["constructors"], v3["spec"]["constructors"]);
+
+        // Messages return the type wrapped into `MessageResult`
+        assert_eq!(
+            labels(&v4["spec"]["messages"]),
+            ["total_supply", "balance_of", "allowance", "transfer", "approve", "transfer_from"]
+        );
+        assert_eq!(v4["spec"]["messages"][0]["returnType"]["type"], 0);
+        assert_eq!(v4["spec"]["messages"][3]["returnType"]["type"], 8);
+        assert_eq!(v4["spec"]["messages"][3]["returnType"]["displayName"], json!(["Result"]));
+        assert!(v4["spec"]["messages"][0].get("default").is_none());
+
+        assert_eq!(labels(&v4["spec"]["events"]), ["Transfer", "Approval"]);
+        assert_eq!(v4["spec"]["events"], v3["spec"]["events"]);
+        assert!(v4["spec"].get("lang_error").is_none());
+
+        // Both deserialize and translate the same, apart from V4 output being wrapped
+        serde_json::from_value::<InkProject>(v4).unwrap();
+        assert_eq!(
+            without_lang_error(&render_sample("ink-erc20-v4")),
+            render_sample("ink-erc20")
+        );
+
+        assert!(matches!(
+            v3_metadata(&json!({ "version": "2" }), None),
+            Err(Error::Metadata(_))
+        ));
+    }
+
+    #[test]
+    fn metadata_v5() {
+        let v5_metadata = sample("ink-erc20-v5");
+        assert_eq!(metadata_version(&v5_metadata).unwrap(), MetadataVersion::V5);
+        assert_eq!(metadata_version(&sample("ink-erc20-v4")).unwrap(), MetadataVersion::V4);
+        assert_eq!(metadata_version(&sample("ink-erc20")).unwrap(), MetadataVersion::V3);
+
+        // Event signature topics are dropped
+        let v5 = v3_metadata(&v5_metadata, None).unwrap();
+        let v3 = v3_metadata(&sample("ink-erc20"), None).unwrap();
+        assert_eq!(v5["spec"], v3["spec"]);
+
+        serde_json::from_value::<InkProject>(v5).unwrap();
+
+        // Events are no longer prefixed with their index
+        let v3 = render_sample("ink-erc20");
+        let unprefixed = v3
+            .lines()
+            .filter(|line| !line.contains("require(uint8(data[0])") && line.trim() != "offset = 1;")
+            .join("\n");
+        assert_ne!(unprefixed, v3.lines().join("\n"));
+        let v5 = without_lang_error(&render_sample("ink-erc20-v5"));
+        assert_eq!(v5.lines().join("\n"), unprefixed);
+
+        // Metadata without version is read as the given one
+        let mut bare = v5_metadata.clone();
+        bare.as_object_mut().unwrap().remove("version");
+        assert!(metadata_version(&bare).is_err());
+        assert_eq!(
+            v3_metadata(&bare, Some(MetadataVersion::V5)).unwrap(),
+            v3_metadata(&v5_metadata, None).unwrap()
+        );
+
+        let error = metadata_version(&json!({ "version": 6 })).unwrap_err().to_string();
+        assert!(error.contains("version 6 is not supported"));
+        assert!(error.contains("supported versions are 3, 4 and 5"));
+    }
+
+    #[test]
+    fn contract_bundle() {
+        let path =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/ink-erc20.contract");
+        let args = Args::parse_from(["sumi", "--mode", "ink-to-evm"]);
+        let rendered = render(&mut std::fs::File::open(path).unwrap(), &args).unwrap();
+
+        assert!(rendered.contains("contract erc20 {"));
+        assert!(rendered.contains(
+            "// Calls ink! contract with code hash 0x0c653052f3e167c2ec6b4f9b3b2e06ddf4e2b53470ead4c0f5cc2695fc8dc0b7"
+        ));
+        assert!(!rendered.contains("0x0061736d"));
+        assert_eq!(rendered, render_sample("ink-erc20-v4"));
+    }
+
+    #[test]
+    fn bare_metadata() {
+        let rendered = render_sample("ink-erc20");
+
+        let v3 = sample("ink-erc20");
+        let project = serde_json::to_string(&ink_project(&v3["V3"], None).unwrap()).unwrap();
+        assert_eq!(project, serde_json::to_string(&ink_project(&v3, None).unwrap()).unwrap());
+
+        // Bare V4 layout, also lacking the version field
+        let mut v4 = sample("ink-erc20-v4");
+        v4.as_object_mut().unwrap().remove("version");
+        assert!(ink_project(&v4, None).is_ok());
+
+        // Bare project lacks the contract name and code hash
+        let args = Args::parse_from(["sumi", "--mode", "ink-to-evm", "--module-name", "erc20"]);
+        let bare = serde_json::to_vec(&v3["V3"]).unwrap();
+        let bare_rendered = render(&mut bare.as_slice(), &args).unwrap();
+        assert!(!bare_rendered.contains("code hash"));
+        let comment = format!(
+            "// Calls ink! contract with code hash {}\n",
+            v3["source"]["hash"].as_str().unwrap()
+        );
+        assert_eq!(bare_rendered, rendered.replace(&comment, ""));
+
+        let error = ink_project(&json!({ "abi": [], "bytecode": "0x" }), None)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("found top level keys [`abi`, `bytecode`]"));
+    }
+
+    fn hex_literal(digits: &str) -> [u8; 20] {
+        hex::decode(digits).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn encode() {
+        use parity_scale_codec::Encode;
+        dbg!([(1u8, 2u8), (3u8, 4u8)].encode().bytes());
+        dbg!(vec![1u8, 2, 3, 4, 5].encode().bytes());
+    }
+
+    /// V3 metadata of a contract with a single `call` message taking `args`
+    /// of the given `types` and returning type id `returns`
+    fn message_metadata(types: Value, args: Value, returns: u32) -> Value {
+        json!({
+            "contract": { "name": "sample" },
+            "V3": {
+                "spec": {
+                    "constructors": [],
+                    "docs": [],
+                    "events": [],
+                    "messages": [{
+                        "args": args,
+                        "docs": [],
+                        "label": "call",
+                        "mutates": true,
+                        "payable": false,
+                        "returnType": { "displayName": [], "type": returns },
+                        "selector": "0x00000001",
+                    }],
+                },
+                "storage": { "struct": { "fields": [] } },
+                "types": types,
+            },
+        })
+    }
+
+    fn try_render(metadata: &Value) -> Result<String, Error> {
+        try_render_with(metadata, &[])
+    }
+
+    /// Renders `metadata` with the given command line options added
+    fn try_render_with(metadata: &Value, options: &[&str]) -> Result<String, Error> {
+        let args = ["sumi", "--mode", "ink-to-evm"].iter().chain(options);
+        let args = Args::parse_from(args);
+        render(&mut serde_json::to_vec(metadata).unwrap().as_slice(), &args)
+    }
+
+    fn try_render_message(types: Value, args: Value, returns: u32) -> Result<String, Error> {
+        try_render(&message_metadata(types, args, returns))
+    }
+
+    fn render_message(types: Value, args: Value, returns: u32) -> String {
+        try_render_message(types, args, returns).unwrap()
+    }
+
+    fn arg(label: &str, ty: u32) -> Value {
+        json!({ "label": label, "type": { "displayName": [], "type": ty } })
+    }
+
+    #[test]
+    fn option() {
+        let option = |id: u32, value: u32| {
+            json!({
+                "id": id,
+                "type": {
+                    "def": { "variant": { "variants": [
+                        { "index": 0, "name": "None" },
+                        { "fields": [{ "type": value }], "index": 1, "name": "Some" },
+                    ]}},
+                    "params": [{ "name": "T", "type": value }],
+                    "path": ["Option"],
+                },
+            })
+        };
+
+        let rendered = render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "u32" } } },
+                option(2, 1),
+                option(3, 2),
+            ]),
+            json!([arg("amount", 2), arg("nested", 3)]),
+            0,
+        );
+
+        assert!(rendered.contains("Option_uint32 memory amount"));
+        assert!(rendered.contains("Option_Option_uint32 memory nested"));
+        assert!(rendered.contains("struct Option_uint32 {\n        bool isSome;\n        uint32 value;\n    }"));
+        assert!(rendered.contains("struct Option_Option_uint32 {\n        bool isSome;\n        Option_uint32 value;\n    }"));
+        assert!(rendered.contains("return bytes.concat(hex\"01\", encode_uint32(value.value));"));
+        assert!(rendered.contains("return bytes.concat(hex\"01\", encode_Option_uint32(value.value));"));
+        assert!(!rendered.contains("enum Option"));
+    }
+
+    #[test]
+    fn result() {
+        let result = |id: u32, ok: u32, err: u32| {
+            json!({
+                "id": id,
+                "type": {
+                    "def": { "variant": { "variants": [
+                        { "fields": [{ "type": ok }], "index": 0, "name": "Ok" },
+                        { "fields": [{ "type": err }], "index": 1, "name": "Err" },
+                    ]}},
+                    "params": [{ "name": "T", "type": ok }, { "name": "E", "type": err }],
+                    "path": ["Result"],
+                },
+            })
+        };
+
+        let types = |returns: Value| {
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "u32" } } },
+                {
+                    "id": 2,
+                    "type": {
+                        "def": { "variant": { "variants": [
+                            { "index": 0, "name": "InsufficientBalance" },
+                            { "index": 1, "name": "Unauthorized" },
+                        ]}},
+                        "path": ["MyError"],
+                    },
+                },
+                { "id": 3, "type": { "def": { "tuple": [] } } },
+                returns,
+            ])
+        };
+
+        let rendered = render_message(types(result(4, 1, 2)), json!([]), 4);
+
+        assert!(rendered.contains("returns (uint32)"));
+        assert!(rendered.contains(
+            "(uint32 output, ) = decode_Result_uint32_MyError(data, 0);\n        return output;"
+        ));
+        assert!(rendered.contains(
+            "function decode_Result_uint32_MyError(bytes memory data, uint256 offset) private pure returns (uint32, uint256) {"
+        ));
+        assert!(rendered.contains("return decode_uint32(data, offset + 1);"));
+        assert!(rendered.contains("revert(\"MyError::InsufficientBalance\");"));
+        assert!(rendered.contains("revert(\"MyError::Unauthorized\");"));
+        assert!(rendered.contains("revert InkError(data);"));
+        assert!(rendered.contains("ScaleCodec.from_little_endian(data, offset, 4))), offset + 4);"));
+
+        // `Result<(), E>` returns `true` on success
+        let rendered = render_message(types(result(4, 3, 2)), json!([]), 4);
+        assert!(rendered.contains("returns (bool)"));
+        assert!(rendered.contains("(bool output, ) = decode_Result_unit_MyError(data, 0);"));
+        assert!(rendered.contains("return (true, offset + 1);"));
+    }
+
+    #[test]
+    fn sequence() {
+        let rendered = render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "u32" } } },
+                { "id": 2, "type": { "def": { "sequence": { "type": 1 } } } },
+                {
+                    "id": 3,
+                    "type": {
+                        "def": { "composite": { "fields": [
+                            { "name": "x", "type": 1 },
+                            { "name": "y", "type": 1 },
+                        ]}},
+                        "path": ["sample", "Point"],
+                    },
+                },
+                { "id": 4, "type": { "def": { "sequence": { "type": 3 } } } },
+            ]),
+            json!([arg("values", 2), arg("points", 4)]),
+            0,
+        );
+
+        assert!(rendered.contains("uint32[] memory values"));
+        assert!(rendered.contains("sample_Point[] memory points"));
+        assert!(rendered.contains("encode_Vec_uint32(values)"));
+        assert!(rendered.contains("encode_Vec_sample_Point(points)"));
+        assert!(rendered.contains(
+            "function encode_Vec_uint32(uint32[] memory value) private pure returns (bytes memory) {"
+        ));
+        assert!(rendered.contains("buffer = bytes.concat(buffer, encode_uint32(value[i]));"));
+        assert!(rendered.contains("buffer = bytes.concat(buffer, encode_sample_Point(value[i]));"));
+        assert!(rendered.contains("return ScaleCodec.little_endian(uint256(uint32(value)), 4);"));
+        assert!(rendered.contains("function encode_compact(uint256 value) internal pure"));
+    }
+
+    /// Foundry test written along with the sample by `--with-tests`
+    fn foundry_test(name: &str) -> String {
+        let args = "sumi --mode ink-to-evm --output-dir out --with-tests".split(' ');
+        let metadata = serde_json::to_vec(&sample(name)).unwrap();
+        let reader: &mut dyn Read = &mut metadata.as_slice();
+        let mut files = render_files(&mut [reader], &Args::parse_from(args)).unwrap();
+        files.remove(1).1
+    }
+
+    /// Rust copy of `little_endian` of the generated `ScaleCodec` library
+    fn little_endian(value: u128, length: usize) -> Vec<u8> {
+        (0..length)
+            .map(|i| value.checked_shr(8 * i as u32).unwrap_or(0) as u8)
+            .collect()
+    }
+
+    /// Rust copy of `encode_compact` of the generated `ScaleCodec` library, the Solidity code
+    /// itself is run by [`forge`]
+    fn solidity_compact(value: u128) -> Vec<u8> {
+        match value {
+            value if value < 1 << 6 => little_endian(value << 2, 1),
+            value if value < 1 << 14 => little_endian((value << 2) | 0x01, 2),
+            value if value < 1 << 30 => little_endian((value << 2) | 0x02, 4),
+            value => {
+                let length = (128 - value.leading_zeros() as usize + 7) / 8;
+                [
+                    little_endian((((length - 4) << 2) | 0x03) as u128, 1),
+                    little_endian(value, length),
+                ]
+                .concat()
+            }
+        }
+    }
+
+    #[test]
+    fn byte_vectors() {
+        let rendered = render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "u8" } } },
+                { "id": 2, "type": { "def": { "sequence": { "type": 1 } } } },
+            ]),
+            json!([arg("data", 2)]),
+            0,
+        );
+
+        assert!(rendered.contains("bytes memory data"));
+        assert!(rendered.contains("encode_bytes(data)"));
+        assert!(rendered.contains("return ScaleCodec.encode_bytes(bytes(value));"));
+        assert!(rendered.contains("return bytes.concat(encode_compact(value.length), value);"));
+        assert!(!rendered.contains("uint8[]"));
+
+        // The Foundry test runs the emitted encoder against `parity-scale-codec`
+        let test = foundry_test("ink-erc20");
+        assert!(test.contains("function test_encode_bytes() public {"));
+        assert!(test.contains("assertEq(ScaleCodec.encode_bytes(hex\"\"), hex\"00\");"));
+        assert!(test.contains("assertEq(ScaleCodec.encode_bytes(hex\"010203\"), hex\"0c010203\");"));
+        let data = "ff".repeat(64);
+        let long = format!("encode_bytes(hex\"{data}\"), hex\"0101{data}\");");
+        assert!(test.contains(&long));
+    }
+
+    #[test]
+    fn strings() {
+        let rendered = render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "str" } } },
+            ]),
+            json!([arg("name", 1)]),
+            0,
+        );
+
+        assert!(rendered.contains("string memory name"));
+        assert!(rendered.contains("encode_string(name)"));
+        assert!(rendered.contains(
+            "function encode_string(string memory value) private pure returns (bytes memory) {"
+        ));
+        assert!(rendered.contains("bytes memory data = bytes(value);"));
+
+        use parity_scale_codec::Encode;
+        for value in ["hello".to_owned(), "x".repeat(200)] {
+            let length = solidity_compact(value.len() as u128);
+            let bytes = value.as_bytes().to_vec();
+            assert_eq!([length, bytes].concat(), value.encode());
+        }
+
+        // Longer than 63 bytes takes the two byte compact form
+        assert_eq!(solidity_compact(200), [((200 << 2) | 0x01) as u8, 0x03]);
+    }
+
+    #[test]
+    fn storage_types() {
+        let types = json!([
+            { "id": 0, "type": { "def": { "primitive": "bool" } } },
+            { "id": 1, "type": { "def": { "primitive": "u32" } } },
+            {
+                "id": 2,
+                "type": {
+                    "def": { "composite": { "fields": [{ "name": "offset_key", "type": 1 }] } },
+                    "params": [{ "name": "K", "type": 1 }, { "name": "V", "type": 1 }],
+                    "path": ["ink_storage", "lazy", "mapping", "Mapping"],
+                },
+            },
+            {
+                "id": 3,
+                "type": {
+                    "def": { "composite": { "fields": [
+                        { "name": "total", "type": 1 },
+                        { "name": "balances", "type": 2 },
+                    ]}},
+                    "path": ["sample", "Ledger"],
+                },
+            },
+        ]);
+
+        let error = try_render_message(types.clone(), json!([arg("ledger", 3)]), 0).unwrap_err();
+        assert!(matches!(
+            &error,
+            Error::UnsupportedType { path, context, .. }
+                if path == "ink_storage::lazy::mapping::Mapping"
+                    && context == "argument `ledger` of message `call`"
+        ));
+
+        let error = try_render_message(types.clone(), json!([]), 3).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unsupported type `ink_storage::lazy::mapping::Mapping` in return type of message \
+             `call`: storage-only types have no SCALE encoding"
+        );
+
+        assert!(try_render_message(types, json!([arg("total", 1)]), 0).is_ok());
+    }
+
+    #[test]
+    fn account_id() {
+        let rendered = render_sample("ink-erc20");
+
+        assert!(rendered.contains("bytes32  owner"));
+        assert!(rendered.contains("encode_AccountId(owner)"));
+        assert!(rendered.contains("function encode_AccountId(bytes32 value)"));
+        assert!(rendered.contains("function to_account_id(address account) internal view"));
+        assert!(!rendered.contains("struct ink_env_types_AccountId"));
+
+        let rendered = render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "u8" } } },
+                { "id": 2, "type": { "def": { "array": { "len": 32, "type": 1 } } } },
+                {
+                    "id": 3,
+                    "type": {
+                        "def": { "composite": { "fields": [{ "type": 2 }] } },
+                        "path": ["ink_env", "types", "AccountId"],
+                    },
+                },
+                { "id": 4, "type": { "def": { "sequence": { "type": 3 } } } },
+            ]),
+            json!([arg("accounts", 4)]),
+            0,
+        );
+
+        assert!(rendered.contains("bytes32[] memory accounts"));
+        assert!(rendered.contains("buffer = bytes.concat(buffer, encode_AccountId(value[i]));"));
+
+        // Initial BLAKE2b-256 state `to_account_id` compresses the address with
+        let iv: [u64; 8] = [
+            0x6a09e667f3bcc908 ^ 0x01010020,
+            0xbb67ae8584caa73b,
+            0x3c6ef372fe94f82b,
+            0xa54ff53a5f1d36f1,
+            0x510e527fade682d1,
+            0x9b05688c2b3e6c1f,
+            0x1f83d9abfb41bd6b,
+            0x5be0cd19137e2179,
+        ];
+
+        let state = hex::encode(iv.iter().flat_map(|word| word.to_le_bytes()).collect_vec());
+        assert!(rendered.contains(&format!("hex\"{state}\"")));
+    }
+
+    #[test]
+    fn environment_types() {
+        let rendered = render_sample("ink-erc20-v4");
+        assert!(rendered.contains("/// @param to AccountId\n    /// @param value Balance"));
+        assert!(rendered.contains("uint128  value"));
+
+        // Timestamp, block number and hash only appear in the environment section
+        let mut metadata = sample("ink-erc20-v4");
+        let message = json!({
+            "args": [
+                { "label": "at", "type": { "displayName": ["Timestamp"], "type": 16 } },
+                { "label": "block", "type": { "displayName": ["BlockNumber"], "type": 17 } },
+                { "label": "code", "type": { "displayName": ["Hash"], "type": 18 } },
+            ],
+            "docs": [],
+            "label": "schedule",
+            "mutates": true,
+            "payable": false,
+            "returnType": { "displayName": ["ink", "MessageResult"], "type": 14 },
+            "selector": "0x00000001",
+        });
+        let messages = metadata["spec"]["messages"].as_array_mut().unwrap();
+        messages.push(message);
+        let rendered = try_render(&metadata).unwrap();
+
+        assert!(rendered.contains("uint64  at"));
+        assert!(rendered.contains("uint32  block"));
+        assert!(rendered.contains("bytes32  code"));
+        assert!(rendered.contains("/// @param at Timestamp\n    /// @param block BlockNumber"));
+        assert!(rendered.contains("encode_Hash(code)"));
+        assert!(!rendered.contains("struct ink_primitives_types_Hash"));
+    }
+
+    #[test]
+    fn hash() {
+        let rendered = render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "u8" } } },
+                { "id": 2, "type": { "def": { "array": { "len": 32, "type": 1 } } } },
+                {
+                    "id": 3,
+                    "type": {
+                        "def": { "composite": { "fields": [{ "type": 2 }] } },
+                        "path": ["ink_env", "types", "Hash"],
+                    },
+                },
+            ]),
+            json!([arg("code", 3)]),
+            0,
+        );
+
+        assert!(rendered.contains("bytes32  code"));
+        assert!(rendered.contains("encode_Hash(code)"));
+        assert!(rendered.contains(
+            "function encode_Hash(bytes32 value) private pure returns (bytes memory) {\n        \
+             return abi.encodePacked(value);"
+        ));
+        assert!(!rendered.contains("struct ink_env_types_Hash"));
+    }
+
+    #[test]
+    fn compact() {
+        let rendered = render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "u128" } } },
+                { "id": 2, "type": { "def": { "compact": { "type": 1 } } } },
+            ]),
+            json!([arg("amount", 2)]),
+            0,
+        );
+
+        assert!(rendered.contains("uint128  amount"));
+        assert!(rendered.contains("encode_Compact_uint128(amount)"));
+        assert!(rendered.contains(
+            "function encode_Compact_uint128(uint128 value) private pure returns (bytes memory) {\n        \
+             return ScaleCodec.encode_compact_u128(value);"
+        ));
+
+        // `u32` and `u128` go through helpers of their own, other widths are range checked
+        let types = json!([
+            { "id": 0, "type": { "def": { "primitive": "u32" } } },
+            { "id": 1, "type": { "def": { "compact": { "type": 0 } } } },
+            { "id": 2, "type": { "def": { "primitive": "u64" } } },
+            { "id": 3, "type": { "def": { "compact": { "type": 2 } } } },
+        ]);
+
+        let rendered = render_message(types.clone(), json!([arg("count", 1)]), 1);
+        assert!(rendered.contains("return ScaleCodec.encode_compact_u32(value);"));
+        assert!(rendered.contains("(uint32 output, ) = decode_Compact_uint32(data, 0);"));
+        assert!(rendered.contains("return ScaleCodec.decode_compact_u32(data, offset);"));
+        assert!(rendered.contains(
+            "function decode_compact_u32(bytes memory data, uint256 offset) \
+             internal pure returns (uint32, uint256) {\n        \
+             (uint256 value, uint256 next) = decode_compact(data, offset);\n        \
+             require(value <= type(uint32).max, \"compact u32 out of range\");"
+        ));
+
+        let rendered = render_message(types, json!([]), 3);
+        assert!(rendered.contains("return ScaleCodec.encode_compact(value);"));
+        assert!(rendered.contains(
+            "(uint256 value, uint256 next) = ScaleCodec.decode_compact(data, offset);\n        \
+             require(value <= type(uint64).max, \"Compact_uint64 out of range\");\n        \
+             return (uint64(value), next);"
+        ));
+
+        // The Foundry test runs the emitted codec on either side of the boundaries between the
+        // modes, and expects `decode_compact_u32` to revert past `u32::MAX`
+        let test = foundry_test("ink-erc20");
+        assert!(test.contains("assertEq(ScaleCodec.encode_compact(63), hex\"fc\");"));
+        assert!(test.contains("assertEq(ScaleCodec.encode_compact(16384), hex\"02000100\");"));
+        assert!(test.contains(
+            "assertEq(this.decode_compact_u32(hex\"03ffffffff\"), type(uint32).max);\n        \
+             vm.expectRevert(bytes(\"compact u32 out of range\"));\n        \
+             this.decode_compact_u32(hex\"070000000001\");"
+        ));
+    }
+
+    #[test]
+    fn bit_sequence() {
+        let types = json!([
+            { "id": 0, "type": { "def": { "primitive": "bool" } } },
+            { "id": 1, "type": { "def": { "primitive": "u8" } } },
+            {
+                "id": 2,
+                "type": { "def": { "bitsequence": { "bit_order_type": 3, "bit_store_type": 1 } } },
+            },
+            {
+                "id": 3,
+                "type": { "def": { "composite": {} }, "path": ["bitvec", "order", "Lsb0"] },
+            },
+            {
+                "id": 4,
+                "type": {
+                    "def": { "composite": { "fields": [{ "name": "flags", "type": 2 }] } },
+                    "path": ["sample", "Settings"],
+                },
+            },
+        ]);
+
+        let error = try_render_message(types, json!([arg("settings", 4)]), 0).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unsupported type `BitSequence` in field `flags` of argument `settings` of message \
+             `call`: bit sequences have no Solidity counterpart"
+        );
+
+        let types = json!([
+            { "id": 0, "type": { "def": { "primitive": "bool" } } },
+            { "id": 1, "type": { "def": { "quantum": { "type": 0 } } } },
+        ]);
+
+        let error = try_render_message(types, json!([arg("state", 1)]), 0).unwrap_err();
+        assert!(matches!(error, Error::UnknownTypeDef { .. }));
+        assert_eq!(
+            error.to_string(),
+            "unknown TypeDef `quantum` of type 1, only those of scale-info 2 are supported"
+        );
+    }
+
+    #[test]
+    fn data_enums() {
+        let rendered = render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "u8" } } },
+                { "id": 2, "type": { "def": { "array": { "len": 32, "type": 1 } } } },
+                {
+                    "id": 3,
+                    "type": {
+                        "def": { "composite": { "fields": [{ "type": 2 }] } },
+                        "path": ["ink_env", "types", "AccountId"],
+                    },
+                },
+                { "id": 4, "type": { "def": { "primitive": "u128" } } },
+                {
+                    "id": 5,
+                    "type": {
+                        "def": { "variant": { "variants": [
+                            {
+                                "fields": [{ "name": "to", "type": 3 }, { "name": "amount", "type": 4 }],
+                                "index": 0,
+                                "name": "Transfer",
+                            },
+                            { "fields": [{ "type": 4 }], "index": 1, "name": "Burn" },
+                        ]}},
+                        "path": ["sample", "Action"],
+                    },
+                },
+                {
+                    "id": 6,
+                    "type": {
+                        "def": { "variant": { "variants": [
+                            { "index": 0, "name": "Fast" },
+                            { "index": 1, "name": "Safe" },
+                        ]}},
+                        "path": ["sample", "Mode"],
+                    },
+                },
+            ]),
+            json!([arg("action", 5), arg("mode", 6)]),
+            0,
+        );
+
+        assert!(rendered.contains("sample_Action memory action"));
+        assert!(rendered.contains(
+            "struct sample_Action_Transfer {\n        bytes32 to;\n        uint128 amount;\n    }"
+        ));
+        assert!(rendered.contains("struct sample_Action_Burn {\n        uint128 f0;\n    }"));
+        assert!(rendered.contains(
+            "struct sample_Action {\n        uint8 tag;\n        \
+             sample_Action_Transfer Transfer;\n        \
+             sample_Action_Burn Burn;\n    }"
+        ));
+        assert!(rendered.contains(
+            "abi.encodePacked(uint8(0)),\n                \
+             encode_AccountId(value.Transfer.to),\n                \
+             encode_uint128(value.Transfer.amount)\n            );"
+        ));
+        assert!(rendered.contains("encode_uint128(value.Burn.f0)"));
+        assert!(rendered.contains("revert(\"invalid sample_Action tag\");"));
+
+        // C-style enums keep the plain representation
+        assert!(rendered.contains("enum sample_Mode {"));
+        assert!(rendered.contains("function encode_sample_Mode(sample_Mode value)"));
+    }
+
+    #[test]
+    fn custom_discriminants() {
+        let types = json!([
+            { "id": 0, "type": { "def": { "primitive": "bool" } } },
+            {
+                "id": 1,
+                "type": {
+                    "def": { "variant": { "variants": [
+                        { "index": 0, "name": "Low" },
+                        { "index": 5, "name": "High" },
+                    ]}},
+                    "path": ["sample", "Level"],
+                },
+            },
+        ]);
+
+        let error = try_render_message(types, json!([arg("level", 1)]), 0).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unsupported type `sample::Level` in argument `level` of message `call`: \
+             variant `High` has discriminant 5, which Solidity enums cannot express"
+        );
+    }
+
+    #[test]
+    fn chars() {
+        let rendered = render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "char" } } },
+                { "id": 2, "type": { "def": { "primitive": "u8" } } },
+                {
+                    "id": 3,
+                    "type": {
+                        "def": { "composite": { "fields": [
+                            { "name": "symbol", "type": 1 },
+                            { "name": "width", "type": 2 },
+                        ]}},
+                        "path": ["sample", "Glyph"],
+                    },
+                },
+            ]),
+            json!([arg("glyph", 3), arg("separator", 1)]),
+            0,
+        );
+
+        assert!(rendered.contains("struct sample_Glyph {\n        uint32 symbol;\n"));
+        assert!(rendered.contains("uint32  separator"));
+        assert!(rendered.contains("encode_char(separator)"));
+        assert!(rendered.contains("function encode_char(uint32 value) private pure"));
+        assert!(rendered.contains(
+            "require(value < 0xD800 || (value > 0xDFFF && value <= 0x10FFFF), \"invalid char\");"
+        ));
+
+        // Bounds of the range check
+        assert_eq!('\u{D7FF}' as u32 + 1, 0xD800);
+        assert_eq!('\u{E000}' as u32 - 1, 0xDFFF);
+        assert_eq!(char::MAX as u32, 0x10FFFF);
+        assert!(char::from_u32(0xD800).is_none());
+    }
+
+    #[test]
+    fn generic_instantiations() {
+        let generic = |id: u32, path: &[&str], param: u32| {
+            json!({
+                "id": id,
+                "type": {
+                    "def": { "composite": { "fields": [{ "name": "value", "type": param }] } },
+                    "params": [{ "name": "T", "type": param }],
+                    "path": path,
+                },
+            })
+        };
+
+        let option = |id: u32, value: u32| {
+            json!({
+                "id": id,
+                "type": {
+                    "def": { "variant": { "variants": [
+                        { "index": 0, "name": "None" },
+                        { "fields": [{ "type": value }], "index": 1, "name": "Some" },
+                    ]}},
+                    "params": [{ "name": "T", "type": value }],
+                    "path": ["Option"],
+                },
+            })
+        };
+
+        let types = json!([
+            { "id": 0, "type": { "def": { "primitive": "bool" } } },
+            { "id": 1, "type": { "def": { "primitive": "u32" } } },
+            { "id": 2, "type": { "def": { "primitive": "u8" } } },
+            { "id": 3, "type": { "def": { "array": { "len": 32, "type": 2 } } } },
+            {
+                "id": 4,
+                "type": {
+                    "def": { "composite": { "fields": [{ "type": 3 }] } },
+                    "path": ["ink_env", "types", "AccountId"],
+                },
+            },
+            option(5, 1),
+            option(6, 4),
+            generic(7, &["sample", "Wrapper"], 1),
+            generic(8, &["sample", "Wrapper"], 0),
+            generic(9, &["sample", "Single"], 1),
+        ]);
+
+        let args = json!([
+            arg("a", 5),
+            arg("b", 6),
+            arg("c", 7),
+            arg("d", 8),
+            arg("e", 9),
+        ]);
+
+        let rendered = render_message(types.clone(), args.clone(), 0);
+        assert!(rendered.contains("struct Option_uint32 {"));
+        assert!(rendered.contains("struct Option_AccountId {\n        bool isSome;\n"));
+        assert!(rendered.contains("struct sample_Wrapper_uint32 {\n        uint32 value;\n    }"));
+        assert!(rendered.contains("struct sample_Wrapper_bool {\n        bool value;\n    }"));
+        assert!(rendered.contains("sample_Wrapper_uint32 memory c"));
+        assert!(rendered.contains("sample_Wrapper_bool memory d"));
+        assert!(rendered.contains("encode_sample_Wrapper_bool(sample_Wrapper_bool memory value)"));
+
+        // Parameters are only added to names that would clash
+        assert!(rendered.contains("struct sample_Single {"));
+        assert_eq!(rendered.matches("struct sample_Wrapper").count(), 2);
+
+        // Names are stable across runs
+        assert_eq!(render_message(types, args, 0), rendered);
+    }
+
+    #[test]
+    fn definitions_once() {
+        let rendered = render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "u8" } } },
+                { "id": 2, "type": { "def": { "sequence": { "type": 1 } } } },
+                { "id": 3, "type": { "def": { "sequence": { "type": 1 } } } },
+            ]),
+            json!([arg("key", 2), arg("value", 3)]),
+            0,
+        );
+
+        assert_eq!(rendered.matches("function encode_bytes(").count(), 1);
+
+        // Every struct, encoder and decoder of the sample is declared exactly once
+        let rendered = render_sample("ink-erc20");
+        let declarations = rendered
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.starts_with("struct ") || line.starts_with("function "))
+            .map(|line| line.split('(').next().unwrap())
+            .collect_vec();
+
+        assert!(!declarations.is_empty());
+        assert_eq!(declarations.iter().unique().count(), declarations.len());
+    }
+
+    #[test]
+    fn nested_structs() {
+        let rendered = render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "u16" } } },
+                { "id": 2, "type": { "def": { "primitive": "u32" } } },
+                { "id": 3, "type": { "def": { "tuple": [1, 2] } } },
+                {
+                    "id": 4,
+                    "type": {
+                        "def": { "composite": { "fields": [
+                            { "name": "a", "type": 1 },
+                            { "name": "b", "type": 3 },
+                        ]}},
+                        "path": ["sample", "Inner"],
+                    },
+                },
+                {
+                    "id": 5,
+                    "type": {
+                        "def": { "composite": { "fields": [
+                            { "name": "inner", "type": 4 },
+                            { "name": "amount", "type": 2 },
+                        ]}},
+                        "path": ["sample", "Outer"],
+                    },
+                },
+            ]),
+            json!([arg("outer", 5)]),
+            0,
+        );
+
+        assert!(rendered.contains(
+            "struct sample_Outer {\n        sample_Inner inner;\n        uint32 amount;\n    }"
+        ));
+        assert!(rendered.contains(
+            "struct sample_Inner {\n        uint16 a;\n        Tuple_uint16_uint32 b;\n    }"
+        ));
+        assert!(rendered.contains(
+            "return bytes.concat(\n            \
+             encode_sample_Inner(value.inner),\n            \
+             encode_uint32(value.amount)\n        );"
+        ));
+        assert!(rendered.contains(
+            "return bytes.concat(\n            \
+             encode_uint16(value.a),\n            \
+             encode_Tuple_uint16_uint32(value.b)\n        );"
+        ));
+        assert!(rendered.contains("function encode_Tuple_uint16_uint32(Tuple_uint16_uint32 memory"));
+        assert!(rendered.contains("encode_uint16(value.f0),\n            encode_uint32(value.f1)"));
+    }
+
+    #[test]
+    fn arrays_of_composites() {
+        let rendered = render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "u32" } } },
+                { "id": 2, "type": { "def": { "tuple": [1, 0] } } },
+                { "id": 3, "type": { "def": { "sequence": { "type": 2 } } } },
+                {
+                    "id": 4,
+                    "type": {
+                        "def": { "composite": { "fields": [
+                            { "name": "x", "type": 1 },
+                            { "name": "y", "type": 1 },
+                        ]}},
+                        "path": ["sample", "Point"],
+                    },
+                },
+                { "id": 5, "type": { "def": { "array": { "len": 3, "type": 4 } } } },
+            ]),
+            json!([arg("pairs", 3), arg("points", 5)]),
+            0,
+        );
+
+        assert!(rendered.contains("Tuple_uint32_bool[] memory pairs"));
+        assert!(rendered.contains("sample_Point[3] memory points"));
+        assert!(rendered.contains("encode_Vec_Tuple_uint32_bool(pairs)"));
+        assert!(rendered.contains("encode_Array_sample_Point_3(points)"));
+        assert!(rendered.contains(
+            "function encode_Array_sample_Point_3(sample_Point[3] memory value) \
+             private pure returns (bytes memory) {\n        \
+             bytes memory buffer;\n        \
+             for (uint256 i = 0; i < 3; i++) {\n            \
+             buffer = bytes.concat(buffer, encode_sample_Point(value[i]));"
+        ));
+        assert!(
+            rendered.contains("buffer = bytes.concat(buffer, encode_Tuple_uint32_bool(value[i]));")
+        );
+    }
+
+    #[test]
+    fn large_byte_arrays() {
+        let rendered = render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "u8" } } },
+                { "id": 2, "type": { "def": { "array": { "len": 33, "type": 1 } } } },
+                { "id": 3, "type": { "def": { "array": { "len": 64, "type": 1 } } } },
+                { "id": 4, "type": { "def": { "array": { "len": 32, "type": 1 } } } },
+            ]),
+            json!([arg("key", 2), arg("signature", 3), arg("hash", 4)]),
+            0,
+        );
+
+        assert!(rendered.contains("bytes memory key"));
+        assert!(rendered.contains("bytes memory signature"));
+        assert!(rendered.contains("bytes32  hash"));
+        assert!(!rendered.contains("uint8["));
+        assert!(rendered.contains("encode_bytes33(key)"));
+        assert!(rendered.contains("encode_bytes64(signature)"));
+        assert!(rendered.contains(
+            "function encode_bytes64(bytes memory value) private pure returns (bytes memory) {\n        \
+             require(value.length == 64, \"bytes64 must be 64 bytes long\");\n        \
+             return value;"
+        ));
+        assert!(
+            rendered.contains("require(value.length == 33, \"bytes33 must be 33 bytes long\");")
+        );
+    }
+
+    #[test]
+    fn bools() {
+        let rendered = render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "sequence": { "type": 0 } } } },
+                { "id": 2, "type": { "def": { "array": { "len": 8, "type": 0 } } } },
+                { "id": 3, "type": { "def": { "primitive": "u16" } } },
+                {
+                    "id": 4,
+                    "type": {
+                        "def": { "composite": { "fields": [
+                            { "name": "enabled", "type": 0 },
+                            { "name": "limit", "type": 3 },
+                        ]}},
+                        "path": ["sample", "Settings"],
+                    },
+                },
+            ]),
+            json!([
+                arg("flag", 0),
+                arg("flags", 1),
+                arg("mask", 2),
+                arg("settings", 4)
+            ]),
+            0,
+        );
+
+        // A single byte per bool wherever it appears
+        assert!(rendered.contains(
+            "function encode_bool(bool value) private pure returns (bytes memory) {\n        \
+             return ScaleCodec.encode_bool(value);"
+        ));
+        assert!(rendered.contains("encode_bool(flag)"));
+        assert!(rendered.contains("bool[] memory flags"));
+        assert!(rendered.contains("bool[8] memory mask"));
+        assert!(rendered.contains("buffer = bytes.concat(buffer, encode_bool(value[i]));"));
+        assert!(rendered
+            .contains("encode_bool(value.enabled),\n            encode_uint16(value.limit)"));
+
+        // The Foundry test runs the emitted codec against `parity-scale-codec`
+        let test = foundry_test("ink-erc20");
+        assert!(test.contains("assertEq(ScaleCodec.encode_bool(true), hex\"01\");"));
+        assert!(test.contains(
+            "assertEq(ScaleCodec.encode_bool(false), hex\"00\");\n        \
+             (value, next) = ScaleCodec.decode_bool(hex\"00\", 0);\n        \
+             assertEq(value, false);"
+        ));
+    }
+
+    #[test]
+    fn units() {
+        let types = json!([
+            { "id": 0, "type": { "def": { "tuple": [] } } },
+            { "id": 1, "type": { "def": { "composite": {} }, "path": ["sample", "Marker"] } },
+            { "id": 2, "type": { "def": { "primitive": "u32" } } },
+            {
+                "id": 3,
+                "type": {
+                    "def": { "composite": { "fields": [
+                        { "name": "marker", "type": 1 },
+                        { "name": "amount", "type": 2 },
+                    ]}},
+                    "path": ["sample", "Payment"],
+                },
+            },
+        ]);
+
+        let rendered = render_message(types, json!([arg("marker", 1), arg("payment", 3)]), 0);
+
+        // Nothing is returned and nothing is decoded
+        assert!(rendered.contains(
+            "function call(\n        sample_Payment memory payment\n    ) public\n    {"
+        ));
+        assert!(rendered.contains("require(success, \"XVM call failed\");\n    }"));
+        assert!(!rendered.contains("returns ()"));
+        assert!(!rendered.contains("output, ) = decode_"));
+
+        // Unit arguments and fields are encoded as no bytes, so they are left out
+        assert!(!rendered.contains("marker"));
+        assert!(!rendered.contains("Tuple"));
+        assert!(rendered.contains("struct sample_Payment {\n        uint32 amount;\n    }"));
+        assert!(rendered.contains("encode_uint32(value.amount)\n        );"));
+    }
+
+    #[test]
+    fn zero_sized_fields() {
+        let rendered = render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "u64" } } },
+                { "id": 2, "type": { "def": { "composite": {} }, "path": ["PhantomData"] } },
+                { "id": 3, "type": { "def": { "array": { "len": 0, "type": 1 } } } },
+                {
+                    "id": 4,
+                    "type": {
+                        "def": { "composite": { "fields": [{ "name": "marker", "type": 2 }] } },
+                        "path": ["sample", "Tagged"],
+                    },
+                },
+                {
+                    "id": 5,
+                    "type": {
+                        "def": { "composite": { "fields": [
+                            { "name": "owner", "type": 2, "typeName": "PhantomData<AccountId>" },
+                            { "name": "value", "type": 1 },
+                            { "name": "reserved", "type": 3 },
+                            { "name": "tag", "type": 4 },
+                        ]}},
+                        "params": [{ "name": "T", "type": 1 }],
+                        "path": ["sample", "Handle"],
+                    },
+                },
+            ]),
+            json!([arg("handle", 5)]),
+            0,
+        );
+
+        // The struct keeps only the field having an encoding
+        assert!(rendered.contains("struct sample_Handle {\n        uint64 value;\n    }"));
+        assert!(rendered
+            .contains("return bytes.concat(\n            encode_uint64(value.value)\n        );"));
+        assert!(!rendered.contains("PhantomData"));
+        assert!(!rendered.contains("sample_Tagged"));
+        assert!(!rendered.contains("[0]"));
+    }
+
+    #[test]
+    fn recursive_types() {
+        let error = try_render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                {
+                    "id": 1,
+                    "type": {
+                        "def": { "composite": { "fields": [{ "name": "right", "type": 2 }] } },
+                        "path": ["sample", "Left"],
+                    },
+                },
+                {
+                    "id": 2,
+                    "type": {
+                        "def": { "composite": { "fields": [{ "name": "left", "type": 1 }] } },
+                        "path": ["sample", "Right"],
+                    },
+                },
+            ]),
+            json!([arg("tree", 1)]),
+            0,
+        )
+        .unwrap_err();
+
+        assert!(matches!(error, Error::RecursiveType { .. }));
+        assert_eq!(
+            error.to_string(),
+            "recursive type `sample::Left -> sample::Right -> sample::Left` \
+             has no Solidity counterpart"
+        );
+
+        // Boxes are transparent, so `Option<Box<Node>>` refers to the node itself
+        let error = try_render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                {
+                    "id": 1,
+                    "type": {
+                        "def": { "composite": { "fields": [{ "name": "next", "type": 2 }] } },
+                        "path": ["sample", "Node"],
+                    },
+                },
+                {
+                    "id": 2,
+                    "type": {
+                        "def": { "variant": { "variants": [
+                            { "index": 0, "name": "None" },
+                            { "fields": [{ "type": 1 }], "index": 1, "name": "Some" },
+                        ]}},
+                        "params": [{ "name": "T", "type": 1 }],
+                        "path": ["Option"],
+                    },
+                },
+            ]),
+            json!([]),
+            1,
+        )
+        .unwrap_err();
+
+        let message = error.to_string();
+        assert!(message.contains("`sample::Node -> Option -> sample::Node`"));
+    }
+
+    #[test]
+    fn constructors() {
+        let mut metadata = message_metadata(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "u128" } } },
+                { "id": 2, "type": { "def": { "tuple": [] } } },
+            ]),
+            json!([]),
+            0,
+        );
 
-    let metadata: serde_json::Value = serde_json::from_str(&buffer)?;
-    let project: Rc<InkProject> = Rc::new(serde_json::from_value(metadata["V3"].clone())?);
+        metadata["V3"]["spec"]["constructors"] = json!([
+            {
+                "args": [arg("supply", 1)],
+                "docs": ["Creates a token"],
+                "label": "new",
+                "payable": false,
+                "selector": "0x9bae9d5e",
+            },
+            {
+                "args": [arg("marker", 2)],
+                "docs": [],
+                "label": "funded",
+                "payable": true,
+                "selector": "0x00000002",
+            },
+        ]);
 
-    static MODULE_TEMPLATE: &'static str = include_str!("../templates/solidity-module.txt");
-    let mut template = tinytemplate::TinyTemplate::new();
+        let rendered = try_render(&metadata).unwrap();
+        assert!(rendered.contains(
+            "    /// @notice Creates a token\n    \
+             /// @dev Returns the data to instantiate the ink! contract with, given its `code_hash` and `salt`\n    \
+             /// @param supply uint128\n    \
+             function new_init_data(\n        \
+             bytes32 code_hash,\n        \
+             bytes memory salt,\n        \
+             uint128  supply\n    \
+             ) public pure returns (bytes memory) {\n        \
+             bytes4 selector = NEW_SELECTOR;\n        \
+             bytes memory input = bytes.concat(\n            \
+             selector,\n            \
+             encode_uint128(supply)\n        \
+             );"
+        ));
+        assert!(rendered.contains("little_endian(0, 16),"));
 
-    template.set_default_formatter(&tinytemplate::format_unescaped);
-    template.add_template("module", MODULE_TEMPLATE)?;
+        // Payable constructors forward the value, unit arguments are left out
+        assert!(rendered.contains(
+            "function funded_init_data(\n        \
+             bytes32 code_hash,\n        \
+             bytes memory salt\n    \
+             ) public payable returns (bytes memory) {"
+        ));
+        assert!(rendered.contains("little_endian(msg.value, 16),"));
+        assert!(!rendered.contains("marker"));
 
-    template.add_formatter("debug", |value, buffer| {
-        buffer.push_str(&format!("{:?}", value));
-        Ok(())
-    });
-
-    template.add_formatter("path", format_path);
-
-    let evm_registry = Rc::new(RefCell::new(EvmTypeRegistry::new()));
-    let context = Context::new(project.clone());
-
-    let registry = evm_registry.clone();
-    template.add_predicate("mapped", move |id| {
-        let id = id
-            .as_u64()
-            .and_then(|id| id.try_into().ok())
-            .ok_or_else(|| GenericError {
-                msg: format!("invalid id {id:?}"),
-            })?;
-
-        Ok(registry.borrow().lookup(id).is_some())
-    });
-
-    template.add_formatter_with_args("type", move |value, arg, buffer| {
-        if let serde_json::Value::Number(id) = value {
-            let id = id
-                .as_u64()
-                .and_then(|id| id.try_into().ok())
-                .ok_or_else(|| GenericError {
-                    msg: format!("invalid id {id:?}"),
-                })?;
-
-            let write_buffer = |ty: &EvmType, buffer: &mut String| {
-                let empty = String::default();
-                buffer.push_str(match arg {
-                    Some("reference") => ty.reference.as_ref(),
-                    Some("definition") => ty.definition.as_ref().unwrap_or(&empty),
-                    Some("modifier") => ty.modifier.as_ref().unwrap_or(&empty),
-                    Some("encoder") => ty.encoder.as_ref().unwrap_or(&empty),
-                    _ => panic!("type formatter must come with an argument"),
-                });
-            };
+        // Same layout as the arguments of `Contracts::instantiate` without the limits
+        use parity_scale_codec::Encode;
+        let (value, code_hash, salt) = (1_000u128, [3u8; 32], vec![4u8, 5]);
+        let input = [vec![0x9b, 0xae, 0x9d, 0x5e], 7u128.encode()].concat();
+        let init_data = [
+            value.encode(),
+            code_hash.to_vec(),
+            solidity_compact(input.len() as u128),
+            input.clone(),
+            solidity_compact(salt.len() as u128),
+            salt.clone(),
+        ]
+        .concat();
 
-            let mut registry = evm_registry.borrow_mut();
-            match registry.lookup_mut(id) {
-                Some(ty) => write_buffer(ty, buffer),
-                None => {
-                    let ty =
-                        context
-                            .project
-                            .registry()
-                            .resolve(id)
-                            .ok_or_else(|| GenericError {
-                                msg: format!("invalid id {id:?}"),
-                            })?;
-                    let mut new_type = registry.convert_type(id, ty, &context).unwrap();
-                    write_buffer(&mut new_type, buffer);
-                    registry.insert(id, new_type);
-                }
+        assert_eq!(init_data, (value, code_hash, input, salt).encode());
+        assert!(render_sample("ink-erc20").contains("function new_init_data("));
+    }
+
+    #[test]
+    fn events() {
+        let rendered = render_sample("ink-erc20");
+
+        assert!(rendered.contains(
+            "    /// @notice Event emitted when a token transfer occurs.\n    \
+             /// @param from Option_AccountId\n    \
+             /// @param to Option_AccountId\n    \
+             /// @param value uint128\n    \
+             event Transfer(\n        \
+             Option_AccountId indexed from,\n        \
+             Option_AccountId indexed to,\n        \
+             uint128 value\n    \
+             );"
+        ));
+        assert!(rendered.contains(
+            "event Approval(\n        \
+             bytes32 indexed owner,\n        \
+             bytes32 indexed spender,\n        \
+             uint128 value\n    \
+             );"
+        ));
+        assert!(rendered.contains(
+            "function decodeTransferEvent(bytes memory topics, bytes memory data) public pure \
+             returns (\n        \
+             Option_AccountId memory from,\n        \
+             Option_AccountId memory to,\n        \
+             uint128 value\n    \
+             ) {\n        \
+             uint256 offset = 0;\n        \
+             require(uint8(data[0]) == 0, \"not a Transfer event\");\n        \
+             offset = 1;\n        \
+             (from, offset) = decode_Option_AccountId(data, offset);\n        \
+             (to, offset) = decode_Option_AccountId(data, offset);\n        \
+             (value, offset) = decode_uint128(data, offset);\n    \
+             }"
+        ));
+        assert!(rendered.contains("require(uint8(data[0]) == 1, \"not a Approval event\");"));
+        assert!(rendered.contains("(option.value, offset) = decode_AccountId(data, offset + 1);"));
+        assert!(rendered.contains("return (bytes32(word), offset + 32);"));
+
+        // Events with fields lacking a decoder are only declared
+        let mut metadata = message_metadata(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "char" } } },
+            ]),
+            json!([]),
+            0,
+        );
+        metadata["V3"]["spec"]["events"] = json!([{
+            "args": [{ "docs": [], "indexed": false, "label": "note", "type": { "displayName": [], "type": 1 } }],
+            "docs": [],
+            "label": "Noted",
+        }]);
+
+        let rendered = try_render(&metadata).unwrap();
+        assert!(rendered.contains("event Noted(\n        uint32 note\n    );"));
+        assert!(!rendered.contains("decodeNotedEvent"));
+    }
+
+    #[test]
+    fn payable_messages() {
+        let mut metadata = message_metadata(
+            json!([{ "id": 0, "type": { "def": { "primitive": "bool" } } }]),
+            json!([]),
+            0,
+        );
+
+        // Non-payable messages transfer nothing
+        let rendered = try_render(&metadata).unwrap();
+        assert!(rendered.contains(
+            "uint256 value\n    ) external payable returns (bool success, bytes memory data);"
+        ));
+        assert!(rendered.contains(") public\n    returns (bool)"));
+        let call =
+            "XVM_PRECOMPILE.xvm_call(\"\\x1f\\x00\\x00\\x00\\x00\", contract_address, buffer, 0);";
+        assert!(rendered.contains(call));
+
+        metadata["V3"]["spec"]["messages"][0]["payable"] = json!(true);
+        let rendered = try_render(&metadata).unwrap();
+        assert!(rendered.contains(") public payable\n    returns (bool)"));
+        assert!(rendered.contains(
+            ");\n\n        (bool success, bytes memory data) = \
+             XVM_PRECOMPILE.xvm_call{value: msg.value}(\"\\x1f\\x00\\x00\\x00\\x00\", contract_address, buffer, msg.value);"
+        ));
+
+        // The legacy interface has no value to forward, so nothing can be paid
+        let rendered = try_render_with(&metadata, &["--xvm-version", "v1"]).unwrap();
+        assert!(rendered.contains(
+            "bytes calldata input\n    ) external returns (bool success, bytes memory data);"
+        ));
+        assert!(rendered.contains(") public\n    returns (bool)"));
+        assert!(
+            rendered.contains("XVM_PRECOMPILE.xvm_call(\"\\x1f\\x00\", contract_address, buffer);")
+        );
+        assert!(!rendered.contains("msg.value"));
+    }
+
+    #[test]
+    fn return_values() {
+        let types = json!([
+            { "id": 0, "type": { "def": { "primitive": "u8" } } },
+            { "id": 1, "type": { "def": { "primitive": "u64" } } },
+            { "id": 2, "type": { "def": { "sequence": { "type": 1 } } } },
+            { "id": 3, "type": { "def": { "primitive": "str" } } },
+            { "id": 4, "type": { "def": { "array": { "len": 32, "type": 0 } } } },
+            { "id": 5, "type": { "def": { "sequence": { "type": 0 } } } },
+            {
+                "id": 6,
+                "type": {
+                    "def": { "composite": { "fields": [{ "name": "x", "type": 7 }] } },
+                    "path": ["sample", "Point"],
+                },
+            },
+            { "id": 7, "type": { "def": { "primitive": "char" } } },
+        ]);
+
+        let rendered = render_message(types.clone(), json!([]), 2);
+        assert!(rendered.contains("returns (uint64[] memory)"));
+        assert!(rendered.contains("(uint64[] memory output, ) = decode_Vec_uint64(data, 0);"));
+        assert!(rendered.contains(
+            "(uint32 length, uint256 next) = ScaleCodec.decode_compact_u32(data, offset);\n        \
+             uint64[] memory value = new uint64[](length);\n        \
+             for (uint256 i = 0; i < length; i++) {\n            \
+             (value[i], next) = decode_uint64(data, next);"
+        ));
+
+        let rendered = render_message(types.clone(), json!([]), 3);
+        assert!(rendered.contains("returns (string memory)"));
+        assert!(rendered.contains("(string memory output, ) = decode_string(data, 0);"));
+        assert!(rendered.contains("return (string(value), next);"));
+
+        let rendered = render_message(types.clone(), json!([]), 4);
+        assert!(rendered.contains("(bytes32 output, ) = decode_bytes32(data, 0);"));
+
+        let rendered = render_message(types.clone(), json!([]), 5);
+        assert!(rendered.contains("(bytes memory output, ) = decode_bytes(data, 0);"));
+
+        // Types without a decoder, like structs with a `char` field, are returned as is
+        let rendered = render_message(types, json!([]), 6);
+        assert!(rendered.contains(") public\n    returns (bytes memory)\n    {"));
+        assert!(rendered.contains(
+            "// `sample_Point` has no decoder yet, so the raw SCALE encoded output is returned\n        \
+             return data;"
+        ));
+
+        // The Foundry test runs the emitted decoders of integers and byte strings against
+        // `parity-scale-codec`
+        let test = foundry_test("ink-erc20");
+        let (encoded, max) = ("ff".repeat(16), u128::MAX);
+        let max = format!("from_little_endian(hex\"{encoded}\", 0, 16), {max});");
+        assert!(test.contains(&max));
+        assert!(test.contains(
+            "(value, next) = ScaleCodec.decode_bytes(hex\"0c010203\", 0);\n        \
+             assertEq(value, hex\"010203\");\n        \
+             assertEq(next, 4);"
+        ));
+    }
+
+    #[test]
+    fn read_only_messages() {
+        let rendered = render_sample("ink-erc20");
+
+        assert!(rendered.contains(
+            "    /// @notice Returns the total token supply.\n    \
+             /// @dev Read-only in ink!, but XVM has no query path, so it is called like any other message\n    \
+             function total_supply(\n    \
+             ) public\n    \
+             returns (uint128)\n    {"
+        ));
+        assert!(rendered.contains("function balance_of(\n        bytes32  owner\n    ) public\n"));
+        assert!(rendered.contains("function allowance("));
+
+        // Read-only messages are not payable, so they transfer nothing
+        let total_supply = rendered.split("function total_supply(").nth(1).unwrap();
+        let total_supply = total_supply.split("\n    }").next().unwrap();
+        let call =
+            "XVM_PRECOMPILE.xvm_call(\"\\x1f\\x00\\x00\\x00\\x00\", contract_address, buffer, 0);";
+        assert!(total_supply.contains(call));
+        assert!(!total_supply.contains(" view"));
+    }
+
+    #[test]
+    fn selectors() {
+        // Without overrides metadata selectors match the first 4 bytes of blake2b-256 of the label
+        let rendered = render_sample("ink-erc20");
+        assert!(rendered.contains(
+            "    // Selectors of the ink! constructors and messages, for building their input by hand\n    \
+             bytes4 constant NEW_SELECTOR = 0x9bae9d5e;\n    \
+             bytes4 constant TOTAL_SUPPLY_SELECTOR = 0xdb6375a8;\n"
+        ));
+        assert!(rendered.contains("bytes4 constant TRANSFER_FROM_SELECTOR = 0x0b396f18;\n\n"));
+        assert!(rendered.contains("bytes4 selector = TOTAL_SUPPLY_SELECTOR;"));
+        assert!(rendered.contains("bytes4 selector = NEW_SELECTOR;"));
+
+        // `#[ink(selector = 0xCAFEBABE)]` is taken verbatim instead of hashing `call`
+        let mut metadata = message_metadata(json!([]), json!([]), 0);
+        metadata["V3"]["spec"]["messages"][0]["selector"] = json!("0xcafebabe");
+        metadata["V3"]["types"] = json!([{ "id": 0, "type": { "def": { "tuple": [] } } }]);
+        let rendered = try_render(&metadata).unwrap();
+        assert!(rendered.contains("bytes4 constant CALL_SELECTOR = 0xcafebabe;"));
+
+        let mut other = metadata["V3"]["spec"]["messages"][0].clone();
+        other["label"] = json!("other");
+        metadata["V3"]["spec"]["messages"]
+            .as_array_mut()
+            .unwrap()
+            .push(other);
+        assert!(matches!(
+            try_render(&metadata),
+            Err(Error::SelectorCollision { .. })
+        ));
+    }
+
+    #[test]
+    fn natspec_comments() {
+        let types = json!([
+            { "id": 0, "type": { "def": { "tuple": [] } } },
+            { "id": 1, "type": { "def": { "primitive": "u32" } } },
+        ]);
+
+        let mut metadata = message_metadata(types, json!([arg("amount", 1)]), 0);
+        let message = &mut metadata["V3"]["spec"]["messages"][0];
+        message["docs"] = json!([" Sends `amount`", "", " to user@example.com\n*/ twice"]);
+        let rendered = try_render(&metadata).unwrap();
+        assert!(rendered.contains(
+            "    /// @notice Sends `amount`\n    \
+             /// to user(at)example.com\n    \
+             /// */ twice\n    \
+             /// @param amount uint32\n    \
+             function call("
+        ));
+
+        // Undocumented items name their ink! counterpart
+        metadata["V3"]["spec"]["messages"][0]["docs"] = json!([]);
+        metadata["V3"]["spec"]["events"] = json!([{
+            "args": [
+                { "docs": [" Amount sent"], "indexed": false, "label": "amount", "type": { "displayName": [], "type": 1 } },
+                { "docs": [], "indexed": false, "label": "fee", "type": { "displayName": [], "type": 1 } },
+            ],
+            "docs": [],
+            "label": "Sent",
+        }]);
+        let rendered = try_render(&metadata).unwrap();
+        assert!(rendered.contains(
+            "    /// @notice ink! message `call` with selector 0x00000001\n    \
+             /// @param amount uint32\n    \
+             function call("
+        ));
+        assert!(rendered.contains(
+            "    /// @notice ink! event `Sent`\n    \
+             /// @param amount Amount sent\n    \
+             /// @param fee uint32\n    \
+             event Sent("
+        ));
+    }
+
+    #[test]
+    fn source() {
+        let mut metadata = sample("ink-erc20");
+        metadata["source"]["language"] = json!("ink! 2.1.0");
+        let error = try_render(&metadata).unwrap_err().to_string();
+        assert!(error.contains("`ink! 2.1.0` built with `rustc 1.62.0-nightly`"));
+        assert!(error.contains("supported ink! versions are 3, 4 and 5"));
+
+        // Unsupported sources are only warned about on request
+        let rendered = try_render_with(&metadata, &["--allow-unsupported-source"]).unwrap();
+        assert_eq!(rendered, render_sample("ink-erc20"));
+
+        metadata["source"]["language"] = json!("Solang 0.3.2");
+        metadata["source"]["compiler"] = json!("solang 0.3.2");
+        let error = try_render(&metadata).unwrap_err().to_string();
+        assert!(error.contains("`Solang 0.3.2` built with `solang 0.3.2`"));
+        assert!(error.contains("Solang lays out types differently"));
+
+        assert!(check_source(&json!({ "language": "ink! 5.0.0" }), false).is_ok());
+        assert!(check_source(&Value::Null, false).is_ok());
+    }
+
+    #[test]
+    fn multiple_contracts() {
+        let metadata = |name: &str, fields: Value| {
+            let types = json!([
+                { "id": 0, "type": { "def": { "tuple": [] } } },
+                { "id": 1, "type": { "def": { "primitive": "u32" } } },
+                {
+                    "id": 2,
+                    "type": {
+                        "def": { "composite": { "fields": fields } },
+                        "path": ["sample", "Point"],
+                    },
+                },
+            ]);
+
+            let mut metadata =
+                message_metadata(types, json!([arg("point", 2), arg("count", 1)]), 0);
+            metadata["contract"]["name"] = json!(name);
+            serde_json::to_vec(&metadata).unwrap()
+        };
+
+        let render_pair = |first: &[u8], second: &[u8], args: &Args| {
+            render_all(&mut [&mut &*first as &mut dyn Read, &mut &*second], args)
+        };
+
+        let x = json!([{ "name": "x", "type": 1 }]);
+        let xy = json!([{ "name": "x", "type": 1 }, { "name": "y", "type": 1 }]);
+        let (first, second) = (metadata("first", x.clone()), metadata("second", xy));
+        let args = Args::parse_from(["sumi", "--mode", "ink-to-evm"]);
+        let rendered = render_pair(&first, &second, &args).unwrap();
+
+        assert_eq!(rendered.matches("pragma solidity").count(), 1);
+        assert!(rendered.contains("contract first is SumiCodec {"));
+        assert!(rendered.contains("contract second is SumiCodec {"));
+
+        // Identical definitions are only declared by the base contract
+        let (base, contracts) = rendered.split_once("contract first").unwrap();
+        assert!(base.contains("abstract contract SumiCodec {"));
+        assert!(base.contains("function encode_uint32(uint32 value) internal pure"));
+        assert_eq!(rendered.matches("function encode_compact(").count(), 1);
+        assert!(!contracts.contains("function encode_compact("));
+        assert!(!contracts.contains("function encode_uint32("));
+        assert_eq!(rendered.matches("error InkError(bytes data);").count(), 1);
+
+        // Differing `Point` types stay in their contracts, along with their encoders
+        assert!(!base.contains("sample_Point"));
+        assert_eq!(contracts.matches("struct sample_Point {").count(), 2);
+        assert_eq!(contracts.matches("function encode_sample_Point").count(), 2);
+
+        // Contracts of the same name would clash
+        let duplicate = metadata("first", x);
+        let error = render_pair(&first, &duplicate, &args);
+        assert!(matches!(error, Err(Error::Metadata(_))));
+
+        let args = Args::parse_from(["sumi", "--mode", "ink-to-evm", "--module-name", "both"]);
+        let error = render_pair(&first, &second, &args);
+        assert!(matches!(error, Err(Error::InvalidOptions(_))));
+    }
+
+    #[test]
+    fn trait_messages() {
+        let rendered = render_sample("ink-psp22");
+
+        // Selectors of the trait namespace are kept, the trait is dropped from the names
+        assert!(rendered.contains(
+            "    /// @notice Returns the total token supply.\n    \
+             /// @dev Read-only in ink!, but XVM has no query path, so it is called like any other message\n    \
+             function total_supply(\n    \
+             ) public\n    \
+             returns (uint128)\n    \
+             {\n        \
+             bytes4 selector = TOTAL_SUPPLY_SELECTOR;"
+        ));
+        assert!(rendered.contains("function transfer_from(\n"));
+        assert!(rendered.contains("bytes4 constant TOTAL_SUPPLY_SELECTOR = 0x162df8c2;"));
+        assert!(rendered.contains("bytes4 constant TRANSFER_FROM_SELECTOR = 0x54b3c76e;"));
+        assert!(!rendered.contains("PSP22"));
+
+        // Names shared by several messages keep their trait
+        let mut metadata = sample("ink-psp22");
+        let messages = metadata["spec"]["messages"].as_array_mut().unwrap();
+        let mut inherent = messages[0].clone();
+        inherent["label"] = json!("total_supply");
+        inherent["selector"] = json!("0xdb6375a8");
+        messages.push(inherent);
+        let rendered = try_render(&metadata).unwrap();
+        assert!(rendered.contains("function PSP22_total_supply(\n"));
+        assert!(rendered.contains("function total_supply(\n"));
+        assert!(rendered.contains("bytes4 constant PSP22_TOTAL_SUPPLY_SELECTOR = 0x162df8c2;"));
+        assert!(rendered.contains("bytes4 constant TOTAL_SUPPLY_SELECTOR = 0xdb6375a8;"));
+        assert!(rendered.contains("function transfer(\n"));
+    }
+
+    #[test]
+    fn lang_error() {
+        let rendered = render_sample("ink-erc20-v4");
+        assert!(rendered.contains(
+            "require(success, \"XVM call failed\");\n\n        \
+             // Output is wrapped into `Result<_, LangError>`, which is only `Err` if ink! couldn't read the input\n        \
+             require(data.length > 0 && uint8(data[0]) == 0, \"LangError: CouldNotReadInput\");\n\n        \
+             (uint128 output, ) = decode_uint128(data, 1);"
+        ));
+
+        // V3 output is not wrapped
+        let rendered = render_sample("ink-erc20");
+        assert!(!rendered.contains("LangError"));
+        assert!(rendered.contains("(uint128 output, ) = decode_uint128(data, 0);"));
+    }
+
+    #[test]
+    fn keywords() {
+        let types = json!([
+            { "id": 0, "type": { "def": { "tuple": [] } } },
+            { "id": 1, "type": { "def": { "primitive": "u32" } } },
+            {
+                "id": 2,
+                "type": {
+                    "def": { "composite": { "fields": [
+                        { "name": "address", "type": 1 },
+                        { "name": "uint8", "type": 1 },
+                    ]}},
+                    "path": ["sample", "Account"],
+                },
+            },
+            {
+                "id": 3,
+                "type": {
+                    "def": { "variant": { "variants": [
+                        { "index": 0, "name": "default" },
+                        { "index": 1, "name": "Custom" },
+                    ]}},
+                    "path": ["sample", "Mode"],
+                },
+            },
+        ]);
+
+        let args = json!([arg("contract", 2), arg("data", 3), arg("amount", 1)]);
+        let mut metadata = message_metadata(types, args, 0);
+        metadata["V3"]["spec"]["messages"][0]["label"] = json!("delete");
+        metadata["V3"]["spec"]["events"] = json!([{
+            "args": [{ "docs": [], "indexed": false, "label": "offset", "type": { "displayName": [], "type": 1 } }],
+            "docs": [],
+            "label": "Deleted",
+        }]);
+        let rendered = try_render(&metadata).unwrap();
+
+        assert!(rendered.contains(
+            "struct sample_Account {\n        uint32 address_;\n        uint32 uint8_;\n    }"
+        ));
+        assert!(rendered.contains("encode_uint32(value.address_)"));
+        assert!(rendered.contains("default_, // = 0\n        Custom // = 1"));
+        assert!(rendered.contains(
+            "function delete_(\n        \
+             sample_Account memory contract_,\n        \
+             sample_Mode  data_,\n        \
+             uint32  amount\n    \
+             )"
+        ));
+        assert!(rendered.contains("encode_sample_Account(contract_),"));
+        assert!(rendered.contains("bytes4 selector = DELETE_SELECTOR;"));
+        assert!(rendered.contains("/// @notice ink! message `delete` with selector 0x00000001"));
+        assert!(rendered.contains("returns (\n        uint32 offset_\n    )"));
+        assert!(rendered.contains("(offset_, offset) = decode_uint32(data, offset);"));
+    }
+
+    #[test]
+    fn definitions_order() {
+        let composite = |id: u32, name: &str, field: u32| {
+            json!({
+                "id": id,
+                "type": {
+                    "def": { "composite": { "fields": [{ "name": "value", "type": field }] } },
+                    "path": ["sample", name],
+                },
+            })
+        };
+
+        // Containers come first in the registry
+        let types = json!([
+            { "id": 0, "type": { "def": { "tuple": [] } } },
+            composite(1, "Outer", 2),
+            composite(2, "Middle", 3),
+            composite(3, "Inner", 4),
+            { "id": 4, "type": { "def": { "primitive": "u32" } } },
+        ]);
+
+        let mut metadata = message_metadata(types, json!([arg("amount", 4)]), 0);
+        let mut second = metadata["V3"]["spec"]["messages"][0].clone();
+        second["label"] = json!("nested");
+        second["selector"] = json!("0x00000002");
+        second["args"] = json!([arg("outer", 1)]);
+        metadata["V3"]["spec"]["messages"]
+            .as_array_mut()
+            .unwrap()
+            .push(second);
+        let rendered = try_render(&metadata).unwrap();
+
+        let position = |needle: &str| rendered.find(needle).unwrap();
+        assert!(position("function encode_uint32(") < position("struct sample_Inner {"));
+        assert!(position("struct sample_Inner {") < position("struct sample_Middle {"));
+        assert!(position("struct sample_Middle {") < position("struct sample_Outer {"));
+        assert!(position("function encode_sample_Outer(") < position("function call("));
+        assert!(position("function encode_sample_Outer(") < position("function nested("));
+    }
+
+    #[test]
+    fn contract_names() {
+        let metadata = message_metadata(json!([]), json!([]), 0);
+        let rendered = try_render_with(&metadata, &["--module-name", "Token"]).unwrap();
+        assert!(rendered.contains("contract Token {"));
+        assert!(render_message(json!([]), json!([]), 0).contains("contract sample {"));
+
+        for name in ["2fa", "my-token", "", "contract", "uint8"] {
+            let error = try_render_with(&metadata, &["--module-name", name]).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!("incompatible options: --module-name `{name}` is not a valid Solidity identifier")
+            );
+        }
+
+        let mut keyword = metadata.clone();
+        keyword["contract"]["name"] = json!("library");
+        let rendered = try_render(&keyword).unwrap();
+        assert!(rendered.contains("contract library_ {"));
+
+        let mut unnamed = metadata;
+        unnamed["contract"] = json!({});
+        assert_eq!(
+            try_render(&unnamed).unwrap_err().to_string(),
+            "metadata error: metadata names no contract, see --module-name"
+        );
+    }
+
+    #[test]
+    fn solidity_version() {
+        let metadata = sample("ink-erc20-v4");
+        let rendered = try_render(&metadata).unwrap();
+        assert!(rendered.contains("\npragma solidity ^0.8.13;\n"));
+        assert!(rendered.contains("revert InkError(data);"));
+
+        let options = ["--solidity-version", ">=0.8.4 <0.9.0"];
+        let rendered = try_render_with(&metadata, &options).unwrap();
+        assert!(rendered.contains("\npragma solidity >=0.8.4 <0.9.0;\n"));
+        assert!(rendered.contains("error InkError(bytes data);"));
+
+        // Custom errors and `bytes.concat` are of 0.8.4
+        let rendered = try_render_with(&metadata, &["--solidity-version", "^0.8.0"]).unwrap();
+        assert!(rendered.contains("\npragma solidity ^0.8.0;\n"));
+        assert!(!rendered.contains("InkError(bytes data)"));
+        assert!(!rendered.contains("revert InkError(data);"));
+        assert!(!rendered.contains("bytes.concat("));
+        assert!(rendered.contains("bytes memory input = abi.encodePacked("));
+        let reason = "bytes memory reason = abi.encodeWithSignature(\"InkError(bytes)\", data);";
+        assert!(rendered.contains(reason));
+
+        // Nor can `bytes` be converted to `bytes32` before 0.8.5
+        assert!(rendered.contains("return abi.decode(output, (bytes32));"));
+        assert!(!rendered.contains("bytes32(output)"));
+
+        let error = try_render_with(&metadata, &["--solidity-version", "^0.7.6"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "incompatible options: --solidity-version `^0.7.6` admits compilers before 0.8.0, \
+             whose arithmetic is unchecked"
+        );
+
+        for requirement in ["0.8", "^0.8.x", "latest", ""] {
+            let args = ["sumi", "--solidity-version", requirement];
+            assert!(Args::try_parse_from(args).is_err(), "{requirement}");
+        }
+    }
+
+    #[test]
+    fn license() {
+        let metadata = sample("ink-erc20-v4");
+        let rendered = try_render(&metadata).unwrap();
+        assert!(rendered.starts_with("// SPDX-License-Identifier: UNLICENSED\npragma"));
+
+        let license = "(Apache-2.0 OR MIT) AND GPL-2.0+ WITH Classpath-exception-2.0";
+        let rendered = try_render_with(&metadata, &["--license", license]).unwrap();
+        assert!(rendered.starts_with(&format!("// SPDX-License-Identifier: {license}\n")));
+
+        for license in ["", "MIT OR", "(MIT", "MIT)", "MIT WITH", "GPL 2"] {
+            let args = ["sumi", "--license", license];
+            assert!(Args::try_parse_from(args).is_err(), "{license}");
+        }
+    }
+
+    #[test]
+    fn interface() {
+        let metadata = sample("ink-erc20");
+        let rendered = try_render_with(&metadata, &["--emit", "interface"]).unwrap();
+
+        assert!(rendered.contains("\ninterface Ierc20 {\n"));
+        assert!(rendered.contains(
+            "    /// @notice Returns the total token supply.\n    \
+             /// @dev Read-only in ink!, but XVM has no query path, so it is called like any other message\n    \
+             function total_supply(\n    \
+             ) external\n    \
+             returns (uint128);\n"
+        ));
+        assert!(rendered.contains("function balance_of(\n        bytes32  owner\n    ) external\n"));
+        assert!(rendered.contains("    event Transfer(\n"));
+        assert!(rendered.contains(") external pure returns (bytes memory);"));
+        assert!(rendered.trim_end().ends_with(";\n}"));
+
+        // Neither XVM calls nor the codec they need
+        assert!(!rendered.contains("interface XVM"));
+        assert!(!rendered.contains("XVM_PRECOMPILE"));
+        assert!(!rendered.contains("function encode_"));
+        assert!(!rendered.contains("ink_address"));
+        assert!(!rendered.contains("{\n        bytes4 selector"));
+
+        // Structs are still defined, as signatures refer to them
+        let types = json!([
+            { "id": 0, "type": { "def": { "primitive": "u32" } } },
+            {
+                "id": 1,
+                "type": {
+                    "def": { "composite": { "fields": [{ "name": "amount", "type": 0 }] } },
+                    "path": ["sample", "Payment"],
+                },
+            },
+        ]);
+        let metadata = message_metadata(types, json!([arg("payment", 1)]), 0);
+        let rendered = try_render_with(&metadata, &["--emit", "interface"]).unwrap();
+        assert!(rendered.contains("    struct sample_Payment {\n        uint32 amount;\n    }"));
+        let call = "function call(\n        sample_Payment memory payment\n    ) external";
+        assert!(rendered.contains(call));
+        assert!(!rendered.contains("function encode_sample_Payment("));
+
+        let error = try_render_with(&metadata, &["--emit", "skeleton"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "incompatible options: only bindings and interfaces are emitted in ink-to-evm mode"
+        );
+    }
+
+    #[test]
+    fn library_shape() {
+        let rendered = try_render_with(&sample("ink-erc20"), &["--solidity-shape", "library"]);
+        let rendered = rendered.unwrap();
+
+        assert!(rendered.contains("\nlibrary erc20 {\n    XVM constant XVM_PRECOMPILE"));
+        assert!(!rendered.contains("address ink_address;"));
+        assert!(!rendered.contains("constructor ("));
+        assert!(!rendered.contains(") public"));
+        assert!(rendered.contains(
+            "function balance_of(\n        address ink_address,\n        bytes32  owner\n    ) internal\n"
+        ));
+        let total_supply = "function total_supply(\n        address ink_address\n    ) internal\n";
+        assert!(rendered.contains(total_supply));
+        assert!(rendered.contains(") internal pure returns (bytes memory) {"));
+        assert!(rendered.contains("bytes memory contract_address = abi.encodePacked(ink_address);"));
+
+        // Libraries can't inherit shared definitions, but call the same `ScaleCodec`
+        let first = serde_json::to_vec(&sample("ink-erc20")).unwrap();
+        let second = serde_json::to_vec(&sample("ink-psp22")).unwrap();
+        let args = Args::parse_from("sumi --mode ink-to-evm --solidity-shape library".split(' '));
+        let (mut first, mut second) = (first.as_slice(), second.as_slice());
+        let rendered = render_all(&mut [&mut first as &mut dyn Read, &mut second], &args).unwrap();
+        assert!(!rendered.contains("SumiCodec"));
+        assert_eq!(rendered.matches("function encode_compact(").count(), 1);
+    }
+
+    /// Compiles both shapes of every ink! sample with `solc`, for the default and the oldest
+    /// accepted `--solidity-version`, run with `--ignored`
+    #[test]
+    #[ignore = "needs solc on PATH"]
+    fn solc() {
+        use std::{
+            io::Write,
+            process::{Command, Stdio},
+        };
+
+        let (shapes, versions) = (["contract", "library"], ["^0.8.13", "^0.8.0"]);
+        for (shape, version) in shapes.into_iter().cartesian_product(versions) {
+            for name in ["ink-erc20", "ink-erc20-v4", "ink-erc20-v5", "ink-psp22"] {
+                let options = ["--solidity-shape", shape, "--solidity-version", version];
+                let rendered = try_render_with(&sample(name), &options).unwrap();
+                let mut solc = Command::new("solc")
+                    .arg("-")
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::piped())
+                    .spawn()
+                    .expect("solc is not on PATH");
+
+                let mut stdin = solc.stdin.take().unwrap();
+                stdin.write_all(rendered.as_bytes()).unwrap();
+                drop(stdin);
+
+                let output = solc.wait_with_output().unwrap();
+                let errors = String::from_utf8_lossy(&output.stderr);
+                let case = format!("{name} as {shape} for {version}");
+                assert!(output.status.success(), "{case}:\n{errors}");
             }
+        }
+    }
 
-            Ok(())
-        } else {
-            return Err(GenericError {
-                msg: format!("invalid type id {:?}", value),
-            });
+    #[test]
+    fn scale_codec() {
+        let rendered = render_sample("ink-erc20");
+        let (codec, contract) = rendered.split_once("contract erc20 {").unwrap();
+
+        // Helpers are only declared by the library, which types and functions call into
+        assert!(codec.contains("\nlibrary ScaleCodec {\n"));
+        assert!(codec.contains("function little_endian(uint256 value, uint256 length) internal"));
+        assert!(!contract.contains("function little_endian("));
+        assert!(!contract.contains("function encode_compact("));
+        assert!(contract.contains("ScaleCodec.encode_compact(input.length),"));
+        assert!(contract.contains(
+            "return (uint128(uint128(ScaleCodec.from_little_endian(data, offset, 16))), offset + 16);"
+        ));
+        assert!(contract.trim_end().ends_with("\n    }\n}"));
+
+        // Interfaces call nothing
+        let options = ["--emit", "interface"];
+        let rendered = try_render_with(&sample("ink-erc20"), &options).unwrap();
+        assert!(!rendered.contains("ScaleCodec"));
+    }
+
+    #[test]
+    fn decoders() {
+        let rendered = render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "u32" } } },
+                { "id": 2, "type": { "def": { "primitive": "u8" } } },
+                { "id": 3, "type": { "def": { "tuple": [1, 0] } } },
+                {
+                    "id": 4,
+                    "type": {
+                        "def": { "variant": { "variants": [
+                            { "index": 0, "name": "Low" },
+                            { "index": 1, "name": "High" },
+                        ]}},
+                        "path": ["sample", "Level"],
+                    },
+                },
+                { "id": 5, "type": { "def": { "array": { "len": 2, "type": 3 } } } },
+                { "id": 6, "type": { "def": { "array": { "len": 40, "type": 2 } } } },
+                {
+                    "id": 7,
+                    "type": {
+                        "def": { "composite": { "fields": [
+                            { "name": "level", "type": 4 },
+                            { "name": "pairs", "type": 5 },
+                            { "name": "key", "type": 6 },
+                        ]}},
+                        "path": ["sample", "Entry"],
+                    },
+                },
+            ]),
+            json!([]),
+            7,
+        );
+
+        assert!(rendered.contains("(sample_Entry memory output, ) = decode_sample_Entry(data, 0);"));
+        assert!(rendered.contains(
+            "returns (sample_Entry memory, uint256) {\n        \
+             sample_Entry memory value;\n        \
+             (value.level, offset) = decode_sample_Level(data, offset);\n        \
+             (value.pairs, offset) = decode_Array_Tuple_uint32_bool_2(data, offset);\n        \
+             (value.key, offset) = decode_bytes40(data, offset);\n\n        \
+             return (value, offset);"
+        ));
+        assert!(rendered.contains(
+            "returns (Tuple_uint32_bool memory, uint256) {\n        \
+             Tuple_uint32_bool memory value;\n        \
+             (value.f0, offset) = decode_uint32(data, offset);\n        \
+             (value.f1, offset) = decode_bool(data, offset);"
+        ));
+        assert!(rendered.contains(
+            "returns (Tuple_uint32_bool[2] memory, uint256) {\n        \
+             Tuple_uint32_bool[2] memory value;\n        \
+             for (uint256 i = 0; i < 2; i++) {\n            \
+             (value[i], offset) = decode_Tuple_uint32_bool(data, offset);"
+        ));
+        assert!(rendered.contains(
+            "require(uint8(data[offset]) < 2, \"invalid sample_Level\");\n        \
+             return (sample_Level(uint8(data[offset])), offset + 1);"
+        ));
+        assert!(rendered.contains("return ScaleCodec.decode_fixed_bytes(data, offset, 40);"));
+    }
+
+    #[test]
+    fn little_endian_integers() {
+        let rendered = render_message(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "u16" } } },
+                { "id": 2, "type": { "def": { "primitive": "u32" } } },
+                { "id": 3, "type": { "def": { "primitive": "u128" } } },
+                { "id": 4, "type": { "def": { "primitive": "i64" } } },
+                {
+                    "id": 5,
+                    "type": {
+                        "def": { "composite": { "fields": [
+                            { "name": "a", "type": 3 },
+                            { "name": "b", "type": 4 },
+                        ]}},
+                        "path": ["sample", "Position"],
+                    },
+                },
+                { "id": 6, "type": { "def": { "array": { "len": 2, "type": 1 } } } },
+            ]),
+            json!([arg("count", 2), arg("at", 5), arg("pair", 6)]),
+            0,
+        );
+
+        // Arguments, struct fields and array elements all go through the per-byte encoders
+        assert!(rendered.contains("encode_uint32(count),\n            encode_sample_Position(at)"));
+        assert!(rendered.contains("encode_uint128(value.a),\n            encode_int64(value.b)"));
+        assert!(rendered.contains("buffer = bytes.concat(buffer, encode_uint16(value[i]));"));
+        assert!(rendered.contains("return ScaleCodec.little_endian(uint256(uint16(value)), 2);"));
+        assert!(rendered.contains("return ScaleCodec.little_endian(uint256(uint32(value)), 4);"));
+        assert!(rendered.contains("return ScaleCodec.little_endian(uint256(uint128(value)), 16);"));
+        assert!(rendered.contains("return ScaleCodec.little_endian(uint256(uint64(value)), 8);"));
+        assert!(rendered.contains("buffer[i] = bytes1(uint8(value >> (8 * i)));"));
+
+        // The Foundry test runs the emitted `little_endian` against `parity-scale-codec`, least
+        // significant byte first and signed integers as their two's complement
+        let test = foundry_test("ink-erc20");
+        assert!(test.contains("function test_little_endian() public {"));
+        assert!(test.contains("assertEq(ScaleCodec.little_endian(258, 2), hex\"0201\");"));
+        assert!(test.contains("assertEq(ScaleCodec.little_endian(1234, 4), hex\"d2040000\");"));
+        assert!(test.contains(
+            "assertEq(ScaleCodec.little_endian(18446744073709551614, 8), hex\"feffffffffffffff\");"
+        ));
+        let (max, encoded) = (u128::MAX, "ff".repeat(16));
+        let max = format!("little_endian({max}, 16), hex\"{encoded}\");");
+        assert!(test.contains(&max));
+    }
+
+    #[test]
+    fn foundry_tests() {
+        let render_files = |metadata: &Value, options: &[&str]| {
+            let args = ["sumi", "--mode", "ink-to-evm", "--output-dir", "out"];
+            let args = Args::parse_from(args.iter().chain(options));
+            let metadata = serde_json::to_vec(metadata).unwrap();
+            let reader: &mut dyn Read = &mut metadata.as_slice();
+            super::render_files(&mut [reader], &args)
+        };
+
+        let files = render_files(&sample("ink-erc20"), &["--with-tests"]).unwrap();
+        assert_eq!(files[0].0, PathBuf::from("erc20.sol"));
+        assert_eq!(files[1].0, PathBuf::from("erc20.t.sol"));
+
+        let test = &files[1].1;
+        let header = "// SPDX-License-Identifier: UNLICENSED\npragma solidity ^0.8.13;";
+        assert!(test.starts_with(header));
+        assert!(test.contains("import \"forge-std/Test.sol\";\nimport \"./erc20.sol\";"));
+        assert!(test.contains("contract erc20Test is Test {"));
+        assert!(test.contains("assertEq(ScaleCodec.encode_compact(16384), hex\"02000100\");"));
+        assert!(test.contains(
+            "(value, next) = ScaleCodec.decode_compact(hex\"0300000040\", 0);\n        \
+             assertEq(value, 1073741824);\n        \
+             assertEq(next, 5);"
+        ));
+        assert!(test.contains(
+            "erc20 bindings = new erc20(address(0));\n        \
+             bytes memory data = bindings.new_init_data(\n            \
+             hex\"abababababababababababababababababababababababababababababababab\",\n            \
+             hex\"73616c74\",\n            \
+             0x0102030405060708090a0b0c0d0e0f10\n        );"
+        ));
+
+        // No value, the code hash, then the selector and the balance in little endian
+        // following their compact length, and the salt likewise
+        let balance = 0x0102030405060708090a0b0c0d0e0f10u128.to_le_bytes();
+        let expected = [
+            &[0; 16][..],
+            &[0xab; 32],
+            &[20 << 2, 0x9b, 0xae, 0x9d, 0x5e],
+            &balance,
+            &[4 << 2],
+            b"salt",
+        ];
+        let expected = format!("assertEq(data, hex\"{}\");", hex::encode(expected.concat()));
+        assert!(test.contains(&expected));
+
+        // Libraries are called directly
+        let options = ["--with-tests", "--solidity-shape", "library"];
+        let files = render_files(&sample("ink-erc20"), &options).unwrap();
+        let (_, test) = &files[1];
+        assert!(test.contains("bytes memory data = erc20.new_init_data(\n"));
+
+        // Constructors taking values with no literal are left out
+        let mut metadata = message_metadata(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "u32" } } },
+                { "id": 1, "type": { "def": { "sequence": { "type": 0 } } } },
+            ]),
+            json!([]),
+            0,
+        );
+        metadata["V3"]["spec"]["constructors"] = json!([
+            {
+                "args": [arg("values", 1)],
+                "docs": [],
+                "label": "new",
+                "payable": false,
+                "selector": "0x00000002",
+            },
+            {
+                "args": [arg("value", 0)],
+                "docs": [],
+                "label": "one",
+                "payable": false,
+                "selector": "0x00000003",
+            },
+        ]);
+
+        let files = render_files(&metadata, &["--with-tests"]).unwrap();
+        let test = &files[1].1;
+        assert!(test.contains("// `sample.new_init_data` takes arguments with no sample value"));
+        assert!(!test.contains("function test_sample_new()"));
+        assert!(test.contains("bindings.one_init_data(\n"));
+        assert!(test.contains("0x01020304\n        );"));
+
+        // Without `--with-tests` only the Solidity file is written
+        let files = render_files(&metadata, &[]).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, PathBuf::from("sample.sol"));
+
+        let args = Args::parse_from(["sumi", "--mode", "ink-to-evm", "--with-tests"]);
+        let bytes = serde_json::to_vec(&metadata).unwrap();
+        let reader: &mut dyn Read = &mut bytes.as_slice();
+        let error = super::render_files(&mut [reader], &args).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "incompatible options: Foundry tests are only written to --output-dir"
+        );
+
+        let error = render_files(&metadata, &["--with-tests", "--emit", "interface"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "incompatible options: Foundry tests are only emitted for bindings"
+        );
+    }
+
+    /// Runs the Foundry tests of both shapes of every ink! sample with `forge`, so the generated
+    /// `ScaleCodec` is checked against `parity-scale-codec` as Solidity, run with `--ignored`
+    #[test]
+    #[ignore = "needs forge on PATH and network access"]
+    fn forge() {
+        use std::{fs, path::Path, process::Command};
+
+        let run = |dir: &Path, command: &str, args: &[&str]| {
+            let output = Command::new(command)
+                .args(args)
+                .current_dir(dir)
+                .output()
+                .unwrap_or_else(|_| panic!("{command} is not on PATH"));
+
+            let errors = String::from_utf8_lossy(&output.stderr);
+            let (report, dir) = (String::from_utf8_lossy(&output.stdout), dir.display());
+            assert!(output.status.success(), "{dir}:\n{report}{errors}");
+        };
+
+        // Every project shares one checkout of `forge-std`
+        let scratch = std::env::temp_dir().join("sumi-forge");
+        let forge_std = scratch.join("forge-std");
+        if !forge_std.exists() {
+            let url = "https://github.com/foundry-rs/forge-std";
+            let clone = ["clone", "--depth", "1", url, "forge-std"];
+            fs::create_dir_all(&scratch).unwrap();
+            run(&scratch, "git", &clone);
         }
-    });
 
-    #[derive(Serialize, Debug)]
-    struct RenderContext {
-        module_name: String,
-        project: Rc<InkProject>,
+        let config = format!(
+            "[profile.default]\nsrc = \"src\"\ntest = \"src\"\nremappings = [\"forge-std/={}/src/\"]\n",
+            forge_std.display()
+        );
+        for shape in ["contract", "library"] {
+            for name in ["ink-erc20", "ink-erc20-v4", "ink-erc20-v5", "ink-psp22"] {
+                let dir = scratch.join(format!("{name}-{shape}"));
+                let src = dir.join("src");
+                fs::create_dir_all(&src).unwrap();
+                fs::write(dir.join("foundry.toml"), &config).unwrap();
+
+                let args = ["sumi", "--mode", "ink-to-evm", "--output-dir", "src"];
+                let options = ["--with-tests", "--solidity-shape", shape];
+                let args = Args::parse_from(args.iter().chain(&options));
+                let metadata = serde_json::to_vec(&sample(name)).unwrap();
+                let reader: &mut dyn Read = &mut metadata.as_slice();
+                for (path, content) in render_files(&mut [reader], &args).unwrap() {
+                    fs::write(src.join(path), content).unwrap();
+                }
+
+                run(&dir, "forge", &["test"]);
+            }
+        }
     }
 
-    Ok(template.render(
-        "module",
-        &RenderContext {
-            module_name: module_name.clone().unwrap_or_else(|| {
-                metadata["contract"]["name"]
-                    .as_str()
-                    .unwrap_or("?")
-                    .to_owned()
-            }),
-            project: project.clone(),
-        },
-    )?)
-}
+    #[test]
+    fn abi_json() {
+        let render_files = |metadata: &Value, options: &[&str]| {
+            let args = ["sumi", "--mode", "ink-to-evm", "-o", "erc20.sol"];
+            let options = ["--emit-abi", "abi.json"].iter().chain(options);
+            let args = Args::parse_from(args.iter().chain(options));
+            let metadata = serde_json::to_vec(metadata).unwrap();
+            let reader: &mut dyn Read = &mut metadata.as_slice();
+            super::render_files(&mut [reader], &args)
+        };
 
-#[test]
-fn type_conversion() {}
+        let files = render_files(&sample("ink-erc20"), &[]).unwrap();
+        assert_eq!(files[1].0, PathBuf::from("abi.json"));
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    // use scale_info::{meta_type, PortableRegistry, Registry};
+        let contract = ethabi::Contract::load(files[1].1.as_bytes()).unwrap();
+        let constructor = contract.constructor.as_ref().unwrap();
+        assert_eq!(constructor.inputs[0].kind, ethabi::ParamType::Address);
+        assert!(contract.errors.contains_key("InkError"));
 
-    /* #[test]
-    fn type_registry() {
-        let mut ink_registry = Registry::new();
-        let array_type_id = ink_registry.register_type(&meta_type::<[u8; 20]>()).id();
+        let balance_of = contract.function("balance_of").unwrap();
+        assert_eq!(balance_of.inputs[0].name, "owner");
+        assert_eq!(balance_of.inputs[0].kind, ethabi::ParamType::FixedBytes(32));
+        assert_eq!(balance_of.outputs[0].kind, ethabi::ParamType::Uint(128));
+        assert_eq!(
+            balance_of.state_mutability,
+            ethabi::StateMutability::NonPayable
+        );
 
-        let ink_registry: PortableRegistry = ink_registry.into();
-        // let evm_registry = EvmTypeRegistry::new();
+        // `Result<(), Error>` is decoded into whether it is `Ok`
+        let transfer = contract.function("transfer").unwrap();
+        assert_eq!(transfer.outputs[0].kind, ethabi::ParamType::Bool);
 
-        let evm_registry = Rc::new(RefCell::new(EvmTypeRegistry::new()));
-        let context = Context::new(project.clone());
+        let init_data = contract.function("new_init_data").unwrap();
+        let names = init_data.inputs.iter().map(|param| &param.name);
+        assert!(names.eq(["code_hash", "salt", "initial_supply"]));
+        assert_eq!(init_data.outputs[0].kind, ethabi::ParamType::Bytes);
 
-        evm_registry.convert_type(array_type_id, ink_registry.resolve(array_type_id).unwrap(), context);
+        // Options are tuples of the flag and the value
+        let transfer = contract.event("Transfer").unwrap();
+        let option = ethabi::ParamType::Tuple(vec![
+            ethabi::ParamType::Bool,
+            ethabi::ParamType::FixedBytes(32),
+        ]);
+        assert_eq!(transfer.inputs[0].kind, option);
+        assert!(transfer.inputs[0].indexed);
+        assert!(!transfer.inputs[2].indexed);
 
-        dbg!(&evm_registry);
+        let decoder = contract.function("decodeTransferEvent").unwrap();
+        assert_eq!(decoder.outputs.len(), 3);
+        assert_eq!(decoder.state_mutability, ethabi::StateMutability::Pure);
 
+        // Enums with fields are tuples of the tag and the fields of each variant
+        let metadata = message_metadata(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "u32" } } },
+                {
+                    "id": 1,
+                    "type": {
+                        "path": ["Shape"],
+                        "def": {
+                            "variant": {
+                                "variants": [
+                                    { "name": "Empty", "index": 0 },
+                                    { "name": "Square", "index": 1, "fields": [{ "type": 0 }] },
+                                ],
+                            },
+                        },
+                    },
+                },
+            ]),
+            json!([arg("shape", 1)]),
+            0,
+        );
+        let files = render_files(&metadata, &[]).unwrap();
+        let contract = ethabi::Contract::load(files[1].1.as_bytes()).unwrap();
+        let message = contract.functions().next().unwrap();
+        let square = ethabi::ParamType::Tuple(vec![ethabi::ParamType::Uint(32)]);
         assert_eq!(
-            evm_registry.lookup(array_type_id),
-            Some(&EvmType {
-                reference: "uint8[20]".to_owned(),
-                ..EvmType::default()
-            })
+            message.inputs[0].kind,
+            ethabi::ParamType::Tuple(vec![ethabi::ParamType::Uint(8), square])
         );
 
+        let error = render_files(&metadata, &["--solidity-shape", "library"]).unwrap_err();
         assert_eq!(
-            evm_registry.lookup(1),
-            Some(&EvmType {
-                reference: "uint8".to_owned(),
-                ..EvmType::default()
-            })
+            error.to_string(),
+            "incompatible options: libraries have no ABI, as their functions are internal"
         );
-    } */
+
+        // Solidity code written to stdout has no directory to put the ABI next to
+        let args = ["sumi", "--mode", "ink-to-evm", "--emit-abi", "abi.json"];
+        let metadata = serde_json::to_vec(&sample("ink-erc20")).unwrap();
+        let reader: &mut dyn Read = &mut metadata.as_slice();
+        let error = super::render_files(&mut [reader], &Args::parse_from(args)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "incompatible options: --emit-abi is written next to --output or into --output-dir"
+        );
+    }
+
     #[test]
-    fn encode() {
-        use parity_scale_codec::Encode;
-        dbg!([(1u8, 2u8), (3u8, 4u8)].encode().bytes());
-        dbg!(vec![1u8, 2, 3, 4, 5].encode().bytes());
+    fn xvm_address() {
+        let metadata = sample("ink-erc20");
+        let address = "0x0000000000000000000000000000000000005006";
+        let rendered = try_render_with(&metadata, &["--xvm-address", address]).unwrap();
+        assert!(rendered.contains(&format!("XVM constant XVM_PRECOMPILE = XVM({address});")));
+        assert!(rendered.contains("constructor (address _ink_address) {"));
+
+        // One contract serves several networks if the constructor takes the address
+        let options = ["--xvm-address", address, "--xvm-address-arg"];
+        let rendered = try_render_with(&metadata, &options).unwrap();
+        assert!(rendered.contains(&format!(
+            "contract erc20 {{\n    \
+             XVM constant DEFAULT_XVM_PRECOMPILE = XVM({address});\n\n    \
+             // XVM precompile of the network, set by the constructor\n    \
+             XVM immutable XVM_PRECOMPILE;\n\n"
+        )));
+        assert!(rendered.contains(
+            "    address ink_address;\n\n    \
+             // `_xvm_address` overrides `DEFAULT_XVM_PRECOMPILE` unless zero\n    \
+             constructor (address _ink_address, address _xvm_address) {\n        \
+             ink_address = _ink_address;\n        \
+             XVM_PRECOMPILE = _xvm_address == address(0) ? DEFAULT_XVM_PRECOMPILE : XVM(_xvm_address);\n    \
+             }\n"
+        ));
+        assert!(rendered.contains("XVM_PRECOMPILE.xvm_call("));
+
+        let options = ["--xvm-address-arg", "--solidity-shape", "library"];
+        let error = try_render_with(&metadata, &options).unwrap_err();
+        assert!(matches!(error, Error::InvalidOptions(_)));
+        let error = try_render_with(&metadata, &["--xvm-address-arg", "--emit", "interface"]);
+        assert!(matches!(error, Err(Error::InvalidOptions(_))));
+
+        // Malformed addresses are rejected along with the arguments
+        assert!(Args::try_parse_from(["sumi", "--xvm-address", "0x50050"]).is_err());
+        assert!(Args::try_parse_from(["sumi", "--xvm-address", "xvm"]).is_err());
+    }
+
+    #[test]
+    fn xvm_context() {
+        // VM id of ink! contracts, then compact unlimited `ref_time` and `proof_size`,
+        // and neither storage deposit limit nor environment
+        let rendered = render_sample("ink-erc20");
+        assert!(rendered.contains("xvm_call(\"\\x1f\\x00\\x00\\x00\\x00\", contract_address,"));
+        assert_eq!(
+            solidity_xvm_context(XvmVersion::V2, Some((0, 0))),
+            "\"\\x1f\\x00\\x00\\x00\\x00\""
+        );
+
+        // A single compact weight in the legacy context
+        let rendered = try_render_with(&sample("ink-erc20"), &["--xvm-version", "v1"]).unwrap();
+        assert!(rendered.contains("xvm_call(\"\\x1f\\x00\", contract_address,"));
+        let context = solidity_xvm_context(XvmVersion::V1, Some((0, 0)));
+        assert_eq!(context, "\"\\x1f\\x00\"");
+
+        // Same layout as the context of generated ink! code, following the VM id
+        assert_eq!(super::xvm_context(XvmVersion::V2, (0, 0)), [0x00; 4]);
+        assert_eq!(super::xvm_context(XvmVersion::V1, (0, 0)), [0x00]);
+
+        // The VM id and unlimited weight and storage deposit in the v3 struct
+        assert_eq!(
+            solidity_xvm_context(XvmVersion::V3, Some((0, 0))),
+            "XVM.Context(0x1f, 0, 0, 0)"
+        );
+    }
+
+    #[test]
+    fn weight_limits() {
+        let metadata = message_metadata(
+            json!([{ "id": 0, "type": { "def": { "primitive": "bool" } } }]),
+            json!([]),
+            0,
+        );
+
+        // `--weight-limit` is encoded into the context while generating the code
+        let limit = ["--weight-limit", "1000000000,65536"];
+        let rendered = try_render_with(&metadata, &limit).unwrap();
+        assert!(rendered.contains(
+            "xvm_call(\"\\x1f\\x02\\x28\\x6b\\xee\\x02\\x00\\x04\\x00\\x00\\x00\", contract_address,"
+        ));
+        assert_eq!(Compact(1_000_000_000u64).encode(), [0x02, 0x28, 0x6b, 0xee]);
+        assert_eq!(Compact(65536u64).encode(), [0x02, 0x00, 0x04, 0x00]);
+        assert!(rendered.contains("function call(\n    ) public\n"));
+
+        let v1 = try_render_with(&metadata, &[limit[0], limit[1], "--xvm-version", "v1"]).unwrap();
+        assert!(v1.contains("xvm_call(\"\\x1f\\x02\\x28\\x6b\\xee\", contract_address,"));
+        let v3 = try_render_with(&metadata, &[limit[0], limit[1], "--xvm-version", "v3"]).unwrap();
+        assert!(v3.contains("xvm_call_v3(XVM.Context(0x1f, 1000000000, 65536, 0), "));
+
+        // With `--weight-args` every message takes the limit last, encoded by the codec
+        let rendered = try_render_with(&metadata, &["--weight-args"]).unwrap();
+        assert!(rendered.contains(
+            "    /// @param ref_time `ref_time` weight limit of the ink! call, zero meaning unlimited\n    \
+             /// @param proof_size `proof_size` weight limit of the ink! call, zero meaning unlimited\n    \
+             function call(\n        \
+             uint64 ref_time,\n        \
+             uint64 proof_size\n    \
+             ) public\n"
+        ));
+        assert!(rendered.contains(
+            "XVM_PRECOMPILE.xvm_call(bytes.concat(hex\"1f\", \
+             ScaleCodec.encode_compact(ref_time), ScaleCodec.encode_compact(proof_size), hex\"0000\"), \
+             contract_address, buffer, 0);"
+        ));
+
+        // Which matches the context of `--weight-limit`
+        let (ref_time, proof_size) = (1_000_000_000, 65536);
+        let encoded = [
+            vec![0x1f],
+            solidity_compact(ref_time),
+            solidity_compact(proof_size),
+            vec![0x00, 0x00],
+        ];
+        let context = super::xvm_context(XvmVersion::V2, (ref_time as u64, proof_size as u64));
+        assert_eq!(encoded.concat(), [vec![0x1f], context].concat());
+
+        let options = ["--weight-args", "--xvm-version", "v1"];
+        let rendered = try_render_with(&metadata, &options).unwrap();
+        assert!(rendered.contains("function call(\n        uint64 ref_time\n    ) public\n"));
+        assert!(rendered.contains(
+            "xvm_call(bytes.concat(hex\"1f\", ScaleCodec.encode_compact(ref_time)), contract_address,"
+        ));
+        assert!(!rendered.contains("proof_size"));
+
+        let options = ["--weight-args", "--xvm-version", "v3"];
+        let rendered = try_render_with(&metadata, &options).unwrap();
+        assert!(rendered.contains("xvm_call_v3(XVM.Context(0x1f, ref_time, proof_size, 0), "));
+
+        // Following the arguments of the message, and the address in libraries
+        let types = json!([
+            { "id": 0, "type": { "def": { "primitive": "bool" } } },
+            { "id": 1, "type": { "def": { "primitive": "u32" } } },
+        ]);
+        let metadata = message_metadata(types, json!([arg("amount", 1)]), 0);
+        let options = ["--weight-args", "--solidity-shape", "library"];
+        let rendered = try_render_with(&metadata, &options).unwrap();
+        assert!(rendered.contains(
+            "function call(\n        \
+             address ink_address,\n        \
+             uint32  amount,\n        \
+             uint64 ref_time,\n        \
+             uint64 proof_size\n    \
+             ) internal"
+        ));
+
+        let options = ["--weight-args", "--emit", "interface"];
+        let rendered = try_render_with(&metadata, &options).unwrap();
+        assert!(rendered.contains("uint64 proof_size\n    ) external"));
+
+        // Arguments named like the limits are renamed
+        let metadata = message_metadata(
+            json!([
+                { "id": 0, "type": { "def": { "primitive": "bool" } } },
+                { "id": 1, "type": { "def": { "primitive": "u64" } } },
+            ]),
+            json!([arg("ref_time", 1)]),
+            0,
+        );
+        let rendered = try_render_with(&metadata, &["--weight-args"]).unwrap();
+        assert!(rendered.contains("uint64  ref_time_,\n        uint64 ref_time,\n"));
+    }
+
+    #[test]
+    fn xvm_v3() {
+        let mut metadata = message_metadata(
+            json!([{ "id": 0, "type": { "def": { "primitive": "bool" } } }]),
+            json!([]),
+            0,
+        );
+        let rendered = try_render_with(&metadata, &["--xvm-version", "v3"]).unwrap();
+        assert!(rendered.contains(
+            "    struct Context {\n        \
+             uint8 vm_id;\n        \
+             uint64 ref_time_limit;\n        \
+             uint64 proof_size_limit;\n        \
+             uint128 storage_deposit_limit;\n    \
+             }\n"
+        ));
+        assert!(rendered.contains("enum CallStatus { Success, Revert, Error }"));
+        assert!(rendered.contains(
+            "        uint256 value\n    \
+             ) external payable returns (CallStatus status, bytes memory data);\n}"
+        ));
+        assert!(!rendered.contains("function xvm_call("));
+
+        // Reverts are told apart from other failures
+        assert!(rendered.contains(
+            "(XVM.CallStatus status, bytes memory data) = \
+             XVM_PRECOMPILE.xvm_call_v3(XVM.Context(0x1f, 0, 0, 0), contract_address, buffer, 0);\n        \
+             require(status != XVM.CallStatus.Revert, \"XVM call reverted by the ink! contract\");\n        \
+             require(status == XVM.CallStatus.Success, \"XVM call failed\");\n"
+        ));
+        assert!(!rendered.contains("require(success"));
+
+        // The precompile must implement the v3 interface
+        assert!(rendered.contains("calls through those of\n    // an earlier one fail"));
+
+        metadata["V3"]["spec"]["messages"][0]["payable"] = json!(true);
+        let rendered = try_render_with(&metadata, &["--xvm-version", "v3"]).unwrap();
+        assert!(rendered.contains(
+            "XVM_PRECOMPILE.xvm_call_v3{value: msg.value}(XVM.Context(0x1f, 0, 0, 0), \
+             contract_address, buffer, msg.value);"
+        ));
+        assert!(rendered.contains(") public payable\n"));
+
+        let rendered = try_render(&metadata).unwrap();
+        assert!(!rendered.contains("xvm_call_v3"));
+        assert!(!rendered.contains("v3 precompile"));
     }
 }
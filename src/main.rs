@@ -7,26 +7,44 @@ use clap::Parser;
 use error::Error;
 use std::{
     fs,
-    io::{self, BufRead, BufReader, BufWriter, Write},
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
 };
 
 fn main() -> anyhow::Result<()> {
     let args = cli::Args::parse();
 
-    let mut reader: Box<dyn BufRead> = match args.input {
-        Some(filename) => Box::new(BufReader::new(fs::File::open(&filename).map_err(|e| {
-            Error::ReadInput {
-                path: filename,
-                inner: e,
-            }
-        })?)),
-        None => Box::new(BufReader::new(io::stdin())),
+    let inputs = match args.mode {
+        cli::Mode::EvmToInk if args.input.len() > 1 => {
+            return Err(Error::InvalidOptions(
+                "several inputs are only supported in ink-to-evm mode".to_owned(),
+            )
+            .into())
+        }
+        cli::Mode::EvmToInk => args.input.clone(),
+        cli::Mode::InkToEvm => metadata_files(&args.input)?,
     };
 
-    let mut writer: Box<dyn Write> = match args.output {
-        Some(filename) => Box::new(BufWriter::new(fs::File::create(&filename).map_err(
+    let mut readers = inputs
+        .iter()
+        .map(|filename| -> Result<Box<dyn BufRead>, Error> {
+            let file = fs::File::open(filename).map_err(|e| Error::ReadInput {
+                path: filename.clone(),
+                inner: e,
+            })?;
+
+            Ok(Box::new(BufReader::new(file)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if readers.is_empty() {
+        readers.push(Box::new(BufReader::new(io::stdin())));
+    }
+
+    let mut writer: Box<dyn Write> = match &args.output {
+        Some(filename) => Box::new(BufWriter::new(fs::File::create(filename).map_err(
             |e| Error::WriteOutput {
-                path: filename,
+                path: filename.clone(),
                 inner: e,
             },
         )?)),
@@ -37,20 +55,119 @@ fn main() -> anyhow::Result<()> {
         cli::Mode::EvmToInk => {
             let parsed_json = {
                 let mut buffer = String::new();
-                reader.read_to_string(&mut buffer)?;
+                readers[0].read_to_string(&mut buffer)?;
 
                 json::parse(&buffer).map_err(Error::from)?
             };
 
-            sol2ink::render(parsed_json, &args.module_name.unwrap(), &args.evm_id)?
+            let mut modules = sol2ink::render_all(parsed_json, &args)?;
+
+            if let Some(output_dir) = &args.output_dir {
+                write_modules(output_dir, &modules, &args.license)?;
+                return Ok(());
+            }
+
+            if modules.len() != 1 {
+                return Err(Error::MultipleContracts(modules.len()).into());
+            }
+
+            // Without output directory every module consists of a single file
+            let (_, mut files) = modules.remove(0);
+            files.remove(0).1
         }
 
         cli::Mode::InkToEvm => {
-            ink2sol::render(&mut reader, &args.module_name)?
-        },
+            let mut readers = readers
+                .iter_mut()
+                .map(|reader| reader as &mut dyn Read)
+                .collect::<Vec<_>>();
+
+            let mut files = ink2sol::render_files(&mut readers, &args)?;
+            if let Some(output_dir) = &args.output_dir {
+                for (file_name, source) in files {
+                    write_file(output_dir, &file_name, &format!("{}\n", source.trim_end()))?;
+                }
+                return Ok(());
+            }
+
+            // Without output directory the Solidity file is written to the output, the ABI
+            // JSON next to it
+            let output_dir = args.output.as_deref().and_then(Path::parent);
+            for (file_name, source) in files.drain(1..) {
+                let contents = format!("{}\n", source.trim_end());
+                write_file(output_dir.unwrap_or(Path::new("")), &file_name, &contents)?;
+            }
+            files.remove(0).1
+        }
     };
 
     write!(writer, "{}\n", rendered)?;
 
     Ok(())
 }
+
+/// Writes files of every module and ties modules together with `mod.rs`
+fn write_modules(
+    output_dir: &Path,
+    modules: &[(String, Vec<(PathBuf, String)>)],
+    license: &str,
+) -> Result<(), Error> {
+    let mut mod_file = format!(
+        "// SPDX-License-Identifier: {license}\n//! This file was autogenerated by Sumi\n\n"
+    );
+    for (name, files) in modules {
+        for (file_name, source) in files {
+            write_file(output_dir, file_name, &format!("{}\n", source.trim_end()))?;
+        }
+
+        mod_file.push_str(&format!("pub mod {name};\n"));
+    }
+
+    write_file(output_dir, Path::new("mod.rs"), &mod_file)
+}
+
+/// Writes a file at `file_name` relative to `output_dir`, creating directories on the way
+fn write_file(output_dir: &Path, file_name: &Path, contents: &str) -> Result<(), Error> {
+    let path = output_dir.join(file_name);
+    let directory = path.parent().unwrap_or(output_dir);
+
+    fs::create_dir_all(directory)
+        .and_then(|_| fs::write(&path, contents))
+        .map_err(|inner| Error::WriteOutput { path, inner })
+}
+
+/// Expands directories among `inputs` into the ink! metadata files they contain
+fn metadata_files(inputs: &[PathBuf]) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    for input in inputs {
+        if !input.is_dir() {
+            files.push(input.clone());
+            continue;
+        }
+
+        let entries = fs::read_dir(input).map_err(|inner| Error::ReadInput {
+            path: input.clone(),
+            inner,
+        })?;
+
+        let mut contained = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let extension = path.extension().and_then(|extension| extension.to_str());
+            if matches!(extension, Some("json" | "contract")) {
+                contained.push(path);
+            }
+        }
+
+        // Bundles are preferred over the metadata `cargo-contract` writes next to them
+        contained.sort();
+        contained.retain(|path| {
+            path.extension()
+                .map_or(true, |extension| extension != "json")
+                || !path.with_extension("contract").exists()
+        });
+        files.extend(contained);
+    }
+
+    Ok(files)
+}
@@ -1,14 +1,71 @@
-use crate::error::Error;
+use crate::{
+    cli::{
+        AddressMapping, AddressMode, AddressType, Args, Codec, Emit, EmitStyle, ErrorStyle,
+        Target, WrapperStyle, XvmVersion,
+    },
+    error::Error,
+};
 use convert_case::{Case, Casing};
 use ethabi::ParamType;
 use hex::ToHex;
 use itertools::Itertools;
+use parity_scale_codec::{Compact, Encode};
 use serde::Serialize;
 use sha3::{Digest, Keccak256};
-use std::collections::HashMap;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
 use tinytemplate::{format_unescaped, TinyTemplate};
 
 static MODULE_TEMPLATE: &'static str = include_str!("../templates/ink-module.txt");
+static MOCK_TEMPLATE: &'static str = include_str!("../templates/ink-mock.txt");
+static SKELETON_TEMPLATE: &'static str = include_str!("../templates/ink-skeleton.txt");
+static TYPES_TEMPLATE: &'static str = include_str!("../templates/ink-types.txt");
+static STUBS_TEMPLATE: &'static str = include_str!("../templates/ink-stubs.txt");
+static E2E_TEMPLATE: &'static str = include_str!("../templates/ink-e2e.txt");
+static STRUCTS_TEMPLATE: &'static str = include_str!("../templates/ink-structs.txt");
+static TYPES_ONLY_TEMPLATE: &'static str = include_str!("../templates/ink-types-only.txt");
+static CALL_TEMPLATE: &'static str = include_str!("../templates/ink-call.txt");
+
+/// Ties together files of a split module
+static SPLIT_MOD_FILE: &'static str = "//! This file was autogenerated by Sumi
+
+pub mod calls;
+pub mod types;
+
+pub use self::calls::*;
+pub use self::types::*;
+";
+
+/// Signature of the batching function a `Multicall` builder is emitted for
+static MULTICALL_SIGNATURE: &str = "multicall(bytes[])";
+
+/// Status codes of failed XVM calls reported by the chain extension, along with
+/// the `XvmCallError` variants they map to and their docs
+static XVM_CALL_ERRORS: &[(u32, &str, &str)] = &[
+    (1, "ExecutionFailed", "Target VM failed executing the call"),
+    (2, "OutOfWeight", "Call ran out of the weight it was given"),
+    (3, "InvalidContext", "XVM context could not be decoded"),
+    (4, "InvalidVmId", "Context targets an unknown VM"),
+    (5, "SameVmCallDenied", "Calls into the VM of the caller are not allowed"),
+    (6, "ReentranceDenied", "Reentrant XVM calls are not allowed"),
+];
+
+/// Declares end-to-end tests of a split module
+static E2E_MOD_DECLARATION: &'static str = "
+#[cfg(all(test, feature = \"e2e-tests\"))]
+mod e2e;
+";
+
+/// Allows for lints commonly tripped by generated code.
+/// Emitted unless `--no-default-attrs` is passed.
+static DEFAULT_ATTRIBUTES: &[&str] = &[
+    "#![allow(missing_docs)]",
+    "#![allow(clippy::too_many_arguments, clippy::from_over_into)]",
+];
 
 #[derive(Serialize)]
 struct Input {
@@ -19,15 +76,45 @@ struct Input {
 
     // Equivalent type to use in ink! code
     rust_type: String,
+
+    // Expression producing the `Token` for this input
+    tokenize: String,
+
+    // Value passed by the generated end-to-end test
+    placeholder: String,
 }
 
 #[derive(Serialize)]
 pub struct Function {
     name: String,
+
+    // How the function is called in Rust
+    rust_name: String,
+
     inputs: Vec<Input>,
     output: String,
     selector: String,
     selector_hash: String,
+
+    // Rust type equivalent to the Solidity outputs
+    return_type: String,
+
+    // Whether function may alter contract state
+    mutates: bool,
+    payable: bool,
+
+    // Whether function takes native value to transfer
+    value: bool,
+
+    // Parameters following the inputs, e.g. `transferred_value: u128`
+    extra_params: String,
+    mock_extra_params: String,
+
+    // `ethabi::ParamType` expressions of the outputs, empty unless they are decoded
+    param_types: String,
+
+    // Rendered XVM invocation
+    xvm_call: String,
 }
 
 #[derive(Serialize)]
@@ -36,12 +123,85 @@ struct Variant {
     output: String,
     selector: String,
     selector_hash: String,
+    return_type: String,
+    mutates: bool,
+    payable: bool,
+}
+
+/// Function that cannot be translated, emitted as a `todo!()` stub
+#[derive(Serialize)]
+struct Stub {
+    rust_name: String,
+    signature: String,
+    selector_hash: String,
+
+    // Why translation failed
+    reason: String,
+
+    // Best-effort parameters, empty if any of them cannot be converted
+    inputs: Vec<Input>,
+}
+
+/// Call made by the generated end-to-end test
+#[derive(Serialize)]
+struct E2eCall {
+    rust_name: String,
+
+    // Placeholder arguments, ready to be pasted into the call
+    arguments: String,
+}
+
+/// Variant of the generated `XvmCallError`
+#[derive(Serialize)]
+struct CallError {
+    code: u32,
+    variant: &'static str,
+    doc: &'static str,
+}
+
+/// Row of the generated functions table in the module docs
+#[derive(Serialize)]
+struct SummaryEntry {
+    // How the function is called in Rust
+    name: String,
+
+    // Variant of the selector enum
+    variant: String,
+
+    signature: String,
+    selector_hash: String,
 }
 
 #[derive(Serialize)]
 struct OverloadedFunction {
     name: String,
+    rust_name: String,
+    output: String,
     variants: Vec<Variant>,
+
+    // Whether any variant takes native value to transfer
+    value: bool,
+
+    extra_params: String,
+    mock_extra_params: String,
+
+    // `ethabi::ParamType` expressions of the outputs shared by all variants
+    param_types: String,
+
+    xvm_call: String,
+}
+
+/// Context of the XVM invocation template
+#[derive(Serialize)]
+struct CallSite<'a> {
+    #[serde(flatten)]
+    module: &'a Module,
+
+    // Whether the call transfers native `value`
+    value: bool,
+
+    // Output types to decode returned data as, if any
+    param_types: &'a str,
 }
 
 #[derive(Serialize)]
@@ -49,214 +209,3059 @@ struct Module {
     #[serde(rename = "module_name")]
     name: String,
     evm_id: String,
+    sumi_version: &'static str,
+
+    /// SCALE encoded XVM context following the VM id, as comma separated bytes
+    xvm_context: String,
+
+    /// Length of the whole context including the VM id
+    xvm_context_len: usize,
+
+    /// Calls go through the legacy v1 interface
+    xvm_v1: bool,
+
+    /// Calls go through the v3 interface taking a context struct
+    xvm_v3: bool,
+
+    /// Weight limit of v3 calls, as `Option` expression
+    v3_weight_limit: String,
+
+    /// Generated functions take a `WeightLimit` argument
+    weight_args: bool,
+
+    /// Generated functions take an `Option<u128>` storage deposit limit argument
+    storage_deposit_args: bool,
+
+    /// XVM context is encoded on every call rather than being a constant
+    runtime_context: bool,
+
+    /// Generated functions take an `Option<u8>` overriding the EVM ID
+    evm_id_args: bool,
+
+    /// Generated functions take the address of the EVM contract rather than
+    /// the constructor
+    dynamic_address: bool,
+
+    /// Expression of the EVM contract address in a call
+    evm_address: &'static str,
+
+    /// EVM ID expression of a call
+    vm_id: String,
+
+    /// `WeightLimit` expression of the module wide limit
+    default_weight_limit: String,
+
+    /// Chain extension function id of XVM calls, as hex literal
+    chain_extension_id: String,
+
+    /// Bindings declare their own XVM chain extension and environment
+    chain_extension: bool,
+
+    /// Path of the ink! environment the bindings run in
+    environment: String,
+
+    /// Prepended to names of generated types, e.g. `Erc20`
+    type_prefix: String,
+
+    /// Prepended to names of generated constants, e.g. `ERC20_`
+    const_prefix: String,
+
+    /// Keccak256 of the compact JSON representation of the input ABI
+    abi_hash: String,
+
+    /// Emit version and ABI hash in the header comment
+    banner: bool,
+
     functions: Vec<Function>,
     overloaded_functions: Vec<OverloadedFunction>,
+
+    /// Structs generated for ABI tuples
+    structs: Vec<Struct>,
+
+    /// Structs get explicit SCALE codec implementations instead of derived ones
+    manual_codec: bool,
+
+    /// Rendered definitions of `structs`
+    struct_definitions: String,
+
+    /// Only types are emitted, so structs are not tokenized
+    types_only: bool,
+
+    /// Every generated function in ABI order
+    summary: Vec<SummaryEntry>,
+
+    /// Emit `Selector` enum covering every generated function
+    selector_enum: bool,
+
+    /// Attributes emitted verbatim at the top of the module
+    attributes: Vec<String>,
+
+    /// Functions are methods of a helper struct rather than contract messages
+    helper: bool,
+
+    /// Trait implemented by the messages, if any
+    trait_name: Option<String>,
+
+    /// Addresses are exposed as `AccountId` and need a conversion helper
+    account_id_addresses: bool,
+
+    /// `AccountId` maps to `H160` through its Keccak256 hash rather than truncation
+    keccak_mapping: bool,
+
+    /// Functions return `Result` with a generated `XvmError` instead of `bool`
+    result_errors: bool,
+
+    /// Known status codes of failed XVM calls
+    call_errors: Vec<CallError>,
+
+    /// Emit trace output of every call behind the `sumi-trace` feature
+    with_tracing: bool,
+
+    /// Types are written to a separate file
+    split: bool,
+
+    /// Rendered type definitions
+    types: String,
+
+    /// Outputs of calls are decoded from the returned data
+    decode_outputs: bool,
+
+    /// Contract batches calls through `multicall(bytes[])`, so calldata encoders
+    /// and a `Multicall` builder are emitted
+    multicall: bool,
+
+    /// Rendered off-chain mock module, if requested
+    mock: Option<String>,
+
+    /// Functions that cannot be translated
+    unsupported: Vec<Stub>,
+
+    /// Rendered stubs of unsupported functions, if any
+    stubs: Option<String>,
+
+    /// Declare the end-to-end test module
+    with_e2e: bool,
+
+    /// Call exercised by the end-to-end test, if any
+    e2e: Option<E2eCall>,
+}
+
+/// Strict and reserved keywords that cannot be used as function names
+static RUST_KEYWORDS: &[&str] = &[
+    "Self", "abstract", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let head_valid = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_');
+
+    head_valid
+        && name != "_"
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !RUST_KEYWORDS.contains(&name)
+}
+
+/// Custom Rust names of functions keyed by their Solidity signature,
+/// e.g. `"safeTransferFrom(address,address,uint256,bytes)" = "safe_transfer_from_with_data"`.
+/// Renamed overloads become standalone functions.
+#[derive(Default)]
+pub struct Renames {
+    names: HashMap<String, String>,
+
+    // Signatures that matched a function of any rendered module
+    used: RefCell<HashSet<String>>,
+}
+
+impl Renames {
+    /// Reads the map from a `.toml` file or from JSON otherwise
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let source = fs::read_to_string(path).map_err(|e| Error::ReadInput {
+            path: path.to_owned(),
+            inner: e,
+        })?;
+
+        let is_toml = path.extension().map_or(false, |extension| extension == "toml");
+        Self::parse(&source, is_toml)
+    }
+
+    fn parse(source: &str, is_toml: bool) -> Result<Self, Error> {
+        let names: HashMap<String, String> = if is_toml {
+            toml::from_str(source).map_err(|e| Error::Rename(e.to_string()))?
+        } else {
+            serde_json::from_str(source).map_err(|e| Error::Rename(e.to_string()))?
+        };
+
+        if let Some((signature, name)) = names.iter().find(|(_, name)| !is_identifier(name)) {
+            return Err(Error::Rename(format!(
+                "`{name}` given for `{signature}` is not a valid identifier"
+            )));
+        }
+
+        Ok(Self {
+            names,
+            used: RefCell::default(),
+        })
+    }
+
+    /// Rust name of the function with given signature, snake cased Solidity name by default
+    fn rust_name(&self, function_name: &str, signature: &str) -> String {
+        match self.names.get(signature) {
+            Some(name) => {
+                self.used.borrow_mut().insert(signature.to_owned());
+                name.clone()
+            }
+            None => function_name.to_case(Case::Snake),
+        }
+    }
+
+    fn is_renamed(&self, signature: &str) -> bool {
+        self.names.contains_key(signature)
+    }
+
+    /// Prints a warning for every entry that matched no function, likely a stale one
+    fn warn_unused(&self) {
+        let used = self.used.borrow();
+        for signature in self
+            .names
+            .keys()
+            .filter(|signature| !used.contains(*signature))
+            .sorted()
+        {
+            eprintln!("warning: rename of `{signature}` matches no generated function");
+        }
+    }
+}
+
+/// Solidity signature of an ABI function item, e.g. `transfer(address,uint256)`
+fn signature(function_name: &str, function: &json::JsonValue) -> String {
+    format!(
+        "{function_name}({args})",
+        args = function["inputs"].members().map(canonical_type).join(","),
+    )
+}
+
+/// Type of an ABI parameter as used in signatures, with tuples spelled out
+fn canonical_type(param: &json::JsonValue) -> String {
+    let raw_type = param["type"].as_str().unwrap_or_default();
+    match raw_type.strip_prefix("tuple") {
+        Some(suffix) => format!(
+            "({}){suffix}",
+            param["components"].members().map(canonical_type).join(",")
+        ),
+        None => raw_type.to_owned(),
+    }
+}
+
+/// First 4 bytes of the Keccak256 hash of the signature, hex encoded
+fn selector_hash(signature: &str) -> String {
+    let mut hasher = Keccak256::new();
+    hasher.update(signature.as_bytes());
+    let selector_hash: &[u8] = &hasher.finalize();
+    let selector_hash: [u8; 4] = selector_hash[0..=3]
+        .try_into()
+        .expect("Keccac256 hash should contain at least 4 bytes");
+
+    selector_hash.encode_hex()
+}
+
+/// Parses VM id given either as hex with `0x` prefix or as decimal
+fn parse_vm_id(vm_id: &str) -> Result<u8, Error> {
+    match vm_id.strip_prefix("0x").or_else(|| vm_id.strip_prefix("0X")) {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => vm_id.parse(),
+    }
+    .map_err(|_| Error::InvalidOptions(format!("EVM ID `{vm_id}` is not a byte")))
+}
+
+/// SCALE encoded XVM context of a call limited by the given weight, following the VM id
+pub(crate) fn xvm_context(version: XvmVersion, (ref_time, proof_size): (u64, u64)) -> Vec<u8> {
+    match version {
+        // Compact weight
+        XvmVersion::V1 => Compact(ref_time).encode(),
+
+        // Compact `ref_time` and `proof_size`, no storage deposit limit, no environment
+        XvmVersion::V2 => [
+            Compact(ref_time).encode(),
+            Compact(proof_size).encode(),
+            vec![0x00, 0x00],
+        ]
+        .concat(),
+
+        // Context is passed as a struct instead
+        XvmVersion::V3 => vec![],
+    }
+}
+
+/// How ABI types are spelled in the generated code
+#[derive(Clone, Copy)]
+struct TypeNames<'a> {
+    address_type: AddressType,
+
+    // Prepended to every generated type, see `--type-prefix`
+    prefix: &'a str,
 }
 
-fn convert_type(ty: &ParamType) -> String {
+fn convert_type(ty: &ParamType, types: TypeNames) -> String {
     match ty {
         ParamType::Bool => "bool".to_owned(),
-        ParamType::Address => "H160".to_owned(),
-        ParamType::Array(inner) => format!("Vec<{}>", convert_type(inner)),
-        ParamType::FixedArray(inner, size) => format!("[{}; {}]", convert_type(inner), size),
-        ParamType::Tuple(inner) => format!("({})", inner.iter().map(convert_type).join(", ")),
-        ParamType::FixedBytes(size) => format!("FixedBytes<{}>", size),
+        ParamType::Address => match types.address_type {
+            AddressType::H160 => format!("{}H160", types.prefix),
+            AddressType::AccountId => "AccountId".to_owned(),
+            AddressType::Bytes20 => "[u8; 20]".to_owned(),
+        },
+        ParamType::Array(inner) => format!("Vec<{}>", convert_type(inner, types)),
+        ParamType::FixedArray(inner, size) => {
+            format!("[{}; {}]", convert_type(inner, types), size)
+        }
+        ParamType::Tuple(inner) => format!(
+            "({})",
+            inner
+                .iter()
+                .map(|ty| convert_type(ty, types))
+                .join(", ")
+        ),
+        ParamType::FixedBytes(size) => format!("{}FixedBytes<{}>", types.prefix, size),
         ParamType::Bytes => "Vec<u8>".to_owned(),
         ParamType::String => "String".to_owned(),
 
         ParamType::Int(size) => match size {
-            8 => "i8",
-            16 => "i16",
-            32 => "i32",
-            64 => "i64",
-            128 => "i128",
+            8 => "i8".to_owned(),
+            16 => "i16".to_owned(),
+            32 => "i32".to_owned(),
+            64 => "i64".to_owned(),
+            128 => "i128".to_owned(),
 
-            _ => "I256",
-        }
-        .to_owned(),
+            _ => format!("{}I256", types.prefix),
+        },
 
         ParamType::Uint(size) => match size {
-            8 => "u8",
-            16 => "u16",
-            32 => "u32",
-            64 => "u64",
-            128 => "u128",
+            8 => "u8".to_owned(),
+            16 => "u16".to_owned(),
+            32 => "u32".to_owned(),
+            64 => "u64".to_owned(),
+            128 => "u128".to_owned(),
 
-            _ => "U256",
-        }
-        .to_owned(),
+            _ => format!("{}U256", types.prefix),
+        },
     }
 }
 
-pub fn render(json: json::JsonValue, module_name: &str, evm_id: &str) -> Result<String, Error> {
-    let mut template = TinyTemplate::new();
-
-    template.set_default_formatter(&format_unescaped);
-    template.add_template("module", MODULE_TEMPLATE)?;
+/// Builds an expression converting `expr` of type `ty` into its `H160` based
+/// counterpart, so that every nested `address` can be tokenized. Returns `None`
+/// if no conversion is needed.
+fn convert_addresses(
+    ty: &ParamType,
+    expr: &str,
+    types: TypeNames,
+    depth: usize,
+) -> Option<String> {
+    match ty {
+        ParamType::Address => match types.address_type {
+            AddressType::H160 => None,
+            AddressType::AccountId => Some(format!("to_h160(&{expr})")),
+            AddressType::Bytes20 => Some(format!("{}H160::from({expr})", types.prefix)),
+        },
 
-    template.add_formatter("snake", |value, buffer| match value {
-        serde_json::Value::String(s) => {
-            buffer.push_str(&s.to_case(Case::Snake));
-            Ok(())
+        ParamType::Array(inner) => {
+            let item = format!("item{depth}");
+            convert_addresses(inner, &item, types, depth + 1).map(|converted| {
+                format!("{expr}.into_iter().map(|{item}| {converted}).collect::<Vec<_>>()")
+            })
         }
-        _ => Err(tinytemplate::error::Error::GenericError {
-            msg: "string value expected".to_owned(),
-        }),
-    });
 
-    template.add_formatter("upper_snake", |value, buffer| match value {
-        serde_json::Value::String(s) => {
-            buffer.push_str(&s.to_case(Case::UpperSnake));
-            Ok(())
+        ParamType::FixedArray(inner, _) => {
+            let item = format!("item{depth}");
+            convert_addresses(inner, &item, types, depth + 1)
+                .map(|converted| format!("{expr}.map(|{item}| {converted})"))
         }
-        _ => Err(tinytemplate::error::Error::GenericError {
-            msg: "string value expected".to_owned(),
-        }),
-    });
 
-    template.add_formatter("upper_camel", |value, buffer| match value {
-        serde_json::Value::String(s) => {
-            buffer.push_str(&s.to_case(Case::UpperCamel));
-            Ok(())
-        }
-        _ => Err(tinytemplate::error::Error::GenericError {
-            msg: "string value expected".to_owned(),
-        }),
-    });
+        ParamType::Tuple(inner) => {
+            let fields = (0..inner.len())
+                .map(|index| format!("t{depth}_{index}"))
+                .collect_vec();
 
-    template.add_formatter("capitalize", |value, buffer| match value {
-        serde_json::Value::String(s) => {
-            let (head, tail) = s.split_at(1);
+            let converted = inner
+                .iter()
+                .zip(&fields)
+                .map(|(ty, field)| convert_addresses(ty, field, types, depth + 1))
+                .collect_vec();
 
-            buffer.push_str(&head.to_uppercase());
-            buffer.push_str(tail);
+            if converted.iter().all(Option::is_none) {
+                return None;
+            }
 
-            Ok(())
+            Some(format!(
+                "{{ let ({fields},) = {expr}; ({converted},) }}",
+                fields = fields.join(", "),
+                converted = converted
+                    .into_iter()
+                    .zip(&fields)
+                    .map(|(converted, field)| converted.unwrap_or_else(|| field.clone()))
+                    .join(", "),
+            ))
         }
-        _ => Err(tinytemplate::error::Error::GenericError {
-            msg: "string value expected".to_owned(),
-        }),
-    });
 
-    let mut is_overloaded = HashMap::new();
-    for (index, function) in json
-        .members()
-        .enumerate()
-        .filter(|(_, item)| item["type"] == "function")
-        .filter(|(_, item)| item["stateMutability"] != "view")
-        .filter(|(_, item)| {
-            item["outputs"]
-                .members()
-                .all(|output| output["type"] == "bool")
-        })
-    {
-        let function_name = function["name"].as_str().ok_or_else(|| {
-            Error::Metadata(format!("'name' for ABI item {index} not exists or is not a string"))
-        })?;
+        _ => None,
+    }
+}
 
-        is_overloaded
-            .entry(function_name)
-            .and_modify(|v| *v = true)
-            .or_insert(false);
+/// Whether `ty` contains `bytes` anywhere
+fn contains_bytes(ty: &ParamType) -> bool {
+    match ty {
+        ParamType::Bytes => true,
+        ParamType::Array(inner) | ParamType::FixedArray(inner, _) => contains_bytes(inner),
+        ParamType::Tuple(inner) => inner.iter().any(contains_bytes),
+        _ => false,
     }
+}
 
-    let mut overloaded_functions = Vec::<OverloadedFunction>::new();
-    let mut functions = Vec::new();
+/// Builds an expression producing the `Token` of `expr`. Dynamic `bytes` are
+/// tokenized explicitly, since `Vec<u8>` would be encoded as a `uint8[]` array.
+fn tokenize(ty: &ParamType, expr: &str, types: TypeNames, depth: usize) -> String {
+    if !contains_bytes(ty) {
+        let converted =
+            convert_addresses(ty, expr, types, depth).unwrap_or_else(|| expr.to_owned());
+        return format!("{converted}.tokenize()");
+    }
 
-    for (index, function) in json
-        .members()
-        .enumerate()
-        .filter(|(_, item)| item["type"] == "function")
-        .filter(|(_, item)| item["stateMutability"] != "view")
-        .filter(|(_, item)| {
-            item["outputs"]
-                .members()
-                .all(|output| output["type"] == "bool")
-        })
-    {
-        let function_name = function["name"].as_str().ok_or_else(|| {
-            Error::Metadata(format!("'name' for ABI item {index} not exists or is not a string"))
-        })?;
+    let item = format!("item{depth}");
+    match ty {
+        ParamType::Bytes => format!("Token::Bytes({expr})"),
 
-        let inputs = function["inputs"]
-            .members()
-            .enumerate()
-            .map(|(index, input)| {
-                let name = input["name"].as_str().ok_or_else(|| {
-                    Error::Metadata(format!("invalid 'name' input parameter {index} of function {function_name}"))
-                })?;
+        ParamType::Array(inner) => format!(
+            "Token::Array({expr}.into_iter().map(|{item}| {}).collect())",
+            tokenize(inner, &item, types, depth + 1)
+        ),
 
-                let raw_type = input["type"].as_str().ok_or_else(|| {
-                    Error::Metadata(format!("invalid 'type' in input parameter item {name} ({index}) of function {function_name}"))
-                })?;
+        ParamType::FixedArray(inner, _) => format!(
+            "Token::FixedArray({expr}.into_iter().map(|{item}| {}).collect())",
+            tokenize(inner, &item, types, depth + 1)
+        ),
 
-                let param_type = ethabi::param_type::Reader::read(raw_type)?;
-                let converted = convert_type(&param_type);
+        ParamType::Tuple(inner) => {
+            let fields = (0..inner.len())
+                .map(|index| format!("t{depth}_{index}"))
+                .collect_vec();
 
-                Ok(Input {
-                    name: name.to_owned(),
-                    evm_type: raw_type.to_owned(),
-                    rust_type: converted,
-                })
-            })
-            .collect::<Result<Vec<Input>, Error>>()?;
+            format!(
+                "{{ let ({},) = {expr}; Token::Tuple(ink_prelude::vec![{}]) }}",
+                fields.join(", "),
+                inner
+                    .iter()
+                    .zip(&fields)
+                    .map(|(ty, field)| tokenize(ty, field, types, depth + 1))
+                    .join(", "),
+            )
+        }
 
-        // let outputs: String = function["outputs"].members().map(|m| format!("{}: {}, ", m["name"], m["type"])).collect();
+        _ => unreachable!("only `bytes` and their containers are tokenized explicitly"),
+    }
+}
 
-        let selector = format!(
-            "{function_name}({args})",
-            args = inputs.iter().map(|input| input.evm_type.as_str()).join(","),
-        );
+/// Builds an `ethabi::ParamType` expression of `ty` for generated code
+fn param_type_expr(ty: &ParamType) -> String {
+    match ty {
+        ParamType::Address => "ethabi::ParamType::Address".to_owned(),
+        ParamType::Bytes => "ethabi::ParamType::Bytes".to_owned(),
+        ParamType::Int(size) => format!("ethabi::ParamType::Int({size})"),
+        ParamType::Uint(size) => format!("ethabi::ParamType::Uint({size})"),
+        ParamType::Bool => "ethabi::ParamType::Bool".to_owned(),
+        ParamType::String => "ethabi::ParamType::String".to_owned(),
+        ParamType::FixedBytes(size) => format!("ethabi::ParamType::FixedBytes({size})"),
+        ParamType::Array(inner) => format!(
+            "ethabi::ParamType::Array(ink_prelude::boxed::Box::new({}))",
+            param_type_expr(inner)
+        ),
+        ParamType::FixedArray(inner, size) => format!(
+            "ethabi::ParamType::FixedArray(ink_prelude::boxed::Box::new({}), {size})",
+            param_type_expr(inner)
+        ),
+        ParamType::Tuple(inner) => format!(
+            "ethabi::ParamType::Tuple(ink_prelude::vec![{}])",
+            inner.iter().map(param_type_expr).join(", ")
+        ),
+    }
+}
 
-        let mut hasher = Keccak256::new();
-        hasher.update(selector.as_bytes());
-        let selector_hash: &[u8] = &hasher.finalize();
-        let selector_hash: [u8; 4] = selector_hash[0..=3]
-            .try_into()
-            .expect("Keccac256 hash should contain at least 4 bytes");
+/// Shape of an ABI parameter. Tuples described by `components` become generated
+/// structs, while everything else maps to Rust types directly.
+#[derive(Clone)]
+enum Shape {
+    Plain(ParamType),
+    Struct {
+        name: String,
+        param_type: ParamType,
 
-        if is_overloaded[function_name] {
-            let function = {
-                if let Some(function) = overloaded_functions
-                    .iter_mut()
-                    .find(|f| f.name == function_name)
-                {
-                    function
-                } else {
-                    overloaded_functions.push(OverloadedFunction {
-                        name: function_name.to_owned(),
-                        variants: Vec::new(),
-                    });
+        // Tuple the struct converts from and into
+        tuple_type: String,
+    },
+    Array(Box<Shape>),
+    FixedArray(Box<Shape>, usize),
+}
 
-                    overloaded_functions
-                        .last_mut()
-                        .expect("we've just pushed an item; cannot fail")
-                }
-            };
+impl Shape {
+    fn param_type(&self) -> ParamType {
+        match self {
+            Shape::Plain(param_type) | Shape::Struct { param_type, .. } => param_type.clone(),
+            Shape::Array(inner) => ParamType::Array(Box::new(inner.param_type())),
+            Shape::FixedArray(inner, size) => {
+                ParamType::FixedArray(Box::new(inner.param_type()), *size)
+            }
+        }
+    }
 
-            function.variants.push(Variant {
-                inputs,
-                output: "bool".to_owned(), // TODO
-                selector,
-                selector_hash: selector_hash.encode_hex(),
-            })
-        } else {
-            functions.push(Function {
-                name: function_name.to_owned(),
-                inputs,
-                output: "bool".to_owned(), // TODO
-                selector,
-                selector_hash: selector_hash.encode_hex(),
-            });
+    fn rust_type(&self, types: TypeNames) -> String {
+        match self {
+            Shape::Plain(param_type) => convert_type(param_type, types),
+            Shape::Struct { name, .. } => name.clone(),
+            Shape::Array(inner) => format!("Vec<{}>", inner.rust_type(types)),
+            Shape::FixedArray(inner, size) => {
+                format!("[{}; {}]", inner.rust_type(types), size)
+            }
         }
     }
 
-    let module = Module {
-        name: module_name.to_owned(),
-        evm_id: evm_id.to_owned(),
-        overloaded_functions,
-        functions,
+    /// Rust type with every struct replaced by its tuple counterpart
+    fn tuple_type(&self, types: TypeNames) -> String {
+        match self {
+            Shape::Plain(param_type) => convert_type(param_type, types),
+            Shape::Struct { tuple_type, .. } => tuple_type.clone(),
+            Shape::Array(inner) => format!("Vec<{}>", inner.tuple_type(types)),
+            Shape::FixedArray(inner, size) => {
+                format!("[{}; {}]", inner.tuple_type(types), size)
+            }
+        }
+    }
+
+    /// Builds an expression converting `expr` between its struct and tuple based
+    /// representations, whichever direction is expected. Returns `None` if the
+    /// shape contains no structs.
+    fn convert_structs(&self, expr: &str, depth: usize) -> Option<String> {
+        match self {
+            Shape::Plain(_) => None,
+            Shape::Struct { .. } => Some(format!("{expr}.into()")),
+
+            Shape::Array(inner) => {
+                let item = format!("item{depth}");
+                inner.convert_structs(&item, depth + 1).map(|converted| {
+                    format!("{expr}.into_iter().map(|{item}| {converted}).collect()")
+                })
+            }
+
+            Shape::FixedArray(inner, _) => {
+                let item = format!("item{depth}");
+                inner
+                    .convert_structs(&item, depth + 1)
+                    .map(|converted| format!("{expr}.map(|{item}| {converted})"))
+            }
+        }
+    }
+
+    /// Same as [`tokenize`], but leaves structs to their own `Tokenize` implementation
+    fn tokenize(&self, expr: &str, types: TypeNames, depth: usize) -> String {
+        let item = format!("item{depth}");
+        match self {
+            Shape::Plain(param_type) => tokenize(param_type, expr, types, depth),
+            Shape::Array(inner) if inner.contains_bytes() => format!(
+                "Token::Array({expr}.into_iter().map(|{item}| {}).collect())",
+                inner.tokenize(&item, types, depth + 1)
+            ),
+            Shape::FixedArray(inner, _) if inner.contains_bytes() => format!(
+                "Token::FixedArray({expr}.into_iter().map(|{item}| {}).collect())",
+                inner.tokenize(&item, types, depth + 1)
+            ),
+            _ => {
+                let converted = self
+                    .convert_addresses(expr, types, depth)
+                    .unwrap_or_else(|| expr.to_owned());
+                format!("{converted}.tokenize()")
+            }
+        }
+    }
+
+    /// Whether the shape contains `bytes` outside of structs
+    fn contains_bytes(&self) -> bool {
+        match self {
+            Shape::Plain(param_type) => contains_bytes(param_type),
+            Shape::Struct { .. } => false,
+            Shape::Array(inner) | Shape::FixedArray(inner, _) => inner.contains_bytes(),
+        }
+    }
+
+    /// Same as [`convert_addresses`], but leaves structs as is since they
+    /// convert their own fields when tokenized.
+    fn convert_addresses(
+        &self,
+        expr: &str,
+        types: TypeNames,
+        depth: usize,
+    ) -> Option<String> {
+        match self {
+            Shape::Plain(param_type) => convert_addresses(param_type, expr, types, depth),
+            Shape::Struct { .. } => None,
+
+            Shape::Array(inner) => {
+                let item = format!("item{depth}");
+                inner
+                    .convert_addresses(&item, types, depth + 1)
+                    .map(|converted| {
+                        format!("{expr}.into_iter().map(|{item}| {converted}).collect::<Vec<_>>()")
+                    })
+            }
+
+            Shape::FixedArray(inner, _) => {
+                let item = format!("item{depth}");
+                inner
+                    .convert_addresses(&item, types, depth + 1)
+                    .map(|converted| format!("{expr}.map(|{item}| {converted})"))
+            }
+        }
+    }
+}
+
+/// Compilable value of the given shape for generated tests,
+/// `None` if it has to be provided by hand
+fn placeholder(shape: &Shape, types: TypeNames) -> Option<String> {
+    let param_type = match shape {
+        Shape::Plain(param_type) => param_type,
+        Shape::Struct { .. } => return None,
+        Shape::Array(_) => return Some("Vec::new()".to_owned()),
+        Shape::FixedArray(inner, _) => {
+            return placeholder(inner, types)
+                .map(|item| format!("core::array::from_fn(|_| {item})"))
+        }
+    };
+
+    match param_type {
+        ParamType::Address => match types.address_type {
+            AddressType::H160 => Some(format!("{}H160::from([0u8; 20])", types.prefix)),
+            AddressType::AccountId => {
+                Some("ink_e2e::account_id(ink_e2e::AccountKeyring::Alice)".to_owned())
+            }
+            AddressType::Bytes20 => Some("[0u8; 20]".to_owned()),
+        },
+
+        ParamType::FixedBytes(size) => Some(format!("{}FixedBytes([0u8; {size}])", types.prefix)),
+        ParamType::Uint(size) if *size > 128 => {
+            Some(format!("{}U256::from([0u8; 32])", types.prefix))
+        }
+        ParamType::Int(size) if *size > 128 => {
+            Some(format!("{}I256::from([0u8; 32])", types.prefix))
+        }
+
+        ParamType::FixedArray(inner, _) => {
+            placeholder(&Shape::Plain(*inner.clone()), types)
+                .map(|item| format!("core::array::from_fn(|_| {item})"))
+        }
+
+        ParamType::Tuple(inner) => inner
+            .iter()
+            .map(|ty| placeholder(&Shape::Plain(ty.clone()), types))
+            .collect::<Option<Vec<_>>>()
+            .map(|items| format!("({},)", items.join(", "))),
+
+        _ => Some("Default::default()".to_owned()),
+    }
+}
+
+#[derive(Serialize, Clone, PartialEq)]
+struct Field {
+    name: String,
+    rust_type: String,
+    tuple_type: String,
+
+    // Expressions converting the field from the tuple and into it
+    from_tuple: String,
+    into_tuple: String,
+
+    // Expression producing the `Token` for this field
+    tokenize: String,
+}
+
+/// Rust struct generated for an ABI tuple
+#[derive(Serialize)]
+struct Struct {
+    name: String,
+
+    // Solidity type the struct came from, if known
+    internal_type: String,
+
+    fields: Vec<Field>,
+
+    // Whether any member, however deeply nested, is dynamic. Dynamic tuples are
+    // ABI encoded in the tail behind an offset, static ones inline.
+    dynamic: bool,
+}
+
+/// Reads an ABI parameter of a function or struct named `owner`.
+/// Every tuple with `components` is registered in `structs`, reusing an existing
+/// struct if it has the same name and fields.
+fn read_param(
+    param: &json::JsonValue,
+    owner: &str,
+    structs: &mut Vec<Struct>,
+    types: TypeNames,
+) -> Result<Shape, Error> {
+    let raw_type = param["type"]
+        .as_str()
+        .ok_or_else(|| Error::Metadata(format!("invalid 'type' of parameter of {owner}")))?;
+
+    let suffix = match raw_type.strip_prefix("tuple") {
+        Some(suffix) => suffix,
+        None => return Ok(Shape::Plain(ethabi::param_type::Reader::read(raw_type)?)),
+    };
+
+    if param["components"].is_empty() {
+        return Err(Error::Metadata(format!(
+            "tuple parameter of {owner} has no components"
+        )));
+    }
+
+    // `struct Market.Order[]` becomes `Order`
+    let internal_type = param["internalType"].as_str().unwrap_or_default();
+    let base_name = match internal_type
+        .strip_prefix("struct ")
+        .or_else(|| internal_type.strip_prefix("event "))
+    {
+        Some(name) => name
+            .split('[')
+            .next()
+            .and_then(|name| name.rsplit('.').next())
+            .unwrap_or(name)
+            .to_owned(),
+        None => format!(
+            "{owner}_{}",
+            param["name"].as_str().filter(|name| !name.is_empty()).unwrap_or("tuple")
+        )
+        .to_case(Case::UpperCamel),
+    };
+
+    let mut fields = Vec::new();
+    let mut param_types = Vec::new();
+    for (index, component) in param["components"].members().enumerate() {
+        let shape = read_param(component, &base_name, structs, types)?;
+        let name = match component["name"].as_str() {
+            Some(name) if !name.is_empty() => name.to_case(Case::Snake),
+            _ => format!("field_{index}"),
+        };
+
+        fields.push(Field {
+            rust_type: shape.rust_type(types),
+            tuple_type: shape.tuple_type(types),
+            from_tuple: shape
+                .convert_structs(&format!("tuple.{index}"), 0)
+                .unwrap_or_else(|| format!("tuple.{index}")),
+            into_tuple: shape
+                .convert_structs(&format!("value.{name}"), 0)
+                .unwrap_or_else(|| format!("value.{name}")),
+            tokenize: shape.tokenize(&format!("self.{name}"), types, 0),
+            name,
+        });
+
+        param_types.push(shape.param_type());
+    }
+
+    // Same struct is usually referenced by several functions
+    let mut name = format!("{}{base_name}", types.prefix);
+    let mut count = 1;
+    while let Some(existing) = structs.iter().find(|s| s.name == name) {
+        if existing.fields == fields {
+            break;
+        }
+
+        count += 1;
+        name = format!("{}{base_name}{count}", types.prefix);
+    }
+
+    let tuple_type = format!(
+        "({},)",
+        fields.iter().map(|field| field.tuple_type.as_str()).join(", ")
+    );
+
+    let param_type = ParamType::Tuple(param_types);
+    if !structs.iter().any(|s| s.name == name) {
+        structs.push(Struct {
+            name: name.clone(),
+            internal_type: internal_type.to_owned(),
+            fields,
+            dynamic: param_type.is_dynamic(),
+        });
+    }
+
+    let mut shape = Shape::Struct {
+        name,
+        param_type,
+        tuple_type,
+    };
+
+    // `tuple[3][]` is a dynamic array of fixed arrays
+    for dimension in suffix.split_terminator(']') {
+        shape = match dimension.strip_prefix('[') {
+            Some("") => Shape::Array(Box::new(shape)),
+            Some(size) => match size.parse() {
+                Ok(size) => Shape::FixedArray(Box::new(shape), size),
+                Err(_) => {
+                    return Err(Error::Metadata(format!(
+                        "invalid tuple type `{raw_type}` of parameter of {owner}"
+                    )))
+                }
+            },
+            None => {
+                return Err(Error::Metadata(format!(
+                    "invalid tuple type `{raw_type}` of parameter of {owner}"
+                )))
+            }
+        };
+    }
+
+    Ok(shape)
+}
+
+/// Walks the input looking for contract ABIs. Recognizes plain ABI arrays,
+/// `solc --combined-json` and standard JSON outputs, as well as build artifacts
+/// (or arrays of them) carrying an `abi` field. Plain ABIs have no name.
+fn collect_contracts(
+    name: Option<&str>,
+    value: &json::JsonValue,
+    contracts: &mut Vec<(Option<String>, json::JsonValue)>,
+) -> Result<(), Error> {
+    if value.is_array() {
+        if value.members().all(|item| item.has_key("type")) {
+            contracts.push((name.map(str::to_owned), value.clone()));
+        } else {
+            for item in value.members() {
+                collect_contracts(None, item, contracts)?;
+            }
+        }
+    } else if value.has_key("abi") {
+        let name = value["contractName"]
+            .as_str()
+            .or_else(|| value["name"].as_str())
+            .or(name);
+
+        // Older compilers emit ABI as a JSON encoded string
+        match value["abi"].as_str() {
+            Some(abi) => collect_contracts(name, &json::parse(abi)?, contracts)?,
+            None => collect_contracts(name, &value["abi"], contracts)?,
+        }
+    } else if value.has_key("contracts") {
+        collect_contracts(None, &value["contracts"], contracts)?;
+    } else if value.is_object() {
+        // Either `path:Name` keys of combined JSON or file names of standard JSON
+        for (key, item) in value.entries() {
+            let name = key.rsplit(':').next().unwrap_or(key);
+            collect_contracts(Some(name), item, contracts)?;
+        }
+    } else {
+        return Err(Error::Metadata(
+            "input is neither an ABI nor a set of contracts".to_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Renders a module for every contract found in the input.
+/// Returns pairs of module name and files generated for it.
+pub fn render_all(
+    json: json::JsonValue,
+    args: &Args,
+) -> Result<Vec<(String, Vec<(PathBuf, String)>)>, Error> {
+    let mut contracts = Vec::new();
+    collect_contracts(None, &json, &mut contracts)?;
+
+    let renames = match &args.rename {
+        Some(path) => Renames::load(path)?,
+        None => Renames::default(),
+    };
+
+    let mut seen = HashMap::new();
+    let modules = contracts
+        .into_iter()
+        .map(|(name, abi)| -> Result<(String, Vec<(PathBuf, String)>), Error> {
+            let mut module_name = match name {
+                Some(name) => name.to_case(Case::Snake),
+                None => args.module_name.clone().ok_or(Error::ModuleNameRequired)?,
+            };
+
+            // Same contract name may come from different source files
+            let count = seen.entry(module_name.clone()).or_insert(0);
+            *count += 1;
+            if *count > 1 {
+                module_name = format!("{module_name}_{count}");
+            }
+
+            let files = render(abi, &module_name, &renames, args)?;
+            Ok((module_name, files))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    renames.warn_unused();
+    Ok(modules)
+}
+
+/// Renders bindings of a single contract. Returns pairs of file path relative to
+/// the output directory and file contents. Unless split, the module is a single
+/// `<module_name>.rs` file, otherwise it is a directory with `types.rs`, `calls.rs`
+/// and `mod.rs` files.
+pub fn render(
+    json: json::JsonValue,
+    module_name: &str,
+    renames: &Renames,
+    args: &Args,
+) -> Result<Vec<(PathBuf, String)>, Error> {
+    if args.wrapper_style == WrapperStyle::Helper && args.emit_style == EmitStyle::TraitImpl {
+        return Err(Error::InvalidOptions(
+            "trait definitions are only available for message wrappers".to_owned(),
+        ));
+    }
+
+    if args.emit == Emit::Interface {
+        return Err(Error::InvalidOptions(
+            "interfaces are only emitted in ink-to-evm mode".to_owned(),
+        ));
+    }
+
+    if args.split && args.emit != Emit::Bindings {
+        return Err(Error::InvalidOptions(
+            "only bindings may be split".to_owned(),
+        ));
+    }
+
+    if args.with_e2e
+        && (args.output_dir.is_none()
+            || args.emit != Emit::Bindings
+            || args.wrapper_style != WrapperStyle::Message)
+    {
+        return Err(Error::InvalidOptions(
+            "end-to-end tests are only emitted for message bindings written to --output-dir"
+                .to_owned(),
+        ));
+    }
+
+    if args.with_tests {
+        return Err(Error::InvalidOptions(
+            "Foundry tests are only emitted in ink-to-evm mode".to_owned(),
+        ));
+    }
+
+    if args.emit_abi.is_some() {
+        return Err(Error::InvalidOptions(
+            "ABI JSON is only emitted in ink-to-evm mode".to_owned(),
+        ));
+    }
+
+    if args.xvm_address_arg {
+        return Err(Error::InvalidOptions(
+            "the XVM precompile address is only taken in ink-to-evm mode".to_owned(),
+        ));
+    }
+
+    if args.storage_deposit_args && args.xvm_version == XvmVersion::V1 {
+        return Err(Error::InvalidOptions(
+            "XVM v1 context has no storage deposit limit".to_owned(),
+        ));
+    }
+
+    if args.target == Target::ChainExtension && args.xvm_version == XvmVersion::V3 {
+        return Err(Error::InvalidOptions(
+            "the declared chain extension only supports XVM v1 and v2 contexts".to_owned(),
+        ));
+    }
+
+    let type_prefix = args.type_prefix.to_case(Case::UpperCamel);
+    if !args.type_prefix.is_empty() && !is_identifier(&type_prefix)
+        || !args.type_prefix.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(Error::InvalidOptions(format!(
+            "type prefix `{}` is not a valid identifier fragment",
+            args.type_prefix
+        )));
+    }
+
+    let types = TypeNames {
+        address_type: args.address_type,
+        prefix: &type_prefix,
+    };
+
+    parse_vm_id(&args.evm_id)?;
+    let context = xvm_context(args.xvm_version, args.weight_limit);
+    let abi_hash: String = Keccak256::digest(json.dump().as_bytes()).encode_hex();
+
+    let mut template = TinyTemplate::new();
+
+    template.set_default_formatter(&format_unescaped);
+    template.add_template("module", MODULE_TEMPLATE)?;
+    template.add_template("mock", MOCK_TEMPLATE)?;
+    template.add_template("skeleton", SKELETON_TEMPLATE)?;
+    template.add_template("types", TYPES_TEMPLATE)?;
+    template.add_template("stubs", STUBS_TEMPLATE)?;
+    template.add_template("e2e", E2E_TEMPLATE)?;
+    template.add_template("structs", STRUCTS_TEMPLATE)?;
+    template.add_template("types_only", TYPES_ONLY_TEMPLATE)?;
+    template.add_template("call", CALL_TEMPLATE)?;
+
+    template.add_formatter("snake", |value, buffer| match value {
+        serde_json::Value::String(s) => {
+            buffer.push_str(&s.to_case(Case::Snake));
+            Ok(())
+        }
+        _ => Err(tinytemplate::error::Error::GenericError {
+            msg: "string value expected".to_owned(),
+        }),
+    });
+
+    template.add_formatter("upper_snake", |value, buffer| match value {
+        serde_json::Value::String(s) => {
+            buffer.push_str(&s.to_case(Case::UpperSnake));
+            Ok(())
+        }
+        _ => Err(tinytemplate::error::Error::GenericError {
+            msg: "string value expected".to_owned(),
+        }),
+    });
+
+    template.add_formatter("upper_camel", |value, buffer| match value {
+        serde_json::Value::String(s) => {
+            buffer.push_str(&s.to_case(Case::UpperCamel));
+            Ok(())
+        }
+        _ => Err(tinytemplate::error::Error::GenericError {
+            msg: "string value expected".to_owned(),
+        }),
+    });
+
+    template.add_formatter("capitalize", |value, buffer| match value {
+        serde_json::Value::String(s) => {
+            let (head, tail) = s.split_at(1);
+
+            buffer.push_str(&head.to_uppercase());
+            buffer.push_str(tail);
+
+            Ok(())
+        }
+        _ => Err(tinytemplate::error::Error::GenericError {
+            msg: "string value expected".to_owned(),
+        }),
+    });
+
+    // Only v3 calls return data, decoded into `H160` wherever an `address` is expected
+    let decode_outputs = args.xvm_version == XvmVersion::V3
+        && args.emit == Emit::Bindings
+        && args.address_type == AddressType::H160;
+
+    let unsupported = |item: &json::JsonValue| {
+        let owner = item["name"].as_str().unwrap_or_default();
+        let unsupported_input = item["inputs"].members().find_map(|input| {
+            read_param(input, owner, &mut Vec::new(), types)
+                .err()
+                .map(|_| {
+                    format!(
+                        "input `{}` has unsupported type `{}`",
+                        input["name"].as_str().unwrap_or_default(),
+                        input["type"].as_str().unwrap_or_default(),
+                    )
+                })
+        });
+
+        if unsupported_input.is_some() || args.emit != Emit::Bindings || decode_outputs {
+            unsupported_input
+        } else if item["stateMutability"] == "view" {
+            Some("`view` functions cannot return data through XVM".to_owned())
+        } else if item["outputs"].members().any(|output| output["type"] != "bool") {
+            Some(format!(
+                "returns `({})` while XVM calls only report success as `bool`",
+                item["outputs"]
+                    .members()
+                    .map(|output| output["type"].as_str().unwrap_or_default())
+                    .join(","),
+            ))
+        } else {
+            None
+        }
+    };
+
+    let is_exported =
+        |item: &json::JsonValue| item["type"] == "function" && unsupported(item).is_none();
+
+    let mut is_overloaded = HashMap::new();
+    let mut renamed = HashSet::new();
+    for (index, function) in json
+        .members()
+        .enumerate()
+        .filter(|(_, item)| is_exported(item))
+    {
+        let function_name = function["name"].as_str().ok_or_else(|| {
+            Error::Metadata(format!("'name' for ABI item {index} not exists or is not a string"))
+        })?;
+
+        let signature = signature(function_name, function);
+        let rust_name = renames.rust_name(function_name, &signature);
+        let is_renamed = renames.is_renamed(&signature);
+
+        // Renamed functions may not share their name with any other one
+        match is_overloaded.get_mut(&rust_name) {
+            Some(_) if is_renamed || renamed.contains(&rust_name) => {
+                return Err(Error::Rename(format!(
+                    "`{rust_name}` is used by several functions of module `{module_name}`"
+                )));
+            }
+            Some(overloaded) => *overloaded = true,
+            None => {
+                is_overloaded.insert(rust_name.clone(), false);
+            }
+        }
+
+        if is_renamed {
+            renamed.insert(rust_name);
+        }
+    }
+
+    // Unless decoded, outputs are ignored and only the outcome of the call is reported
+    let output = match args.error_style {
+        ErrorStyle::Result => format!("Result<(), {type_prefix}XvmError>"),
+        ErrorStyle::Panic => "bool".to_owned(),
     };
 
-    Ok(template.render("module", &module)?)
+    let mut overloaded_functions = Vec::<OverloadedFunction>::new();
+    let mut functions = Vec::new();
+    let mut structs = Vec::new();
+    let mut summary = Vec::new();
+
+    for (index, function) in json
+        .members()
+        .enumerate()
+        .filter(|(_, item)| is_exported(item))
+    {
+        let function_name = function["name"].as_str().ok_or_else(|| {
+            Error::Metadata(format!("'name' for ABI item {index} not exists or is not a string"))
+        })?;
+
+        let inputs = function["inputs"]
+            .members()
+            .enumerate()
+            .map(|(index, input)| {
+                let name = input["name"].as_str().ok_or_else(|| {
+                    Error::Metadata(format!("invalid 'name' input parameter {index} of function {function_name}"))
+                })?;
+
+                let shape = read_param(input, function_name, &mut structs, types)?;
+
+                Ok(Input {
+                    name: name.to_owned(),
+                    evm_type: canonical_type(input),
+                    rust_type: shape.rust_type(types),
+                    tokenize: shape.tokenize(name, types, 0),
+                    placeholder: placeholder(&shape, types).unwrap_or_else(|| {
+                        format!("todo!(\"provide `{name}`\")")
+                    }),
+                })
+            })
+            .collect::<Result<Vec<Input>, Error>>()?;
+
+        let outputs = function["outputs"]
+            .members()
+            .enumerate()
+            .map(|(index, output)| {
+                if !output["type"].is_string() {
+                    return Err(Error::Metadata(format!("invalid 'type' in output parameter {index} of function {function_name}")));
+                }
+
+                read_param(output, function_name, &mut structs, types)
+            })
+            .collect::<Result<Vec<Shape>, Error>>()?;
+
+        let return_type = match outputs.as_slice() {
+            [output] => output.rust_type(types),
+            outputs => format!(
+                "({})",
+                outputs.iter().map(|output| output.rust_type(types)).join(", ")
+            ),
+        };
+
+        let mutates = function["stateMutability"] != "view" && function["stateMutability"] != "pure";
+        let payable = function["stateMutability"] == "payable";
+        let value = payable && args.xvm_version != XvmVersion::V1;
+
+        let selector = format!(
+            "{function_name}({args})",
+            args = inputs.iter().map(|input| input.evm_type.as_str()).join(","),
+        );
+
+        let selector_hash = selector_hash(&selector);
+
+        let rust_name = renames.rust_name(function_name, &selector);
+
+        let param_types = outputs
+            .iter()
+            .filter(|_| decode_outputs)
+            .map(|output| param_type_expr(&output.param_type()))
+            .join(", ");
+
+        let output = match args.error_style {
+            _ if param_types.is_empty() => output.clone(),
+            ErrorStyle::Result => format!("Result<{return_type}, {type_prefix}XvmError>"),
+            ErrorStyle::Panic => format!("Option<{return_type}>"),
+        };
+
+        if is_overloaded[&rust_name] {
+            let function = {
+                if let Some(function) = overloaded_functions
+                    .iter_mut()
+                    .find(|f| f.rust_name == rust_name)
+                {
+                    function
+                } else {
+                    overloaded_functions.push(OverloadedFunction {
+                        name: function_name.to_owned(),
+                        rust_name: rust_name.clone(),
+                        output: output.clone(),
+                        variants: Vec::new(),
+                        value: false,
+                        extra_params: String::new(),
+                        mock_extra_params: String::new(),
+                        param_types: param_types.clone(),
+                        xvm_call: String::new(),
+                    });
+
+                    overloaded_functions
+                        .last_mut()
+                        .expect("we've just pushed an item; cannot fail")
+                }
+            };
+
+            // Variants share the function and so the type its call returns
+            if function.param_types != param_types {
+                return Err(Error::Unsupported {
+                    signature: selector,
+                    reason: format!(
+                        "overloads of `{function_name}` return different types, which one function cannot decode"
+                    ),
+                });
+            }
+
+            summary.push(SummaryEntry {
+                name: format!(
+                    "{}({}{}Args::V{})",
+                    rust_name,
+                    type_prefix,
+                    rust_name.to_case(Case::UpperCamel),
+                    function.variants.len(),
+                ),
+                variant: format!(
+                    "{}V{}",
+                    rust_name.to_case(Case::UpperCamel),
+                    function.variants.len()
+                ),
+                signature: selector.clone(),
+                selector_hash: selector_hash.clone(),
+            });
+
+            function.value |= value;
+            function.variants.push(Variant {
+                inputs,
+                output: output.clone(),
+                selector,
+                selector_hash,
+                return_type,
+                mutates,
+                payable,
+            })
+        } else {
+            summary.push(SummaryEntry {
+                name: rust_name.clone(),
+                variant: rust_name.to_case(Case::UpperCamel),
+                signature: selector.clone(),
+                selector_hash: selector_hash.clone(),
+            });
+
+            functions.push(Function {
+                name: function_name.to_owned(),
+                rust_name,
+                inputs,
+                output,
+                selector,
+                selector_hash,
+                return_type,
+                mutates,
+                payable,
+                value,
+                extra_params: String::new(),
+                mock_extra_params: String::new(),
+                param_types,
+                xvm_call: String::new(),
+            });
+        }
+    }
+
+    let mut stubs = Vec::<Stub>::new();
+    for (index, function) in json
+        .members()
+        .enumerate()
+        .filter(|(_, item)| item["type"] == "function")
+    {
+        let reason = match unsupported(function) {
+            Some(reason) => reason,
+            None => continue,
+        };
+
+        let function_name = function["name"].as_str().ok_or_else(|| {
+            Error::Metadata(format!("'name' for ABI item {index} not exists or is not a string"))
+        })?;
+
+        let signature = signature(function_name, function);
+        if args.strict || args.emit != Emit::Bindings {
+            return Err(Error::Unsupported { signature, reason });
+        }
+
+        // Stubs must not clash with translated functions or each other
+        let base_name = renames.rust_name(function_name, &signature);
+        let mut rust_name = base_name.clone();
+        let mut count = 1;
+        while is_overloaded.contains_key(&rust_name)
+            || stubs.iter().any(|stub| stub.rust_name == rust_name)
+        {
+            count += 1;
+            rust_name = format!("{base_name}_{count}");
+        }
+
+        let inputs = function["inputs"]
+            .members()
+            .map(|input| {
+                let name = input["name"].as_str()?;
+                let shape =
+                    read_param(input, function_name, &mut structs, types).ok()?;
+
+                Some(Input {
+                    name: name.to_owned(),
+                    evm_type: canonical_type(input),
+                    rust_type: shape.rust_type(types),
+                    tokenize: String::new(),
+                    placeholder: String::new(),
+                })
+            })
+            .collect::<Option<Vec<Input>>>()
+            .unwrap_or_default();
+
+        stubs.push(Stub {
+            rust_name,
+            selector_hash: selector_hash(&signature),
+            signature,
+            reason,
+            inputs,
+        });
+    }
+
+    // Events are only of interest as types
+    if args.emit == Emit::Types {
+        for event in json
+            .members()
+            .filter(|item| item["type"] == "event" && !item["inputs"].is_empty())
+        {
+            let event_name = event["name"].as_str().unwrap_or_default();
+            let param = json::object! {
+                "type": "tuple",
+                "internalType": format!("event {event_name}"),
+                "components": event["inputs"].clone(),
+            };
+
+            read_param(&param, event_name, &mut structs, types)?;
+        }
+    }
+
+    // Messages, as well as selector enum variants, must be distinguishable
+    for (index, entry) in summary.iter().enumerate() {
+        if let Some(other) = summary[..index]
+            .iter()
+            .find(|other| other.selector_hash == entry.selector_hash)
+        {
+            return Err(Error::SelectorCollision {
+                first: other.signature.clone(),
+                second: entry.signature.clone(),
+                selector: entry.selector_hash.clone(),
+            });
+        }
+    }
+
+    // Parameters of XVM calls, along with their e2e placeholders
+    let call_params = [
+        (
+            "evm_address",
+            format!("{type_prefix}H160"),
+            "evm_address()".to_owned(),
+        ),
+        (
+            "weight_limit",
+            format!("{type_prefix}WeightLimit"),
+            format!("{type_prefix}WeightLimit::default()"),
+        ),
+        ("storage_deposit_limit", "Option<u128>".to_owned(), "None".to_owned()),
+        ("evm_id", "Option<u8>".to_owned(), "None".to_owned()),
+    ]
+    .into_iter()
+    .zip([
+        args.address_mode == AddressMode::Dynamic,
+        args.weight_args,
+        args.storage_deposit_args,
+        args.evm_id_args,
+    ])
+    .filter_map(|(param, enabled)| enabled.then_some(param))
+    .collect::<Vec<_>>();
+
+    // Payable functions take the value to transfer before other parameters
+    let value_param = ("transferred_value", "u128".to_owned(), "0".to_owned());
+    let extra_params = |value: bool| value.then_some(&value_param).into_iter().chain(&call_params);
+    let format_params = |value: bool, prefix: &str| {
+        extra_params(value)
+            .map(|(name, rust_type, _)| format!("{prefix}{name}: {rust_type}"))
+            .join(", ")
+    };
+
+    for function in &mut functions {
+        function.extra_params = format_params(function.value, "");
+        function.mock_extra_params = format_params(function.value, "_");
+    }
+
+    for function in &mut overloaded_functions {
+        function.extra_params = format_params(function.value, "");
+        function.mock_extra_params = format_params(function.value, "_");
+    }
+
+    let multicall = functions
+        .iter()
+        .map(|function| &function.selector)
+        .chain(overloaded_functions.iter().flat_map(|function| {
+            function.variants.iter().map(|variant| &variant.selector)
+        }))
+        .any(|selector| selector == MULTICALL_SIGNATURE);
+
+    // Test exercises the first function, preferably a plain one
+    let e2e = functions
+        .first()
+        .map(|function| E2eCall {
+            rust_name: function.rust_name.clone(),
+            arguments: function
+                .inputs
+                .iter()
+                .map(|input| &input.placeholder)
+                .chain(extra_params(function.value).map(|(_, _, placeholder)| placeholder))
+                .join(", "),
+        })
+        .or_else(|| {
+            overloaded_functions.first().map(|function| E2eCall {
+                rust_name: function.rust_name.clone(),
+                arguments: [format!(
+                    "{type_prefix}{}Args::V0 {{ {} }}",
+                    function.rust_name.to_case(Case::UpperCamel),
+                    function.variants[0]
+                        .inputs
+                        .iter()
+                        .map(|input| format!("{}: {}", input.name, input.placeholder))
+                        .join(", "),
+                )]
+                .into_iter()
+                .chain(
+                    extra_params(function.value).map(|(_, _, placeholder)| placeholder.clone()),
+                )
+                .join(", "),
+            })
+        })
+        .filter(|_| args.with_e2e);
+
+    let attributes = DEFAULT_ATTRIBUTES
+        .iter()
+        .filter(|_| !args.no_default_attrs)
+        .map(|attribute| attribute.to_string())
+        .chain(args.module_attr.iter().cloned())
+        .collect();
+
+    let const_prefix = match type_prefix.as_str() {
+        "" => String::new(),
+        prefix => format!("{}_", prefix.to_case(Case::UpperSnake)),
+    };
+
+    let mut module = Module {
+        name: module_name.to_owned(),
+        evm_id: args.evm_id.clone(),
+        sumi_version: env!("CARGO_PKG_VERSION"),
+        xvm_context: context.iter().map(|byte| format!("0x{byte:02x}")).join(", "),
+        xvm_context_len: context.len() + 1,
+        xvm_v1: args.xvm_version == XvmVersion::V1,
+        xvm_v3: args.xvm_version == XvmVersion::V3,
+        v3_weight_limit: match args.weight_limit {
+            (0, 0) => "None".to_owned(),
+            (ref_time, proof_size) => format!(
+                "Some(xvm_environment::v3::Weight::from_parts({ref_time}, {proof_size}))"
+            ),
+        },
+        weight_args: args.weight_args,
+        storage_deposit_args: args.storage_deposit_args,
+        runtime_context: args.weight_args || args.storage_deposit_args,
+        evm_id_args: args.evm_id_args,
+        dynamic_address: args.address_mode == AddressMode::Dynamic,
+        evm_address: match args.address_mode {
+            AddressMode::Static => "self.evm_address",
+            AddressMode::Dynamic => "evm_address",
+        },
+        vm_id: match args.evm_id_args {
+            true => format!("evm_id.unwrap_or(super::{const_prefix}DEFAULT_EVM_ID)"),
+            false => format!("super::{const_prefix}DEFAULT_EVM_ID"),
+        },
+        default_weight_limit: format!(
+            "{type_prefix}WeightLimit {{ ref_time: {}, proof_size: {} }}",
+            args.weight_limit.0, args.weight_limit.1,
+        ),
+        chain_extension_id: format!("{:#010x}", args.chain_extension_id),
+        chain_extension: args.target == Target::ChainExtension,
+        environment: match args.target {
+            Target::Precompile => "xvm_environment::XvmDefaultEnvironment".to_owned(),
+            Target::ChainExtension => format!("{type_prefix}XvmEnvironment"),
+        },
+        const_prefix,
+        type_prefix,
+        abi_hash,
+        banner: !args.no_banner,
+        overloaded_functions,
+        functions,
+        structs,
+        manual_codec: args.codec == Codec::Manual,
+        struct_definitions: String::new(),
+        types_only: args.emit == Emit::Types,
+        summary,
+        selector_enum: args.selector_enum,
+        attributes,
+        helper: args.wrapper_style == WrapperStyle::Helper,
+        trait_name: match args.emit_style {
+            EmitStyle::Inherent => None,
+            EmitStyle::TraitImpl => Some(args.trait_name.clone().unwrap_or_else(|| {
+                format!("{}Like", module_name.to_case(Case::UpperCamel))
+            })),
+        },
+        account_id_addresses: args.address_type == AddressType::AccountId,
+        keccak_mapping: args.address_mapping == AddressMapping::Keccak,
+        result_errors: args.error_style == ErrorStyle::Result,
+        call_errors: XVM_CALL_ERRORS
+            .iter()
+            .map(|&(code, variant, doc)| CallError { code, variant, doc })
+            .collect(),
+        with_tracing: args.with_tracing,
+        split: args.split,
+        types: String::new(),
+        decode_outputs,
+        multicall,
+        mock: None,
+        unsupported: stubs,
+        stubs: None,
+        with_e2e: args.with_e2e,
+        e2e,
+    };
+
+    module.struct_definitions = template.render("structs", &module)?;
+    module.types = template.render("types", &module)?;
+
+    let render_call = |value: bool, param_types: &str| {
+        template.render("call", &CallSite { module: &module, value, param_types })
+    };
+
+    let calls = module
+        .functions
+        .iter()
+        .map(|function| render_call(function.value, &function.param_types))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let overloaded_calls = module
+        .overloaded_functions
+        .iter()
+        .map(|function| render_call(function.value, &function.param_types))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (function, call) in module.functions.iter_mut().zip(calls) {
+        function.xvm_call = call;
+    }
+
+    for (function, call) in module.overloaded_functions.iter_mut().zip(overloaded_calls) {
+        function.xvm_call = call;
+    }
+
+    if !module.unsupported.is_empty() {
+        module.stubs = Some(template.render("stubs", &module)?);
+    }
+
+    let files = match args.emit {
+        Emit::Bindings => {
+            if args.with_mock {
+                module.mock = Some(template.render("mock", &module)?);
+            }
+
+            let calls = template.render("module", &module)?;
+            let directory = Path::new(module_name);
+
+            let mut files = if args.split {
+                let mut mod_file = SPLIT_MOD_FILE.to_owned();
+                if args.with_e2e {
+                    mod_file.push_str(E2E_MOD_DECLARATION);
+                }
+
+                vec![
+                    (directory.join("types.rs"), std::mem::take(&mut module.types)),
+                    (directory.join("calls.rs"), calls),
+                    (directory.join("mod.rs"), mod_file),
+                ]
+            } else {
+                vec![(PathBuf::from(format!("{module_name}.rs")), calls)]
+            };
+
+            if args.with_e2e {
+                files.push((directory.join("e2e.rs"), template.render("e2e", &module)?));
+            }
+
+            files
+        }
+
+        Emit::Types => vec![(
+            PathBuf::from(format!("{module_name}.rs")),
+            template.render("types_only", &module)?,
+        )],
+
+        Emit::Skeleton => vec![(
+            PathBuf::from(format!("{module_name}.rs")),
+            template.render("skeleton", &module)?,
+        )],
+
+        Emit::Interface => unreachable!("interfaces are rejected above"),
+    };
+
+    // Formatting drops regular comments, so the license is prepended afterwards
+    let license = format!("// SPDX-License-Identifier: {}\n", args.license);
+    Ok(files
+        .into_iter()
+        .map(|(path, source)| {
+            let source = match args.format {
+                true => format_source(&path, source),
+                false => source,
+            };
+            (path, license.clone() + &source)
+        })
+        .collect())
+}
+
+/// Pretty prints generated code. Falls back to the raw template output if it
+/// cannot be parsed, which usually indicates a bug in the template.
+fn format_source(path: &Path, source: String) -> String {
+    match syn::parse_file(&source) {
+        Ok(file) => prettyplease::unparse(&file),
+        Err(error) => {
+            let line = error.span().start().line;
+            let snippet = source
+                .lines()
+                .enumerate()
+                .skip(line.saturating_sub(3))
+                .take(5)
+                .map(|(index, text)| format!("{:>5} | {text}", index + 1))
+                .join("\n");
+
+            eprintln!(
+                "warning: unable to format {}, writing it as is: {error}\n{snippet}",
+                path.display()
+            );
+
+            source
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::{fs, path::Path};
+
+    fn render_sample(sample: &str, extra_args: &[&str]) -> String {
+        let args = Args::parse_from(["sumi", "--format=false"].iter().chain(extra_args));
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("samples")
+            .join(format!("{sample}.json"));
+        let json = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+
+        render(json, "erc20", &Renames::default(), &args).unwrap().remove(0).1
+    }
+
+    fn render_abi(abi: &str, extra_args: &[&str]) -> String {
+        let args = Args::parse_from(["sumi", "--format=false"].iter().chain(extra_args));
+        let json = json::parse(abi).unwrap();
+
+        render(json, "erc20", &Renames::default(), &args).unwrap().remove(0).1
+    }
+
+    /// Manifest of the scratch crate `--ignored` tests build generated code in,
+    /// with the dependencies generated code expects
+    const SCRATCH_MANIFEST: &str = r#"[package]
+name = "sumi-scratch"
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+path = "lib.rs"
+
+[dependencies]
+ink_env = { version = "3.4", default-features = false }
+ink_lang = { version = "3.4", default-features = false }
+ink_prelude = { version = "3.4", default-features = false }
+ink_primitives = { version = "3.4", default-features = false }
+ink_storage = { version = "3.4", default-features = false }
+scale = { package = "parity-scale-codec", version = "3", default-features = false, features = ["derive"] }
+scale-info = { version = "2", default-features = false, features = ["derive"] }
+ethabi = { version = "18", default-features = false }
+hex-literal = "0.3"
+xvm_environment = { git = "https://github.com/AstarNetwork/ink-xvm-sdk" }
+
+[features]
+default = ["std"]
+std = [
+    "ink_env/std",
+    "ink_lang/std",
+    "ink_prelude/std",
+    "ink_primitives/std",
+    "ink_storage/std",
+    "scale/std",
+    "scale-info/std",
+    "ethabi/std",
+]
+ink-as-dependency = []
+sumi-trace = []
+e2e-tests = []
+"#;
+
+    /// Runs `cargo` with the given arguments on a scratch crate rooted at the
+    /// generated module. `checks` are put into a test module inside the generated
+    /// one, so they reach its private helpers too.
+    fn cargo(name: &str, rendered: &str, checks: &str, cargo_args: &[&str]) {
+        use std::process::Command;
+
+        let scratch = std::env::temp_dir().join("sumi-scratch");
+        let dir = scratch.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("Cargo.toml"), SCRATCH_MANIFEST).unwrap();
+
+        // Generated modules end with the closing brace of the `mod` item
+        let module = rendered.trim_end().strip_suffix('}').unwrap();
+        let lib = format!(
+            "{module}\n    #[cfg(test)]\n    mod sumi_checks {{\n        use super::*;\n{checks}\n    }}\n}}\n"
+        );
+        fs::write(dir.join("lib.rs"), lib).unwrap();
+
+        let output = Command::new("cargo")
+            .args(cargo_args)
+            .current_dir(&dir)
+            .env("CARGO_TARGET_DIR", scratch.join("target"))
+            .output()
+            .expect("cargo is not on PATH");
+
+        let errors = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{name}:\n{errors}");
+    }
+
+    #[test]
+    fn no_std_imports() {
+        let rendered = render_sample("evm-erc20", &[]);
+
+        assert!(rendered.contains("#![cfg_attr(not(feature = \"std\"), no_std)]"));
+        assert!(rendered.contains("use ink_prelude::{string::String, vec::Vec};"));
+        assert!(!rendered.contains(" vec!["));
+
+        // Storage layout is only available to `std` builds
+        let import = "#[cfg(feature = \"std\")]\n    use ink_storage::traits::StorageLayout;";
+        let derive = "#[cfg_attr(feature = \"std\", derive(StorageLayout))]\n    pub struct H160(";
+        assert!(rendered.contains(import));
+        assert!(rendered.contains(derive));
+    }
+
+    /// Builds the bindings of every sample without `std`, run with `--ignored`
+    #[test]
+    #[ignore = "needs cargo and network access"]
+    fn no_std_build() {
+        for name in ["evm-erc20", "xcm", "market"] {
+            let rendered = render_sample(name, &[]);
+            let cargo_args = ["check", "--lib", "--no-default-features"];
+            cargo(name, &rendered, "", &cargo_args);
+        }
+    }
+
+    #[test]
+    fn module_attributes() {
+        let rendered = render_sample("evm-erc20", &["--module-attr", "#![allow(clippy::all)]"]);
+        assert!(rendered.contains("#![allow(missing_docs)]\n"));
+        assert!(rendered.contains("#![allow(clippy::all)]\n"));
+
+        let rendered = render_sample(
+            "evm-erc20",
+            &["--no-default-attrs", "--module-attr", "#![allow(clippy::all)]"],
+        );
+        assert!(!rendered.contains("#![allow(missing_docs)]"));
+        assert!(rendered.contains("no_std)]\n#![allow(clippy::all)]\n"));
+    }
+
+    #[test]
+    fn emit_style() {
+        let rendered = render_sample("evm-erc20", &[]);
+        assert!(rendered.contains("    impl Erc20 {\n"));
+        assert!(!rendered.contains("#[ink::trait_definition]"));
+
+        let rendered = render_sample("evm-erc20", &["--emit-style", "trait-impl"]);
+        assert!(rendered.contains("#[ink::trait_definition]\npub trait Erc20Like {"));
+        assert!(rendered.contains("impl super::Erc20Like for Erc20 {"));
+
+        let rendered = render_sample(
+            "evm-erc20",
+            &["--emit-style", "trait-impl", "--trait-name", "Token"],
+        );
+        assert!(rendered.contains("pub trait Token {"));
+        assert!(rendered.contains("impl super::Token for Erc20 {"));
+    }
+
+    #[test]
+    fn multiple_contracts() {
+        let args = Args::parse_from(["sumi", "--format=false", "--module-name", "erc20"]);
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/combined.json");
+        let json = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+
+        let modules = render_all(json, &args).unwrap();
+        let names: Vec<_> = modules.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["token", "token_2", "router"]);
+        assert_eq!(modules[2].1[0].0, Path::new("router.rs"));
+        assert!(modules[2].1[0].1.contains("mod router {"));
+
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/evm-erc20.json");
+        let json = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+
+        let modules = render_all(json, &args).unwrap();
+        assert_eq!(modules.len(), 1);
+        assert_eq!(modules[0].0, "erc20");
+    }
+
+    #[test]
+    fn skeleton() {
+        let rendered = render_sample("evm-erc20", &["--emit", "skeleton"]);
+
+        assert!(!rendered.contains("xvm_call"));
+        assert!(rendered.contains("#[ink(message, selector = 0xa9059cbb)]\n        pub fn transfer(&mut self, to: H160, amount: U256) -> bool {"));
+        assert!(rendered.contains("#[ink(message, selector = 0x70a08231)]\n        pub fn balance_of(&self, account: H160) -> U256 {"));
+    }
+
+    #[test]
+    fn tracing() {
+        let rendered = render_sample("evm-erc20", &[]);
+        assert!(!rendered.contains("sumi-trace"));
+
+        let rendered = render_sample("evm-erc20", &["--with-tracing"]);
+        assert!(rendered.contains(
+            "#[cfg(feature = \"sumi-trace\")]\n            ink_env::debug_println!(\n                \"sumi: `transfer` to {:?}, selector 0x{}, calldata 0x{}\","
+        ));
+        assert!(rendered.contains("fn trace_hex(bytes: &[u8]) -> String {"));
+    }
+
+    #[test]
+    fn abi_hash() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/evm-erc20.json");
+        let json = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+        let hash: String = Keccak256::digest(json.dump().as_bytes()).encode_hex();
+
+        let rendered = render_sample("evm-erc20", &[]);
+        assert!(rendered.contains(&format!("//! Source ABI hash (keccak256): 0x{hash}\n")));
+        assert!(rendered.contains(&format!("//! Sumi version: {}\n", env!("CARGO_PKG_VERSION"))));
+        assert!(rendered.contains(&format!(
+            "pub const SOURCE_ABI_HASH: [u8; 32] = hex_literal::hex![\"{hash}\"];"
+        )));
+
+        let rendered = render_sample("evm-erc20", &["--no-banner"]);
+        assert!(!rendered.contains("//! Source ABI hash"));
+        assert!(rendered.contains("//! This file was autogenerated by Sumi\n#![cfg_attr"));
+        assert!(rendered.contains(&format!("hex_literal::hex![\"{hash}\"];")));
+    }
+
+    #[test]
+    fn wrapper_style() {
+        let rendered = render_sample("evm-erc20", &[]);
+        assert!(rendered.contains("#[ink::contract(env = xvm_environment::XvmDefaultEnvironment)]\nmod erc20 {"));
+        assert!(rendered.contains("pub fn transfer(&mut self, to: H160, amount: U256) -> bool {"));
+        assert!(rendered.contains("self.env()\n                .extension()"));
+
+        let rendered = render_sample("evm-erc20", &["--wrapper-style", "helper"]);
+        assert!(!rendered.contains("#[ink("));
+        assert!(rendered.contains("pub mod erc20 {"));
+        assert!(rendered.contains("pub fn transfer(&self, env: ink_lang::EnvAccess<'_, xvm_environment::XvmDefaultEnvironment>, to: H160, amount: U256) -> bool {"));
+        assert!(rendered.contains(" env\n                .extension()"));
+    }
+
+    /// Builds both wrapper styles, run with `--ignored`
+    #[test]
+    #[ignore = "needs cargo and network access"]
+    fn wrapper_style_build() {
+        for style in ["message", "helper"] {
+            let rendered = render_sample("evm-erc20", &["--wrapper-style", style]);
+            let name = format!("wrapper-{style}");
+            cargo(&name, &rendered, "", &["check", "--lib"]);
+        }
+    }
+
+    #[test]
+    fn split() {
+        let args = Args::parse_from(["sumi", "--format=false", "--output-dir", "out", "--split"]);
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/xcm.json");
+        let json = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+
+        let files = render(json, "xcm", &Renames::default(), &args).unwrap();
+        let paths: Vec<_> = files.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                Path::new("xcm/types.rs"),
+                Path::new("xcm/calls.rs"),
+                Path::new("xcm/mod.rs")
+            ]
+        );
+
+        let (types, calls) = (&files[0].1, &files[1].1);
+        assert!(types.contains("pub enum AssetsReserveTransferArgs {"));
+        assert!(types.contains("pub trait Tokenize {"));
+        assert!(!calls.contains("pub enum AssetsReserveTransferArgs {"));
+        assert!(calls.contains("    use super::super::types::*;\n"));
+        assert!(calls.contains("pub use self::xcm::{\n    Xcm,\n    XcmRef,\n};"));
+    }
+
+    #[test]
+    fn formatting() {
+        let args = Args::parse_from(["sumi"]);
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/evm-erc20.json");
+        let json = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+        let formatted = render(json, "erc20", &Renames::default(), &args).unwrap().remove(0).1;
+
+        let raw = render_sample("evm-erc20", &[]);
+        let expected = prettyplease::unparse(&syn::parse_file(&raw).unwrap());
+        assert_eq!(formatted, format!("// SPDX-License-Identifier: UNLICENSED\n{expected}"));
+
+        let broken = "fn main() {\n    let x = ;\n}\n".to_owned();
+        assert_eq!(format_source(Path::new("broken.rs"), broken.clone()), broken);
+    }
+
+    #[test]
+    fn function_summary() {
+        let rendered = render_sample("evm-erc20", &["--evm-id", "0x1F"]);
+        assert!(rendered.contains("//! Bindings of `erc20` contract calling EVM `0x1F` through XVM."));
+        assert!(rendered.contains("//! | `transfer` | `transfer(address,uint256)` | `0xa9059cbb` |\n"));
+
+        let rendered = render_sample("xcm", &[]);
+        assert!(rendered.contains("//! | `assets_reserve_transfer(AssetsReserveTransferArgs::V1)` |"));
+
+        let rendered = render_sample("evm-erc20", &["--no-banner"]);
+        assert!(!rendered.contains("//! | `transfer`"));
+    }
+
+    #[test]
+    fn mock_requires_std() {
+        let rendered = render_sample("evm-erc20", &["--with-mock"]);
+
+        assert!(rendered.contains("#[cfg(all(test, feature = \"std\"))]\n    pub mod mock {"));
+    }
+
+    #[test]
+    fn rename() {
+        let args = Args::parse_from(["sumi", "--format=false"]);
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/xcm.json");
+        let json = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+
+        let renames = Renames::parse(
+            r#"
+            "remote_transact(uint256,bool,address,uint256,bytes,uint64)" = "transact"
+            "stale(uint256)" = "unused"
+            "#,
+            true,
+        )
+        .unwrap();
+
+        let rendered = render(json.clone(), "xcm", &renames, &args).unwrap().remove(0).1;
+        assert!(rendered.contains("pub fn transact(&mut self, "));
+        assert!(rendered.contains("const TRANSACT_SELECTOR: [u8; 4]"));
+        assert!(!rendered.contains("fn remote_transact("));
+        assert!(renames
+            .used
+            .borrow()
+            .contains("remote_transact(uint256,bool,address,uint256,bytes,uint64)"));
+        assert!(!renames.used.borrow().contains("stale(uint256)"));
+
+        let invalid = Renames::parse(r#"{ "transfer(address,uint256)": "fn" }"#, false);
+        assert!(matches!(invalid, Err(Error::Rename(_))));
+
+        let colliding = Renames::parse(
+            r#"{ "remote_transact(uint256,bool,address,uint256,bytes,uint64)": "assets_withdraw" }"#,
+            false,
+        )
+        .unwrap();
+        assert!(matches!(
+            render(json, "xcm", &colliding, &args),
+            Err(Error::Rename(_))
+        ));
+    }
+
+    #[test]
+    fn unsupported_stubs() {
+        let rendered = render_sample("evm-erc20", &[]);
+        assert!(rendered.contains("/// Not translated: `view` functions cannot return data through XVM"));
+        assert!(rendered.contains(
+            "pub fn balance_of(&self, account: H160) -> ! {\n            todo!(\"unsupported: `view` functions cannot return data through XVM\")"
+        ));
+
+        let abi = r#"[{
+            "type": "function",
+            "name": "mint",
+            "inputs": [{ "name": "order", "type": "tuple", "components": [] }],
+            "outputs": [],
+            "stateMutability": "nonpayable"
+        }]"#;
+        let rendered = render_abi(abi, &[]);
+        assert!(rendered.contains("/// Not translated: input `order` has unsupported type `tuple`"));
+        assert!(rendered.contains("pub fn mint(&self) -> ! {"));
+
+        let args = Args::parse_from(["sumi", "--format=false", "--strict"]);
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/evm-erc20.json");
+        let json = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+        assert!(matches!(
+            render(json, "erc20", &Renames::default(), &args),
+            Err(Error::Unsupported { .. })
+        ));
+    }
+
+    #[test]
+    fn struct_conversions() {
+        let rendered = render_sample("market", &["--address-type", "account-id"]);
+
+        assert!(rendered.contains("`fill((address,uint256,(uint8,bytes32)[]))`"));
+        assert!(rendered.contains("pub fn fill(&mut self, order: Order) -> bool {"));
+        assert!(rendered.contains("pub struct Fee {\n        pub kind: u8,\n        pub tag: FixedBytes<32>,\n"));
+        assert!(rendered.contains("impl From<(u8, FixedBytes<32>, )> for Fee {"));
+        assert!(rendered.contains(
+            "impl From<(AccountId, U256, Vec<(u8, FixedBytes<32>,)>, )> for Order {"
+        ));
+        assert!(rendered.contains("fees: tuple.2.into_iter().map(|item0| item0.into()).collect(),"));
+        assert!(rendered.contains(
+            "impl From<Order> for (AccountId, U256, Vec<(u8, FixedBytes<32>,)>, ) {"
+        ));
+        assert!(rendered.contains("value.fees.into_iter().map(|item0| item0.into()).collect(),"));
+        assert!(rendered.contains("to_h160(&self.maker).tokenize(),"));
+    }
+
+    #[test]
+    fn type_prefix() {
+        let rendered = render_sample("evm-erc20", &["--type-prefix", "evm"]);
+        assert!(rendered.contains("pub struct EvmH160([u8; 20]);"));
+        assert!(rendered.contains("impl Tokenize for EvmU256 {"));
+        assert!(rendered.contains("pub const EVM_DEFAULT_EVM_ID: u8 = 0x0F;"));
+        assert!(rendered.contains("const EVM_TRANSFER_SELECTOR: [u8; 4]"));
+        assert!(rendered.contains("pub fn transfer(&mut self, to: EvmH160, amount: EvmU256) -> bool {"));
+        assert!(!rendered.contains(" H160"));
+
+        let rendered = render_sample("xcm", &["--type-prefix", "evm"]);
+        assert!(rendered.contains("pub enum EvmAssetsWithdrawArgs {"));
+
+        let args = Args::parse_from(["sumi", "--type-prefix", "my-prefix"]);
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/evm-erc20.json");
+        let json = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+        assert!(matches!(
+            render(json, "erc20", &Renames::default(), &args),
+            Err(Error::InvalidOptions(_))
+        ));
+    }
+
+    #[test]
+    fn manual_codec() {
+        let rendered = render_sample("market", &[]);
+        assert!(rendered.contains("#[derive(Debug, Encode, Decode, TypeInfo)]\n    pub struct Order {"));
+        assert!(!rendered.contains("impl Encode for Order {"));
+
+        let rendered = render_sample("market", &["--codec", "manual"]);
+        assert!(rendered.contains("#[derive(Debug, TypeInfo)]\n    pub struct Order {"));
+        assert!(rendered.contains(
+            "0 + self.maker.size_hint() + self.amount.size_hint() + self.fees.size_hint()"
+        ));
+        assert!(rendered.contains(
+            "self.maker.encode_to(dest);\n            self.amount.encode_to(dest);\n            self.fees.encode_to(dest);"
+        ));
+        assert!(rendered.contains(
+            "Ok(Self {\n                maker: Decode::decode(input)?,\n                amount: Decode::decode(input)?,\n                fees: Decode::decode(input)?,"
+        ));
+    }
+
+    #[test]
+    fn error_style() {
+        let rendered = render_sample("xcm", &[]);
+        assert!(!rendered.contains("XvmError"));
+        assert!(rendered.contains("args: AssetsWithdrawArgs) -> bool {"));
+
+        let rendered = render_sample("xcm", &["--error-style", "result", "--with-mock"]);
+        assert!(rendered.contains("pub enum XvmError {"));
+        assert!(rendered.contains("    XvmError,\n    XvmCallError,\n    RevertReason,\n};"));
+        assert!(rendered.contains("args: AssetsWithdrawArgs) -> Result<(), XvmError> {"));
+        assert!(rendered.contains(") -> Result<(), XvmError> {\n            let mut encoded_input = REMOTE_TRANSACT_SELECTOR"));
+        assert!(rendered.contains("                .map(|_| ())\n                .map_err(|_| XvmError::CallFailed(XvmCallError::Unreported))\n        }"));
+        assert!(!rendered.contains(".is_ok()"));
+        assert!(rendered.contains("false => Err(XvmError::CallFailed(XvmCallError::ExecutionFailed)),"));
+    }
+
+    #[test]
+    fn e2e() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/evm-erc20.json");
+        let json = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+
+        let args = Args::parse_from(["sumi", "--format=false", "--with-e2e"]);
+        assert!(matches!(
+            render(json.clone(), "erc20", &Renames::default(), &args),
+            Err(Error::InvalidOptions(_))
+        ));
+
+        let args = Args::parse_from(["sumi", "--format=false", "--output-dir", "out", "--with-e2e"]);
+        let files = render(json, "erc20", &Renames::default(), &args).unwrap();
+        assert_eq!(files[1].0, Path::new("erc20/e2e.rs"));
+        assert!(files[0].1.contains("#[cfg(all(test, feature = \"e2e-tests\"))]\nmod e2e;"));
+
+        let e2e = &files[1].1;
+        assert!(e2e.contains("async fn approve_works(mut client: ink_e2e::Client<C, E>)"));
+        assert!(e2e.contains(
+            ".call(|contract| contract.approve(H160::from([0u8; 20]), U256::from([0u8; 32])));"
+        ));
+
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/market.json");
+        let json = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+        let args = Args::parse_from([
+            "sumi",
+            "--format=false",
+            "--output-dir",
+            "out",
+            "--split",
+            "--with-e2e",
+        ]);
+        let files = render(json, "market", &Renames::default(), &args).unwrap();
+        assert!(files[2].1.ends_with("#[cfg(all(test, feature = \"e2e-tests\"))]\nmod e2e;\n"));
+        assert!(files[3].1.contains("contract.fill(todo!(\"provide `order`\"))"));
+    }
+
+    #[test]
+    fn selector_enum() {
+        let rendered = render_sample("xcm", &[]);
+        assert!(!rendered.contains("enum Selector"));
+
+        let rendered = render_sample("xcm", &["--selector-enum"]);
+        assert!(rendered.contains("    #[repr(u32)]\n    pub enum Selector {"));
+        assert!(rendered.contains("AssetsWithdrawV0 = 0x"));
+        assert!(rendered.contains("AssetsWithdrawV1 = 0x"));
+        assert!(rendered.contains("RemoteTransact = 0x"));
+        assert!(rendered.contains("impl TryFrom<[u8; 4]> for Selector {"));
+        assert!(rendered.contains("    Selector,\n};"));
+
+        let json = json::parse(
+            r#"[
+                { "type": "function", "name": "collate_propagate_storage", "inputs": [{ "name": "x", "type": "bytes16" }], "outputs": [], "stateMutability": "nonpayable" },
+                { "type": "function", "name": "burn", "inputs": [{ "name": "amount", "type": "uint256" }], "outputs": [], "stateMutability": "nonpayable" }
+            ]"#,
+        )
+        .unwrap();
+        let args = Args::parse_from(["sumi", "--selector-enum"]);
+        assert!(matches!(
+            render(json, "token", &Renames::default(), &args),
+            Err(Error::SelectorCollision { .. })
+        ));
+    }
+
+    #[test]
+    fn types_only() {
+        let rendered = render_sample("evm-erc20", &["--emit", "types"]);
+        assert!(rendered.contains(
+            "/// Rust counterpart of `event Transfer`\n    #[derive(Debug, Encode, Decode, TypeInfo)]\n    pub struct Transfer {\n        pub from: H160,\n        pub to: H160,\n        pub value: U256,\n"
+        ));
+        assert!(rendered.contains("pub struct Approval {"));
+        assert!(!rendered.contains("xvm_call"));
+        assert!(!rendered.contains("Tokenize"));
+
+        let rendered = render_sample("market", &["--emit", "types", "--type-prefix", "evm"]);
+        assert!(rendered.contains("pub struct EvmOrder {"));
+        assert!(rendered.contains("impl From<EvmFee> for (u8, EvmFixedBytes<32>, ) {"));
+        assert!(!rendered.contains("Tokenize"));
+
+        let rendered = render_sample("market", &[]);
+        assert!(rendered.contains("impl Tokenize for Order {"));
+        assert!(!rendered.contains("event"));
+    }
+
+    #[test]
+    fn xvm_version() {
+        let rendered = render_sample("evm-erc20", &[]);
+        assert!(rendered.contains("const XVM_CONTEXT: [u8; 5] = [DEFAULT_EVM_ID, 0x00, 0x00, 0x00, 0x00];"));
+        assert!(rendered.contains("super::XVM_CONTEXT.to_vec(),"));
+
+        let rendered = render_sample("evm-erc20", &["--xvm-version", "v1", "-e", "0x1F"]);
+        assert!(rendered.contains("pub const DEFAULT_EVM_ID: u8 = 0x1F;"));
+        assert!(rendered.contains("const XVM_CONTEXT: [u8; 2] = [DEFAULT_EVM_ID, 0x00];"));
+
+        let rendered = render_sample("evm-erc20", &["--xvm-version", "v3"]);
+        assert!(!rendered.contains("XVM_CONTEXT"));
+        assert!(rendered.contains(".xvm_call_v3("));
+        assert!(rendered.contains("vm_id: super::DEFAULT_EVM_ID,"));
+
+        let rendered = render_sample(
+            "evm-erc20",
+            &["--xvm-version", "v3", "--error-style", "result"],
+        );
+        assert!(rendered.contains("XvmError::CallFailed(XvmCallError::Unreported)"));
+
+        let args = Args::parse_from(["sumi", "-e", "0x100"]);
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/evm-erc20.json");
+        let json = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+        assert!(matches!(
+            render(json, "erc20", &Renames::default(), &args),
+            Err(Error::InvalidOptions(_))
+        ));
+    }
+
+    #[test]
+    fn chain_extension_id() {
+        let rendered = render_sample("evm-erc20", &[]);
+        assert!(rendered.contains("pub const XVM_EXTENSION_ID: u32 = 0x00010001;"));
+
+        let rendered = render_sample("evm-erc20", &["--chain-extension-id", "42"]);
+        assert!(rendered.contains("pub const XVM_EXTENSION_ID: u32 = 0x0000002a;"));
+
+        assert!(Args::try_parse_from(["sumi", "--chain-extension-id", "0x1ffffffff"]).is_err());
+        assert!(Args::try_parse_from(["sumi", "--xvm-address", "0x5005"]).is_err());
+    }
+
+    #[test]
+    fn chain_extension_target() {
+        let rendered = render_sample("evm-erc20", &[]);
+        assert!(rendered.contains("#[ink::contract(env = xvm_environment::XvmDefaultEnvironment)]"));
+        assert!(!rendered.contains("chain_extension"));
+
+        let rendered = render_sample("evm-erc20", &["--target", "chain-extension"]);
+        assert!(rendered.contains("#[ink::contract(env = XvmEnvironment)]"));
+        assert!(rendered.contains("#[ink_lang::chain_extension]"));
+        assert!(rendered.contains("#[ink(extension = 0x00010001)]"));
+        assert!(rendered.contains("type ChainExtension = XvmExtension;"));
+        assert!(rendered.contains("super::XVM_CONTEXT.to_vec(),"));
+        assert!(!rendered.contains("xvm_environment"));
+
+        let args = Args::parse_from(["sumi", "--target", "chain-extension", "--xvm-version", "v3"]);
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/evm-erc20.json");
+        let json = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+        assert!(matches!(
+            render(json, "erc20", &Renames::default(), &args),
+            Err(Error::InvalidOptions(_))
+        ));
+    }
+
+    /// Builds the bindings of both call targets, run with `--ignored`
+    #[test]
+    #[ignore = "needs cargo and network access"]
+    fn chain_extension_target_build() {
+        for target in ["precompile", "chain-extension"] {
+            let rendered = render_sample("evm-erc20", &["--target", target]);
+            let name = format!("target-{target}");
+            cargo(&name, &rendered, "", &["check", "--lib"]);
+        }
+    }
+
+    #[test]
+    fn weight_limit() {
+        let rendered = render_sample("evm-erc20", &["--weight-limit", "1000000000,65536"]);
+        assert!(rendered.contains(
+            "const XVM_CONTEXT: [u8; 11] = [DEFAULT_EVM_ID, 0x02, 0x28, 0x6b, 0xee, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00];"
+        ));
+
+        let rendered = render_sample("evm-erc20", &["--weight-limit", "100,0", "--xvm-version", "v1"]);
+        assert!(rendered.contains("const XVM_CONTEXT: [u8; 3] = [DEFAULT_EVM_ID, 0x91, 0x01];"));
+
+        let rendered = render_sample("evm-erc20", &["--weight-args"]);
+        assert!(rendered.contains(
+            "pub fn transfer(&mut self, to: H160, amount: U256, weight_limit: WeightLimit) -> bool {"
+        ));
+        assert!(rendered.contains("weight_limit.xvm_context(\n                        super::DEFAULT_EVM_ID,\n                        None,"));
+        assert!(rendered.contains("pub struct WeightLimit {"));
+        assert!(!rendered.contains("XVM_CONTEXT.to_vec()"));
+
+        assert!(Args::try_parse_from(["sumi", "--weight-limit", "100"]).is_err());
+    }
+
+    #[test]
+    fn storage_deposit_limit() {
+        let rendered = render_sample("evm-erc20", &[]);
+        assert!(!rendered.contains("storage_deposit_limit"));
+
+        let rendered = render_sample("evm-erc20", &["--storage-deposit-args", "--weight-limit", "5,7"]);
+        assert!(rendered.contains(
+            "pub fn transfer(&mut self, to: H160, amount: U256, storage_deposit_limit: Option<u128>) -> bool {"
+        ));
+        assert!(rendered.contains(
+            "WeightLimit { ref_time: 5, proof_size: 7 }.xvm_context(\n                        super::DEFAULT_EVM_ID,\n                        storage_deposit_limit,"
+        ));
+        assert!(rendered.contains("storage_deposit_limit.encode_to(&mut context);"));
+
+        let rendered = render_sample("evm-erc20", &["--storage-deposit-args", "--xvm-version", "v3"]);
+        assert!(rendered.contains("storage_deposit_limit: storage_deposit_limit,"));
+
+        let args = Args::parse_from(["sumi", "--storage-deposit-args", "--xvm-version", "v1"]);
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/evm-erc20.json");
+        let json = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+        assert!(matches!(
+            render(json, "erc20", &Renames::default(), &args),
+            Err(Error::InvalidOptions(_))
+        ));
+    }
+
+    #[test]
+    fn transferred_value() {
+        let abi = r#"[
+            { "type": "function", "name": "deposit", "inputs": [{ "name": "amount", "type": "uint256" }], "outputs": [], "stateMutability": "payable" },
+            { "type": "function", "name": "burn", "inputs": [{ "name": "amount", "type": "uint256" }], "outputs": [], "stateMutability": "nonpayable" }
+        ]"#;
+        let render_deposit = |extra_args: &[&str]| render_abi(abi, extra_args);
+
+        let rendered = render_deposit(&[]);
+        assert!(rendered.contains("pub fn deposit(&mut self, amount: U256, transferred_value: u128) -> bool {"));
+        assert!(rendered.contains("pub fn burn(&mut self, amount: U256) -> bool {"));
+        assert!(rendered.contains(
+            "[\n                    super::XVM_CONTEXT.to_vec(),\n                        transferred_value.encode(),\n                    ]\n                    .concat(),"
+        ));
+
+        let rendered = render_deposit(&["--weight-args"]);
+        assert!(rendered.contains("amount: U256, transferred_value: u128, weight_limit: WeightLimit) -> bool {"));
+        assert!(rendered.contains("                    ),\n                        transferred_value.encode(),"));
+
+        let rendered = render_deposit(&["--xvm-version", "v3"]);
+        assert!(rendered.contains("value: transferred_value,"));
+        assert!(rendered.contains("value: 0,"));
+
+        let rendered = render_deposit(&["--xvm-version", "v1"]);
+        assert!(rendered.contains("pub fn deposit(&mut self, amount: U256) -> bool {"));
+        assert!(!rendered.contains("transferred_value"));
+    }
+
+    const DYNAMIC_ARGUMENTS_ABI: &str = r#"[{
+        "type": "function",
+        "name": "foo",
+        "inputs": [
+            { "name": "amount", "type": "uint256" },
+            { "name": "label", "type": "string" },
+            { "name": "holders", "type": "address[]" },
+            { "name": "data", "type": "bytes" },
+            { "name": "blobs", "type": "bytes[]" }
+        ],
+        "outputs": [],
+        "stateMutability": "nonpayable"
+    }]"#;
+
+    #[test]
+    fn dynamic_arguments() {
+        let rendered = render_abi(DYNAMIC_ARGUMENTS_ABI, &[]);
+        assert!(rendered.contains("pub fn foo(&mut self, amount: U256, label: String, holders: Vec<H160>, data: Vec<u8>, blobs: Vec<Vec<u8>>) -> bool {"));
+        assert!(rendered.contains("holders.tokenize(),\n"));
+        assert!(rendered.contains("Token::Bytes(data),\n"));
+        assert!(rendered.contains("Token::Array(blobs.into_iter().map(|item0| Token::Bytes(item0)).collect()),\n"));
+    }
+
+    /// Compares call data encoded by the generated mock with `ethabi`, run with `--ignored`
+    #[test]
+    #[ignore = "needs cargo and network access"]
+    fn dynamic_arguments_encoding() {
+        let checks = r#"
+        #[test]
+        fn dynamic_arguments() {
+            let mut mock = mock::MockErc20::new();
+            mock.foo(
+                U256::from(42u128),
+                String::from("label"),
+                vec![H160([1; 20]), H160([2; 20])],
+                vec![0xde, 0xad, 0xbe, 0xef],
+                vec![vec![1], vec![2, 3]],
+            );
+
+            let tokens = [
+                Token::Uint(42u64.into()),
+                Token::String("label".into()),
+                Token::Array(vec![Token::Address([1; 20].into()), Token::Address([2; 20].into())]),
+                Token::Bytes(vec![0xde, 0xad, 0xbe, 0xef]),
+                Token::Array(vec![Token::Bytes(vec![1]), Token::Bytes(vec![2, 3])]),
+            ];
+            let input = &mock.calls()[0].input;
+            assert_eq!(input[..4], FOO_SELECTOR);
+            assert_eq!(input[4..], ethabi::encode(&tokens));
+        }"#;
+
+        let rendered = render_abi(DYNAMIC_ARGUMENTS_ABI, &["--with-mock"]);
+        cargo("dynamic-arguments", &rendered, checks, &["test", "--lib"]);
+    }
+
+    const TUPLE_ENCODING_ABI: &str = r#"[{
+        "type": "function",
+        "name": "settle",
+        "inputs": [
+            { "name": "quote", "type": "tuple", "internalType": "struct Desk.Quote", "components": [
+                { "name": "memo", "type": "string" },
+                { "name": "amount", "type": "uint256" }
+            ]},
+            { "name": "legs", "type": "tuple[2]", "internalType": "struct Desk.Leg[2]", "components": [
+                { "name": "to", "type": "address" },
+                { "name": "amount", "type": "uint256" }
+            ]},
+            { "name": "outer", "type": "tuple", "internalType": "struct Desk.Outer", "components": [
+                { "name": "inner", "type": "tuple", "internalType": "struct Desk.Inner", "components": [
+                    { "name": "kind", "type": "uint8" },
+                    { "name": "tag", "type": "bytes32" }
+                ]},
+                { "name": "total", "type": "uint256" }
+            ]}
+        ],
+        "outputs": [],
+        "stateMutability": "nonpayable"
+    }]"#;
+
+    #[test]
+    fn tuple_encoding() {
+        let rendered = render_abi(TUPLE_ENCODING_ABI, &[]);
+
+        let dynamic = "/// ABI encoded in the tail behind an offset, as some members are dynamic\n    impl Tokenize for";
+        let fixed = "/// ABI encoded inline, as all members are static\n    impl Tokenize for";
+        assert!(rendered.contains(&format!("{dynamic} Quote {{")));
+        assert!(rendered.contains(&format!("{fixed} Leg {{")));
+        assert!(rendered.contains(&format!("{fixed} Inner {{")));
+        assert!(rendered.contains(&format!("{fixed} Outer {{")));
+        assert!(rendered.contains("pub fn settle(&mut self, quote: Quote, legs: [Leg; 2], outer: Outer) -> bool {"));
+    }
+
+    /// Compares tuples encoded by the generated mock with `ethabi`, run with `--ignored`
+    #[test]
+    #[ignore = "needs cargo and network access"]
+    fn tuple_encoding_layout() {
+        let checks = r#"
+        #[test]
+        fn tuple_encoding() {
+            let leg = || Leg { to: H160([1; 20]), amount: U256::from(1u128) };
+            let inner = Inner { kind: 2, tag: FixedBytes([3; 32]) };
+
+            let mut mock = mock::MockErc20::new();
+            mock.settle(
+                Quote { memo: String::from("memo"), amount: U256::from(7u128) },
+                [leg(), leg()],
+                Outer { inner, total: U256::from(4u128) },
+            );
+
+            let quote = Token::Tuple(vec![Token::String("memo".into()), Token::Uint(7u64.into())]);
+            let leg = Token::Tuple(vec![Token::Address([1; 20].into()), Token::Uint(1u64.into())]);
+            let legs = Token::FixedArray(vec![leg.clone(), leg]);
+            let inner = Token::Tuple(vec![Token::Uint(2u64.into()), Token::FixedBytes(vec![3; 32])]);
+            let outer = Token::Tuple(vec![inner, Token::Uint(4u64.into())]);
+
+            let input = &mock.calls()[0].input[4..];
+            assert_eq!(input, ethabi::encode(&[quote, legs, outer]));
+
+            // Static tuples are inline, the dynamic one is referenced by its offset of 8 words
+            assert_eq!(input[30..32], [0x01, 0x00]);
+            assert_eq!(input[44..64], [1; 20]);
+        }"#;
+
+        let rendered = render_abi(TUPLE_ENCODING_ABI, &["--with-mock"]);
+        cargo("tuple-encoding", &rendered, checks, &["test", "--lib"]);
+    }
+
+    #[test]
+    fn address_mapping() {
+        let rendered = render_sample("evm-erc20", &["--address-type", "account-id"]);
+        assert!(rendered.contains("fn to_h160(account: &AccountId) -> H160 {"));
+        assert!(rendered.contains("address.copy_from_slice(&bytes[..20]);"));
+        assert!(rendered.contains("to_h160(&to).tokenize(),"));
+
+        let rendered = render_sample(
+            "evm-erc20",
+            &["--address-type", "account-id", "--address-mapping", "keccak"],
+        );
+        assert!(rendered.contains(
+            "ink_env::hash_bytes::<ink_env::hash::Keccak256>(account.as_ref(), &mut hash);"
+        ));
+        assert!(rendered.contains("address.copy_from_slice(&hash[12..]);"));
+        assert!(rendered.contains("pub fn transfer(&mut self, to: AccountId, amount: U256) -> bool {"));
+    }
+
+    /// Maps known accounts with the generated helpers, run with `--ignored`
+    #[test]
+    #[ignore = "needs cargo and network access"]
+    fn address_mapping_vectors() {
+        let truncate = r#"
+        #[test]
+        fn truncate() {
+            let mut account = [0u8; 32];
+            account.iter_mut().zip(1..).for_each(|(byte, value)| *byte = value);
+            let address = to_h160(&AccountId::from(account));
+            assert_eq!(address.0, hex!["0102030405060708090a0b0c0d0e0f1011121314"]);
+        }"#;
+
+        let keccak = r#"
+        #[test]
+        fn keccak() {
+            let address = to_h160(&AccountId::from([0u8; 32]));
+            assert_eq!(address.0, hex!["88386fc84ba6bc95484008f6362f93160ef3e563"]);
+
+            let mut account = [0u8; 32];
+            account[31] = 1;
+            let address = to_h160(&AccountId::from(account));
+            assert_eq!(address.0, hex!["717e6a320cf44b4afac2b0732d9fcbe2b7fa0cf6"]);
+        }"#;
+
+        for (mapping, checks) in [("truncate", truncate), ("keccak", keccak)] {
+            let options = ["--address-type", "account-id", "--address-mapping", mapping];
+            let rendered = render_sample("evm-erc20", &options);
+            let name = format!("address-{mapping}");
+            cargo(&name, &rendered, checks, &["test", "--lib"]);
+        }
+    }
+
+    #[test]
+    fn revert_reasons() {
+        let rendered = render_sample("evm-erc20", &["--error-style", "result", "--xvm-version", "v3"]);
+        assert!(rendered.contains("pub enum RevertReason {"));
+        assert!(rendered.contains("fn decode_revert_reason(bytes: &[u8]) -> RevertReason {"));
+        assert!(rendered.contains("[0x08, 0xc3, 0x79, 0xa0] => ethabi::decode(&[ethabi::ParamType::String], data),"));
+        assert!(rendered.contains("[0x4e, 0x48, 0x7b, 0x71] => ethabi::decode(&[ethabi::ParamType::Uint(256)], data),"));
+        assert!(rendered.contains("XvmError::Reverted(decode_revert_reason(&data))"));
+
+        // Selectors the generated helper matches
+        assert_eq!(selector_hash("Error(string)"), "08c379a0");
+        assert_eq!(selector_hash("Panic(uint256)"), "4e487b71");
+    }
+
+    /// Decodes revert data of every shape with the generated helper, run with `--ignored`
+    #[test]
+    #[ignore = "needs cargo and network access"]
+    fn revert_reason_decoding() {
+        let checks = r#"
+        #[test]
+        fn revert_reasons() {
+            let revert = |selector: [u8; 4], tokens: &[Token]| {
+                [&selector[..], &ethabi::encode(tokens)[..]].concat()
+            };
+
+            let message = revert(hex!["08c379a0"], &[Token::String("insufficient balance".into())]);
+            let reason = RevertReason::Message(String::from("insufficient balance"));
+            assert_eq!(decode_revert_reason(&message), reason);
+
+            let panic = revert(hex!["4e487b71"], &[Token::Uint(0x11u64.into())]);
+            let reason = RevertReason::Panic(U256::from(0x11u128));
+            assert_eq!(decode_revert_reason(&panic), reason);
+
+            let args = [Token::Uint(1u64.into()), Token::Uint(2u64.into())];
+            let custom = revert(hex!["CUSTOM"], &args);
+            let reason = RevertReason::Custom { selector: hex!["CUSTOM"], data: ethabi::encode(&args) };
+            assert_eq!(decode_revert_reason(&custom), reason);
+
+            assert_eq!(decode_revert_reason(&[]), RevertReason::Empty);
+            let reason = RevertReason::Custom { selector: [0; 4], data: vec![1, 2] };
+            assert_eq!(decode_revert_reason(&[1, 2]), reason);
+        }"#;
+
+        let custom = selector_hash("InsufficientBalance(uint256,uint256)");
+        let checks = checks.replace("CUSTOM", &custom);
+        let options = ["--error-style", "result", "--xvm-version", "v3"];
+        let rendered = render_sample("evm-erc20", &options);
+        cargo("revert-reasons", &rendered, &checks, &["test", "--lib"]);
+    }
+
+    #[test]
+    fn return_data() {
+        let rendered = render_sample("evm-erc20", &["--xvm-version", "v3"]);
+        assert!(rendered.contains("pub fn balance_of(&mut self, account: H160) -> Option<U256> {"));
+        assert!(rendered.contains("-> Option<bool> {"));
+        assert!(rendered.contains("decode_output(&output, &[ethabi::ParamType::Uint(256)])"));
+        assert!(rendered.contains("fn decode_output<T: Detokenize>("));
+
+        let rendered = render_sample("evm-erc20", &["--xvm-version", "v3", "--error-style", "result"]);
+        assert!(rendered.contains("-> Result<U256, XvmError> {"));
+        assert!(rendered.contains(".ok_or(XvmError::DecodeFailed)"));
+
+        // Return data of earlier interfaces is not available
+        let rendered = render_sample("evm-erc20", &[]);
+        assert!(!rendered.contains("decode_output"));
+        assert!(!rendered.contains("-> Option<U256>"));
+
+        // Return data is converted by the generated `Detokenize` implementations
+        let rendered = render_abi(RETURN_DATA_ABI, &["--xvm-version", "v3"]);
+        assert!(rendered.contains("pub fn pair(&mut self, ) -> Option<(H160, i8)> {"));
+        assert!(rendered.contains("impl<T: Detokenize> Detokenize for Vec<T> {"));
+        assert!(rendered.contains("Token::Bytes(bytes) => bytes\n"));
+        assert!(rendered.contains("detokenize_tuple!(A, B);"));
+        assert!(rendered.contains("(value.low_u128() as i128).try_into().ok(),"));
+        assert!(rendered.contains("impl Detokenize for String {"));
+    }
+
+    const RETURN_DATA_ABI: &str = r#"[
+        { "type": "function", "name": "total", "inputs": [], "outputs": [{ "name": "", "type": "uint256" }], "stateMutability": "view" },
+        { "type": "function", "name": "approve", "inputs": [], "outputs": [{ "name": "", "type": "bool" }], "stateMutability": "nonpayable" },
+        { "type": "function", "name": "symbol", "inputs": [], "outputs": [{ "name": "", "type": "string" }], "stateMutability": "view" },
+        { "type": "function", "name": "blob", "inputs": [], "outputs": [{ "name": "", "type": "bytes" }], "stateMutability": "view" },
+        { "type": "function", "name": "pair", "inputs": [], "outputs": [{ "name": "owner", "type": "address" }, { "name": "delta", "type": "int8" }], "stateMutability": "view" },
+        { "type": "function", "name": "balanceOf", "inputs": [{ "name": "owner", "type": "address" }], "outputs": [{ "name": "", "type": "uint256" }], "stateMutability": "view" },
+        { "type": "function", "name": "balanceOf", "inputs": [{ "name": "owner", "type": "address" }, { "name": "id", "type": "uint256" }], "outputs": [{ "name": "", "type": "uint256" }], "stateMutability": "view" }
+    ]"#;
+
+    #[test]
+    fn overloaded_return_data() {
+        let rendered = render_abi(RETURN_DATA_ABI, &["--xvm-version", "v3", "--with-mock"]);
+        assert!(rendered.contains("balance_of(&mut self, args: BalanceOfArgs) -> Option<U256> {"));
+        assert!(rendered.contains("let output = self.env()"));
+        assert!(rendered.contains("decode_output(&output, &[ethabi::ParamType::Uint(256)])"));
+        assert!(rendered.contains("self.output(\"balanceOf\"), &[ethabi::ParamType::Uint(256)])"));
+
+        let options = ["--xvm-version", "v3", "--error-style", "result"];
+        let rendered = render_abi(RETURN_DATA_ABI, &options);
+        assert!(rendered.contains("args: BalanceOfArgs) -> Result<U256, XvmError> {"));
+
+        // One function cannot return the types of both overloads
+        let abi = RETURN_DATA_ABI.replace(
+            r#"{ "name": "id", "type": "uint256" }], "outputs": [{ "name": "", "type": "uint256" }]"#,
+            r#"{ "name": "id", "type": "uint256" }], "outputs": [{ "name": "", "type": "bool" }]"#,
+        );
+        let args = Args::parse_from(["sumi", "--xvm-version", "v3"]);
+        let json = json::parse(&abi).unwrap();
+        assert!(matches!(
+            render(json, "erc20", &Renames::default(), &args),
+            Err(Error::Unsupported { .. })
+        ));
+    }
+
+    /// Decodes return data with the generated mock, run with `--ignored`
+    #[test]
+    #[ignore = "needs cargo and network access"]
+    fn return_data_decoding() {
+        let checks = r#"
+        #[test]
+        fn return_data() {
+            let mut mock = mock::MockErc20::new();
+
+            mock.respond_with("total", ethabi::encode(&[Token::Uint(1000u64.into())]));
+            assert_eq!(mock.total(), Some(U256::from(1000u128)));
+
+            // Tokens returning nothing succeed
+            assert_eq!(mock.approve(), Some(true));
+
+            mock.respond_with("symbol", ethabi::encode(&[Token::String("ASTR".into())]));
+            assert_eq!(mock.symbol(), Some(String::from("ASTR")));
+
+            mock.respond_with("blob", ethabi::encode(&[Token::Bytes(vec![1, 2, 3])]));
+            assert_eq!(mock.blob(), Some(vec![1, 2, 3]));
+
+            // Sign extended `int8` of -1
+            let max = ethabi::ethereum_types::U256::MAX;
+            let tokens = [Token::Address([7; 20].into()), Token::Int(max)];
+            mock.respond_with("pair", ethabi::encode(&tokens));
+            let (owner, delta) = mock.pair().unwrap();
+            assert_eq!((owner.0, delta), ([7; 20], -1));
+
+            mock.respond_with("balanceOf", ethabi::encode(&[Token::Uint(5u64.into())]));
+            let args = BalanceOfArgs::V1 { owner: H160([1; 20]), id: U256::from(2u128) };
+            assert_eq!(mock.balance_of(args), Some(U256::from(5u128)));
+
+            // Data of another shape fails to decode
+            mock.respond_with("total", vec![1, 2, 3]);
+            assert_eq!(mock.total(), None);
+        }"#;
+
+        let options = ["--xvm-version", "v3", "--with-mock"];
+        let rendered = render_abi(RETURN_DATA_ABI, &options);
+        cargo("return-data", &rendered, checks, &["test", "--lib"]);
+    }
+
+    #[test]
+    fn multicall() {
+        let abi = |name: &str| {
+            format!(
+                r#"[{{
+                    "type": "function",
+                    "name": "{name}",
+                    "inputs": [{{ "name": "data", "type": "bytes[]" }}],
+                    "outputs": [],
+                    "stateMutability": "nonpayable"
+                }}, {{
+                    "type": "function",
+                    "name": "transfer",
+                    "inputs": [
+                        {{ "name": "to", "type": "address" }},
+                        {{ "name": "amount", "type": "uint256" }}
+                    ],
+                    "outputs": [{{ "name": "", "type": "bool" }}],
+                    "stateMutability": "nonpayable"
+                }}]"#
+            )
+        };
+
+        let rendered = render_abi(&abi("multicall"), &[]);
+
+        assert!(rendered.contains("    Multicall,\n};"));
+        assert!(rendered.contains("pub fn encode_transfer(to: H160, amount: U256) -> Vec<u8> {"));
+        assert!(rendered.contains("pub fn transfer(mut self, to: H160, amount: U256) -> Self {"));
+        assert!(rendered.contains("self.data.push(encode_transfer(to, amount));"));
+        assert!(rendered.contains("pub fn multicall(&mut self, data: Vec<Vec<u8>>) -> bool {"));
+
+        // Other batching functions do not qualify
+        let rendered = render_abi(&abi("batch"), &[]);
+
+        assert!(!rendered.contains("Multicall"));
+        assert!(!rendered.contains("fn encode_transfer"));
+    }
+
+    const SIGN_EXTENSION_ABI: &str = r#"[{
+        "type": "function",
+        "name": "shift",
+        "inputs": [
+            { "name": "small", "type": "int8" },
+            { "name": "odd", "type": "int24" },
+            { "name": "large", "type": "int256" }
+        ],
+        "outputs": [],
+        "stateMutability": "nonpayable"
+    }]"#;
+
+    #[test]
+    fn sign_extension() {
+        let rendered = render_abi(SIGN_EXTENSION_ABI, &[]);
+
+        assert!(rendered.contains("pub fn shift(&mut self, small: i8, odd: I256, large: I256) -> bool {"));
+        assert!(rendered.contains("pub struct I256([u8; 32]);"));
+        assert!(rendered.contains("Token::Int(sign_extend(self.into()))"));
+        assert!(rendered.contains("true => word | (ethabi::ethereum_types::U256::MAX << 128),"));
+    }
+
+    /// Encodes boundary values with the generated `sign_extend`, run with `--ignored`
+    #[test]
+    #[ignore = "needs cargo and network access"]
+    fn sign_extension_encoding() {
+        let checks = r#"
+        #[test]
+        fn sign_extension() {
+            use ethabi::ethereum_types::U256 as Word;
+
+            let cases = [
+                (i8::MIN as i128, Word::MAX - Word::from(127)),
+                (-1, Word::MAX),
+                (0, Word::zero()),
+                (i8::MAX as i128, Word::from(127)),
+                (i128::MIN, Word::MAX << 127),
+                (i128::MAX, Word::from(i128::MAX as u128)),
+            ];
+
+            for (value, word) in cases {
+                assert_eq!(sign_extend(value), word);
+                assert_eq!(ethabi::encode(&[value.tokenize()]), ethabi::encode(&[Token::Int(word)]));
+            }
+
+            let mut mock = mock::MockErc20::new();
+            mock.shift(i8::MIN, I256::from(-1i128), I256::from(i128::MIN));
+
+            let input = &mock.calls()[0].input[4..];
+            assert_eq!(input[..31], [0xff; 31]);
+            assert_eq!(input[31], 0x80);
+            assert_eq!(input[32..64], [0xff; 32]);
+            assert_eq!(input[64..80], [0xff; 16]);
+            assert_eq!(input[80], 0x80);
+            assert_eq!(input[81..], [0; 15]);
+        }"#;
+
+        let rendered = render_abi(SIGN_EXTENSION_ABI, &["--with-mock"]);
+        cargo("sign-extension", &rendered, checks, &["test", "--lib"]);
+    }
+
+    const FIXED_BYTES_ABI: &str = r#"[{
+        "type": "function",
+        "name": "tag",
+        "inputs": [
+            { "name": "kind", "type": "bytes1" },
+            { "name": "ids", "type": "bytes4[2]" },
+            { "name": "roots", "type": "bytes32[]" }
+        ],
+        "outputs": [],
+        "stateMutability": "nonpayable"
+    }]"#;
+
+    #[test]
+    fn fixed_bytes_padding() {
+        let rendered = render_abi(FIXED_BYTES_ABI, &[]);
+
+        assert!(rendered.contains("kind: FixedBytes<1>, ids: [FixedBytes<4>; 2], roots: Vec<FixedBytes<32>>"));
+        assert!(rendered.contains("Token::FixedBytes(Vec::from(self.0))"));
+    }
+
+    /// Checks padding of `bytesN` encoded by the generated code, run with `--ignored`
+    #[test]
+    #[ignore = "needs cargo and network access"]
+    fn fixed_bytes_encoding() {
+        let checks = r#"
+        #[test]
+        fn fixed_bytes_padding() {
+            let encoded = ethabi::encode(&[FixedBytes([0xab]).tokenize()]);
+            assert_eq!(encoded[0], 0xab);
+            assert_eq!(encoded[1..], [0; 31]);
+
+            // Integers of the same value are left-padded instead
+            let encoded = ethabi::encode(&[0xabu8.tokenize()]);
+            assert_eq!(encoded[..31], [0; 31]);
+            assert_eq!(encoded[31], 0xab);
+
+            let mut mock = mock::MockErc20::new();
+            mock.tag(
+                FixedBytes([0xab]),
+                [FixedBytes([0xde, 0xad, 0xbe, 0xef]), FixedBytes([0x01, 0x02, 0x03, 0x04])],
+                vec![FixedBytes([0x11; 32])],
+            );
+
+            let input = &mock.calls()[0].input[4..];
+            assert_eq!(input[..32], [&[0xabu8][..], &[0; 31]].concat());
+            assert_eq!(input[32..64], [&[0xdeu8, 0xad, 0xbe, 0xef][..], &[0; 28]].concat());
+            assert_eq!(input[64..96], [&[0x01u8, 0x02, 0x03, 0x04][..], &[0; 28]].concat());
+
+            // Offset and length of `roots` followed by its only element
+            assert_eq!(input[127], 4 * 32);
+            assert_eq!(input[159], 1);
+            assert_eq!(input[160..], [0x11; 32]);
+        }"#;
+
+        let rendered = render_abi(FIXED_BYTES_ABI, &["--with-mock"]);
+        cargo("fixed-bytes", &rendered, checks, &["test", "--lib"]);
+    }
+
+    #[test]
+    fn big_endian_integers() {
+        let rendered = render_sample("evm-erc20", &[]);
+        assert!(rendered.contains("bytes[16..].copy_from_slice(&other.to_be_bytes());"));
+        assert!(rendered.contains("Token::Uint(ethabi::ethereum_types::U256::from_big_endian(&self.0))"));
+        assert!(rendered.contains("other.to_big_endian(&mut bytes);"));
+    }
+
+    /// Checks integers converted and tokenized by the generated code against
+    /// `ethabi`, run with `--ignored`
+    #[test]
+    #[ignore = "needs cargo and network access"]
+    fn big_endian_conversions() {
+        let checks = r#"
+        #[test]
+        fn big_endian_integers() {
+            use ethabi::ethereum_types::U256 as Word;
+
+            let mut one = [0u8; 32];
+            one[31] = 1;
+
+            // Value 1 encodes as 31 zero bytes followed by 0x01 whatever the width
+            assert_eq!(ethabi::encode(&[1u64.tokenize()]), one);
+            assert_eq!(ethabi::encode(&[U256::from(1u128).tokenize()]), one);
+            assert_eq!(ethabi::encode(&[U256::from(one).tokenize()]), one);
+
+            let value = U256::from(0x0102_0304_0506_0708u128);
+            assert_eq!(value.0[24..], [1, 2, 3, 4, 5, 6, 7, 8]);
+            assert_eq!(value.tokenize(), Token::Uint(0x0102_0304_0506_0708u64.into()));
+
+            let max = ethabi::encode(&[U256::from(u128::MAX).tokenize()]);
+            assert_eq!(max[..16], [0; 16]);
+            assert_eq!(max[16..], [0xff; 16]);
+
+            // Conversions from and into `ethabi` words keep the value
+            let word = Word::from(1_000_000_007u64) << 100;
+            let converted: Word = U256::from(word).into();
+            assert_eq!(converted, word);
+            assert_eq!(I256::from(-2i128).tokenize(), Token::Int(Word::MAX - Word::one()));
+
+            // Arrays and tuples keep every word big-endian
+            let encoded = ethabi::encode(&[[U256::from(1u128), U256::from(0x0100u128)].tokenize()]);
+            assert_eq!(encoded[..32], one);
+            assert_eq!(encoded[62..], [0x01, 0x00]);
+
+            let encoded = ethabi::encode(&[vec![1u128].tokenize()]);
+            assert_eq!(encoded[64..], one);
+
+            let encoded = ethabi::encode(&[(1u64, 1u128, U256::from(1u128)).tokenize()]);
+            assert_eq!(encoded, [one; 3].concat());
+        }"#;
+
+        let rendered = render_sample("evm-erc20", &[]);
+        cargo("big-endian-integers", &rendered, checks, &["test", "--lib"]);
+    }
+
+    const STRING_ARRAYS_ABI: &str = r#"[{
+        "type": "function",
+        "name": "setTokenUris",
+        "inputs": [
+            { "name": "uris", "type": "string[]" },
+            { "name": "blobs", "type": "bytes[]" }
+        ],
+        "outputs": [],
+        "stateMutability": "nonpayable"
+    }]"#;
+
+    #[test]
+    fn string_arrays() {
+        let rendered = render_abi(STRING_ARRAYS_ABI, &[]);
+        assert!(rendered.contains("uris: Vec<String>, blobs: Vec<Vec<u8>>"));
+        assert!(rendered.contains("uris.tokenize(),\n"));
+        assert!(rendered.contains("Token::Array(blobs.into_iter().map(|item0| Token::Bytes(item0)).collect()),\n"));
+    }
+
+    /// Checks the layout of `string[]` encoded by the generated code, run with `--ignored`
+    #[test]
+    #[ignore = "needs cargo and network access"]
+    fn string_arrays_encoding() {
+        let checks = r#"
+        #[test]
+        fn string_arrays() {
+            let word = |encoded: &[u8], index: usize| {
+                ethabi::ethereum_types::U256::from_big_endian(&encoded[index * 32..(index + 1) * 32])
+                    .as_usize()
+            };
+
+            let long = "a string spanning more than a single 32 byte word";
+            let uris = ["", long, "墨 ink"];
+            let blobs = uris.iter().map(|uri| uri.as_bytes().to_vec()).collect();
+
+            let mut mock = mock::MockErc20::new();
+            mock.set_token_uris(uris.iter().map(|uri| String::from(*uri)).collect(), blobs);
+
+            let input = &mock.calls()[0].input[4..];
+            let tokens = uris.iter().map(|uri| Token::String(uri.to_string())).collect();
+            let bytes = uris.iter().map(|uri| Token::Bytes(uri.as_bytes().to_vec())).collect();
+            assert_eq!(input, ethabi::encode(&[Token::Array(tokens), Token::Array(bytes)]));
+
+            // Argument head points at the array, which holds its length followed by
+            // offsets of every element relative to the first of them
+            let encoded = &input[word(input, 0)..word(input, 1)];
+            assert_eq!(word(encoded, 0), uris.len());
+
+            let elements = &encoded[32..];
+            for (index, uri) in uris.iter().enumerate() {
+                let tail = &elements[word(elements, index)..];
+                assert_eq!(word(tail, 0), uri.len());
+                assert_eq!(&tail[32..32 + uri.len()], uri.as_bytes());
+                let padding = &tail[32 + uri.len()..32 + (uri.len() + 31) / 32 * 32];
+                assert!(padding.iter().all(|byte| *byte == 0));
+            }
+
+            // Empty element takes a single length word
+            assert_eq!(word(elements, 1) - word(elements, 0), 32);
+            assert_eq!(word(elements, 2) - word(elements, 1), 32 + 64);
+
+            // `bytes[]` shares the layout
+            assert_eq!(input[word(input, 1)..], *encoded);
+        }"#;
+
+        let rendered = render_abi(STRING_ARRAYS_ABI, &["--with-mock"]);
+        cargo("string-arrays", &rendered, checks, &["test", "--lib"]);
+    }
+
+    #[test]
+    fn evm_id_override() {
+        let rendered = render_sample("evm-erc20", &["--evm-id", "0x1F"]);
+        assert!(rendered.contains("pub const DEFAULT_EVM_ID: u8 = 0x1F;"));
+        assert!(rendered.contains("super::XVM_CONTEXT.to_vec(),"));
+        assert!(!rendered.contains("evm_id: Option<u8>"));
+
+        let rendered = render_sample("evm-erc20", &["--evm-id-args"]);
+        assert!(rendered.contains("pub fn transfer(&mut self, to: H160, amount: U256, evm_id: Option<u8>) -> bool {"));
+        assert!(rendered.contains(
+            "[&[evm_id.unwrap_or(super::DEFAULT_EVM_ID)][..], &super::XVM_CONTEXT[1..]].concat(),"
+        ));
+
+        let rendered = render_sample("evm-erc20", &["--evm-id-args", "--weight-args"]);
+        assert!(rendered.contains("amount: U256, weight_limit: WeightLimit, evm_id: Option<u8>) -> bool {"));
+        assert!(rendered.contains("weight_limit.xvm_context(\n                        evm_id.unwrap_or(super::DEFAULT_EVM_ID),"));
+
+        let rendered = render_sample("evm-erc20", &["--evm-id-args", "--xvm-version", "v3"]);
+        assert!(rendered.contains("vm_id: evm_id.unwrap_or(super::DEFAULT_EVM_ID),"));
+
+        let rendered = render_sample("evm-erc20", &["--evm-id-args", "--with-mock"]);
+        assert!(rendered.contains("_evm_id: Option<u8>) -> bool {"));
+    }
+
+    #[test]
+    fn xvm_call_errors() {
+        let rendered = render_sample("evm-erc20", &["--error-style", "result"]);
+        assert!(rendered.contains("CallFailed(XvmCallError),"));
+        assert!(rendered.contains("pub enum XvmCallError {"));
+        assert!(rendered.contains("/// Call ran out of the weight it was given, status code `2`\n        OutOfWeight,"));
+        assert!(rendered.contains("code => Self::Unknown(code),"));
+
+        for (code, variant, _) in XVM_CALL_ERRORS {
+            assert!(rendered.contains(&format!("{code} => Self::{variant},")));
+        }
+
+        // Codes are distinct and never the success status
+        let codes: Vec<_> = XVM_CALL_ERRORS.iter().map(|(code, _, _)| *code).collect();
+        assert!(!codes.contains(&0));
+        assert_eq!(codes.iter().unique().count(), codes.len());
+
+        let variants: Vec<_> = XVM_CALL_ERRORS.iter().map(|(_, variant, _)| *variant).collect();
+        assert_eq!(variants.iter().unique().count(), variants.len());
+        assert!(!variants.contains(&"Unreported") && !variants.contains(&"Unknown"));
+
+        // Only the bindings' own chain extension reports status codes
+        let rendered = render_sample(
+            "evm-erc20",
+            &["--error-style", "result", "--target", "chain-extension"],
+        );
+        assert!(rendered.contains(".map_err(|error| XvmError::CallFailed(error.0.into()))"));
+    }
+
+    #[test]
+    fn address_mode() {
+        let rendered = render_sample("evm-erc20", &[]);
+        assert!(rendered.contains("pub fn new(evm_address: H160) -> Self {"));
+        assert!(rendered.contains("Vec::from(self.evm_address.0.as_ref()),"));
+
+        let rendered = render_sample("evm-erc20", &["--address-mode", "dynamic", "--with-mock"]);
+        assert!(rendered.contains("pub struct Erc20 {\n    }"));
+        assert!(rendered.contains("pub fn new() -> Self {\n            Self {}\n        }"));
+        assert!(rendered.contains("pub fn transfer(&mut self, to: H160, amount: U256, evm_address: H160) -> bool {"));
+        assert!(rendered.contains("Vec::from(evm_address.0.as_ref()),"));
+        assert!(!rendered.contains("self.evm_address"));
+        assert!(rendered.contains("amount: U256, _evm_address: H160) -> bool {"));
+
+        let args = Args::parse_from([
+            "sumi",
+            "--format=false",
+            "--address-mode",
+            "dynamic",
+            "--with-e2e",
+            "--output-dir",
+            "bindings",
+        ]);
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("samples/evm-erc20.json");
+        let json = json::parse(&fs::read_to_string(path).unwrap()).unwrap();
+        let files = render(json, "erc20", &Renames::default(), &args).unwrap();
+
+        let e2e = &files[1].1;
+        assert!(e2e.contains("let constructor = Erc20Ref::new();"));
+        assert!(e2e.contains(", evm_address())"));
+    }
+
+    /// Builds the bindings of both address modes, run with `--ignored`
+    #[test]
+    #[ignore = "needs cargo and network access"]
+    fn address_mode_build() {
+        for mode in ["static", "dynamic"] {
+            let rendered = render_sample("evm-erc20", &["--address-mode", mode]);
+            let name = format!("address-mode-{mode}");
+            cargo(&name, &rendered, "", &["check", "--lib"]);
+        }
+    }
+
+    #[test]
+    fn license() {
+        let rendered = render_sample("evm-erc20", &[]);
+        assert!(rendered.starts_with("// SPDX-License-Identifier: UNLICENSED\n//! This file"));
+
+        let rendered = render_sample("evm-erc20", &["--license", "MIT"]);
+        assert!(rendered.starts_with("// SPDX-License-Identifier: MIT\n//! This file"));
+    }
 }